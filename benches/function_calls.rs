@@ -0,0 +1,84 @@
+use std::{hint::black_box, rc::Rc, time::Instant};
+
+use interpreter::{
+    evaluator::{environment::Environment, evaluator::Evaluator},
+    lexer::lexer::Lexer,
+    parser::{
+        ast::{expression::Expression, statement::Statement},
+        parser::Parser,
+    },
+    profiler::allocator::{allocations, start_counting, CountingAllocator},
+};
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const CALLS: usize = 10_000;
+
+/// Allocations per call this benchmark measured while functions still
+/// deep-copied their parameters and body on every lookup and call, kept to
+/// compare against.
+const BASELINE_ALLOCATIONS_PER_CALL: f64 = 42.0;
+
+const SETUP: &str = "
+    let limit = 100;
+    let clamp = fn(value, low, high) {
+        let below = value < low;
+        let above = value > high;
+        if (below) {
+            return low;
+        }
+        if (above) {
+            return high;
+        }
+        let doubled = value * 2;
+        let halved = doubled / 2;
+        halved
+    };
+";
+
+fn parse(input: &str) -> Vec<Statement> {
+    let mut parser = Parser::new(Lexer::new(input));
+    let program = parser.parse_program();
+    assert!(parser.errors.is_empty(), "benchmark source failed to parse");
+    program.statements
+}
+
+fn main() {
+    let environment = Environment::new();
+    let mut evaluator = Evaluator::new();
+
+    for statement in &parse(SETUP) {
+        evaluator
+            .eval(statement, Rc::clone(&environment))
+            .expect("setup failed");
+    }
+
+    let call = match parse("clamp(42, 0, limit);").pop() {
+        Some(Statement::Expression(call @ Expression::Call { .. })) => call,
+        _ => unreachable!("benchmark call is not a call expression"),
+    };
+
+    start_counting();
+    let allocations_before = allocations();
+    let start = Instant::now();
+
+    for _ in 0..CALLS {
+        black_box(
+            evaluator
+                .eval(&call, Rc::clone(&environment))
+                .expect("call failed"),
+        );
+    }
+
+    let elapsed = start.elapsed();
+    let allocations = allocations() - allocations_before;
+
+    println!("function_calls: {} calls in {:?}", CALLS, elapsed);
+    println!(
+        "function_calls: {:.1} allocations per call ({:.1} before sharing function bodies), {:?} per call",
+        allocations as f64 / CALLS as f64,
+        BASELINE_ALLOCATIONS_PER_CALL,
+        elapsed / CALLS as u32
+    );
+}
@@ -0,0 +1,68 @@
+use std::{hint::black_box, time::Instant};
+
+use interpreter::{
+    lexer::{lexer::Lexer, token::TokenType},
+    profiler::allocator::{allocations, start_counting, CountingAllocator},
+};
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const ROUNDS: usize = 5;
+
+const CHUNK: &str = "
+// clamps a value into [low, high]
+let clamp_NUMBER = fn(value, low, high) {
+    if (value < low) { return low; } // below the range
+    if (value > high) { return high; }
+    let doubled = value * 2 + 1234567 % 89;
+    doubled / 2 != -value == !false
+};
+clamp_NUMBER(NUMBER, 0, 100);
+";
+
+/// A generated script of roughly 4 MiB, in the spirit of machine-written
+/// input.
+fn input() -> String {
+    let mut input = String::new();
+    let mut number = 0;
+
+    while input.len() < 4 * 1024 * 1024 {
+        input.push_str(&CHUNK.replace("NUMBER", &number.to_string()));
+        number += 1;
+    }
+
+    input
+}
+
+fn main() {
+    let input = input();
+    let mut tokens = 0;
+
+    start_counting();
+    let allocations_before = allocations();
+    let start = Instant::now();
+
+    for _ in 0..ROUNDS {
+        for token in Lexer::new(&input) {
+            if token.token_type != TokenType::EOF {
+                tokens += 1;
+            }
+            black_box(token);
+        }
+    }
+
+    let elapsed = start.elapsed();
+    let allocations = allocations() - allocations_before;
+    let megabytes = (input.len() * ROUNDS) as f64 / (1024.0 * 1024.0);
+
+    println!(
+        "lexer_throughput: {} tokens from {:.1} MiB in {:?}",
+        tokens, megabytes, elapsed
+    );
+    println!(
+        "lexer_throughput: {:.1} MiB/s, {} allocations",
+        megabytes / elapsed.as_secs_f64(),
+        allocations
+    );
+}
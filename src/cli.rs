@@ -0,0 +1,1159 @@
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    rc::Rc,
+};
+
+use crate::{
+    coverage::coverage::Coverage,
+    debugger::debugger::Debugger,
+    evaluator::{environment::Environment, evaluator::Evaluator, module::FsLoader, object::Object},
+    export::{dot::to_dot, json, sexpr::to_sexpr},
+    formatter::formatter::Formatter,
+    lexer::lexer::Lexer,
+    linter::linter::Linter,
+    lsp::lsp::LanguageServer,
+    optimizer::Optimizer,
+    parser::{
+        ast::program::Program, parser::Parser, source_map::SourceMap, stream::StatementStream,
+    },
+    prelude::prelude,
+    profiler::profiler::Profiler,
+    repl::Repl,
+    typechecker::typechecker::TypeChecker,
+};
+
+const USAGE: &str = "usage:
+    interpreter [--no-prelude]            start the repl
+    interpreter run [--optimize] [--typecheck] [--profile] [--folded <out>]
+                    [--coverage] [--lcov <out>] [--module-path <dir>]...
+                    [--no-prelude] <file>
+                                          run a script, or stream one from stdin with -,
+                                          printing a profile or writing folded stacks,
+                                          printing coverage or writing an lcov report;
+                                          imports are found next to the importing file,
+                                          then in each module path;
+                                          --no-prelude leaves out the definitions
+                                          evaluated before every script
+    interpreter run --json <file>         run an AST exported with `ast --format json`
+    interpreter debug [--break <line>]... [--no-prelude] <file>
+                                          run a script in the step debugger
+    interpreter fmt [--check] <files>...  format scripts in place
+    interpreter check <files>...          warn about likely mistakes without running
+    interpreter ast [--format json|sexpr|dot] <file>
+                                          print the syntax tree of a script
+    interpreter tokens <file>             print the tokens of a script
+    interpreter lsp                       serve the Language Server Protocol on stdio";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Repl {
+        prelude: bool,
+    },
+    Run {
+        path: String,
+        optimize: bool,
+        json: bool,
+        typecheck: bool,
+        profile: bool,
+        /// Where to write the folded stacks of a profile.
+        folded: Option<String>,
+        coverage: bool,
+        /// Where to write the coverage in the lcov format.
+        lcov: Option<String>,
+        /// Directories to look for imported modules in.
+        module_paths: Vec<String>,
+        prelude: bool,
+    },
+    Debug {
+        path: String,
+        breakpoints: Vec<usize>,
+        prelude: bool,
+    },
+    Fmt {
+        paths: Vec<String>,
+        check: bool,
+    },
+    Check {
+        paths: Vec<String>,
+    },
+    Ast {
+        path: String,
+        format: AstFormat,
+    },
+    Tokens {
+        path: String,
+    },
+    Lsp,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AstFormat {
+    Json,
+    Sexpr,
+    Dot,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CliError {
+    msg: String,
+}
+
+impl CliError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        CliError { msg: msg.into() }
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n{}", self.msg, USAGE)
+    }
+}
+
+impl Command {
+    /// Parses the command line arguments, without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, CliError> {
+        let mut args = args.into_iter();
+
+        match args.next().as_deref() {
+            None => Ok(Command::Repl { prelude: true }),
+            Some("--no-prelude") => match args.next() {
+                Some(arg) => Err(CliError::new(format!("unexpected argument {}", arg))),
+                None => Ok(Command::Repl { prelude: false }),
+            },
+            Some("run") => Command::parse_run(args),
+            Some("debug") => Command::parse_debug(args),
+            Some("fmt") => Command::parse_fmt(args),
+            Some("check") => Command::parse_check(args),
+            Some("ast") => Command::parse_ast(args),
+            Some("tokens") => Command::parse_tokens(args),
+            Some("lsp") => match args.next() {
+                Some(arg) => Err(CliError::new(format!("unexpected argument {}", arg))),
+                None => Ok(Command::Lsp),
+            },
+            Some(arg) => Err(CliError::new(format!("unknown command {}", arg))),
+        }
+    }
+
+    fn parse_run(mut args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+        let mut path = None;
+        let mut optimize = false;
+        let mut json = false;
+        let mut typecheck = false;
+        let mut profile = false;
+        let mut folded = None;
+        let mut coverage = false;
+        let mut lcov = None;
+        let mut module_paths = vec![];
+        let mut prelude = true;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--optimize" => optimize = true,
+                "--json" => json = true,
+                "--typecheck" => typecheck = true,
+                "--profile" => profile = true,
+                "--folded" => match args.next() {
+                    Some(out) => folded = Some(out),
+                    None => return Err(CliError::new("missing file after --folded")),
+                },
+                "--coverage" => coverage = true,
+                "--no-prelude" => prelude = false,
+                "--lcov" => match args.next() {
+                    Some(out) => lcov = Some(out),
+                    None => return Err(CliError::new("missing file after --lcov")),
+                },
+                "--module-path" => match args.next() {
+                    Some(directory) => module_paths.push(directory),
+                    None => return Err(CliError::new("missing directory after --module-path")),
+                },
+                flag if flag.starts_with("--") => {
+                    return Err(CliError::new(format!("unknown flag {}", flag)))
+                }
+                _ if path.is_some() => return Err(CliError::new("expected a single file")),
+                _ => path = Some(arg),
+            }
+        }
+
+        // coverage needs the spans of a parsed file, which a loaded AST
+        // lacks and optimizing loses
+        if coverage || lcov.is_some() {
+            if path.as_deref() == Some("-") {
+                return Err(CliError::new("coverage needs a script file"));
+            }
+            if json {
+                return Err(CliError::new("coverage cannot be combined with --json"));
+            }
+            if optimize {
+                return Err(CliError::new("coverage cannot be combined with --optimize"));
+            }
+        }
+
+        match path {
+            Some(path) => Ok(Command::Run {
+                path,
+                optimize,
+                json,
+                typecheck,
+                profile,
+                folded,
+                coverage,
+                lcov,
+                module_paths,
+                prelude,
+            }),
+            None => Err(CliError::new("missing file to run")),
+        }
+    }
+
+    fn parse_debug(mut args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+        let mut path = None;
+        let mut breakpoints = vec![];
+        let mut prelude = true;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--no-prelude" => prelude = false,
+                "--break" => match args.next().map(|line| line.parse::<usize>()) {
+                    Some(Ok(line)) if line > 0 => breakpoints.push(line),
+                    Some(_) => return Err(CliError::new("invalid line number after --break")),
+                    None => return Err(CliError::new("missing line number after --break")),
+                },
+                flag if flag.starts_with("--") => {
+                    return Err(CliError::new(format!("unknown flag {}", flag)))
+                }
+                _ if path.is_some() => return Err(CliError::new("expected a single file")),
+                _ => path = Some(arg),
+            }
+        }
+
+        match path {
+            Some(path) => Ok(Command::Debug {
+                path,
+                breakpoints,
+                prelude,
+            }),
+            None => Err(CliError::new("missing file to debug")),
+        }
+    }
+
+    fn parse_fmt(args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+        let mut paths = vec![];
+        let mut check = false;
+
+        for arg in args {
+            match arg.as_str() {
+                "--check" => check = true,
+                flag if flag.starts_with("--") => {
+                    return Err(CliError::new(format!("unknown flag {}", flag)))
+                }
+                _ => paths.push(arg),
+            }
+        }
+
+        if paths.is_empty() {
+            return Err(CliError::new("missing files to format"));
+        }
+
+        Ok(Command::Fmt { paths, check })
+    }
+
+    fn parse_check(args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+        let mut paths = vec![];
+
+        for arg in args {
+            match arg.as_str() {
+                flag if flag.starts_with("--") => {
+                    return Err(CliError::new(format!("unknown flag {}", flag)))
+                }
+                _ => paths.push(arg),
+            }
+        }
+
+        if paths.is_empty() {
+            return Err(CliError::new("missing files to check"));
+        }
+
+        Ok(Command::Check { paths })
+    }
+
+    fn parse_ast(mut args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+        let mut path = None;
+        let mut format = AstFormat::Json;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => {
+                    format = match args.next().as_deref() {
+                        Some("json") => AstFormat::Json,
+                        Some("sexpr") => AstFormat::Sexpr,
+                        Some("dot") => AstFormat::Dot,
+                        Some(format) => {
+                            return Err(CliError::new(format!("unknown format {}", format)))
+                        }
+                        None => return Err(CliError::new("missing format after --format")),
+                    }
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(CliError::new(format!("unknown flag {}", flag)))
+                }
+                _ if path.is_some() => return Err(CliError::new("expected a single file")),
+                _ => path = Some(arg),
+            }
+        }
+
+        match path {
+            Some(path) => Ok(Command::Ast { path, format }),
+            None => Err(CliError::new("missing file to print")),
+        }
+    }
+
+    fn parse_tokens(args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+        let mut path = None;
+
+        for arg in args {
+            match arg.as_str() {
+                flag if flag.starts_with("--") => {
+                    return Err(CliError::new(format!("unknown flag {}", flag)))
+                }
+                _ if path.is_some() => return Err(CliError::new("expected a single file")),
+                _ => path = Some(arg),
+            }
+        }
+
+        match path {
+            Some(path) => Ok(Command::Tokens { path }),
+            None => Err(CliError::new("missing file to lex")),
+        }
+    }
+
+    pub fn execute(self) -> ExitCode {
+        match self {
+            Command::Repl { prelude } => {
+                println!("Monkey repl! enter empty string to exit");
+
+                let repl = Repl::new(std::io::stdin(), prelude);
+                repl.start();
+
+                ExitCode::SUCCESS
+            }
+            Command::Run {
+                path,
+                optimize,
+                json,
+                typecheck,
+                profile,
+                folded,
+                coverage,
+                lcov,
+                module_paths,
+                prelude,
+            } => run(
+                &path,
+                &RunOptions {
+                    optimize,
+                    json,
+                    check_types: typecheck,
+                    profile,
+                    folded: folded.as_deref(),
+                    coverage,
+                    lcov: lcov.as_deref(),
+                    module_paths: &module_paths,
+                    prelude,
+                },
+            ),
+            Command::Debug {
+                path,
+                breakpoints,
+                prelude,
+            } => debug(&path, breakpoints, prelude),
+            Command::Fmt { paths, check } => fmt(&paths, check),
+            Command::Check { paths } => check(&paths),
+            Command::Ast { path, format } => ast(&path, format),
+            Command::Tokens { path } => tokens(&path),
+            Command::Lsp => lsp(),
+        }
+    }
+}
+
+/// Serves the Language Server Protocol on stdin and stdout. Fails if the
+/// client exits without shutting the server down first.
+fn lsp() -> ExitCode {
+    let stdout = io::stdout();
+    let mut server = LanguageServer::new(stdout.lock());
+
+    match server.run(&mut io::stdin().lock()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Formats every file in place, or with `check` only lists the files that
+/// are not formatted. Fails if any file could not be formatted or, when
+/// checking, if any file needs formatting.
+fn fmt(paths: &[String], check: bool) -> ExitCode {
+    let formatter = Formatter::new();
+    let mut success = true;
+
+    for path in paths {
+        let input = match fs::read_to_string(path) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("failed to read {}: {}", path, err);
+                success = false;
+                continue;
+            }
+        };
+
+        let formatted = match formatter.format(&input) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                eprintln!("failed to parse {}:", path);
+                for error in errors {
+                    eprintln!("{}", error);
+                }
+                success = false;
+                continue;
+            }
+        };
+
+        if formatted == input {
+            continue;
+        }
+
+        if check {
+            println!("{}", path);
+            success = false;
+        } else if let Err(err) = fs::write(path, formatted) {
+            eprintln!("failed to write {}: {}", path, err);
+            success = false;
+        }
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Prints the warnings for every file, prefixed with its path. Fails if any
+/// file could not be checked or got warnings.
+fn check(paths: &[String]) -> ExitCode {
+    let linter = Linter::new();
+    let mut success = true;
+
+    for path in paths {
+        let input = match fs::read_to_string(path) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("failed to read {}: {}", path, err);
+                success = false;
+                continue;
+            }
+        };
+
+        match linter.check(&input) {
+            Ok(warnings) => {
+                for warning in &warnings {
+                    println!("{}{}", path, warning);
+                }
+                success &= warnings.is_empty();
+            }
+            Err(errors) => {
+                eprintln!("failed to parse {}:", path);
+                for error in errors {
+                    eprintln!("{}", error);
+                }
+                success = false;
+            }
+        }
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Prints one token per line with its location, byte range and source text.
+fn tokens(path: &str) -> ExitCode {
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for token in Lexer::new(&input) {
+        println!(
+            "{:<10} {:<12} {:<20} {:?}",
+            token.location.to_string(),
+            format!("{}..{}", token.offsets.start, token.offsets.end),
+            token.token_type.to_string(),
+            token.text(&input)
+        );
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Prints the syntax tree of a script in the requested format.
+fn ast(path: &str, format: AstFormat) -> ExitCode {
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut parser = Parser::new(Lexer::new(&input));
+    let program = parser.parse_program();
+
+    if !parser.errors.is_empty() {
+        eprintln!("Woops! parser got {} errors!", parser.errors.len());
+        for error in parser.errors {
+            eprintln!("{}", error);
+        }
+        return ExitCode::FAILURE;
+    }
+
+    let source_map = parser.source_map(&program);
+    match format {
+        AstFormat::Json => println!("{:#}", json::to_json(&program, Some(&source_map))),
+        AstFormat::Sexpr => println!("{}", to_sexpr(&program)),
+        AstFormat::Dot => println!("{}", to_dot(&program, Some(&source_map))),
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Reads a script, with the parser that read it for its source map, or with
+/// `json` a program exported by `ast --format json`.
+fn load(input: &str, json: bool) -> Option<(Program, Option<Parser<'_>>)> {
+    if json {
+        let loaded = serde_json::from_str(input)
+            .map_err(|err| err.to_string())
+            .and_then(|value| json::from_json(&value).map_err(|err| err.to_string()));
+
+        return match loaded {
+            Ok(program) => Some((program, None)),
+            Err(err) => {
+                eprintln!("failed to load AST: {}", err);
+                None
+            }
+        };
+    }
+
+    let mut parser = Parser::new(Lexer::new(input));
+    let program = parser.parse_program();
+
+    if !parser.errors.is_empty() {
+        eprintln!("Woops! parser got {} errors!", parser.errors.len());
+        for error in &parser.errors {
+            eprintln!("{}", error);
+        }
+        return None;
+    }
+
+    Some((program, Some(parser)))
+}
+
+/// Prints the type errors of `program`, if any, telling whether there were
+/// none.
+fn typecheck(checker: &mut TypeChecker, program: &Program, source_map: Option<&SourceMap>) -> bool {
+    match checker.check(program, source_map) {
+        Ok(_) => true,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            false
+        }
+    }
+}
+
+/// The flags of the `run` command.
+struct RunOptions<'a> {
+    optimize: bool,
+    json: bool,
+    check_types: bool,
+    profile: bool,
+    folded: Option<&'a str>,
+    coverage: bool,
+    lcov: Option<&'a str>,
+    module_paths: &'a [String],
+    prelude: bool,
+}
+
+/// Runs a script, profiling it when asked to print a table of the
+/// functions or write their folded stacks.
+fn run(path: &str, options: &RunOptions) -> ExitCode {
+    let mut evaluator = evaluator(path, options.module_paths);
+    let profiler = (options.profile || options.folded.is_some())
+        .then(|| Rc::new(RefCell::new(Profiler::new())));
+    if let Some(profiler) = &profiler {
+        evaluator.attach(Box::new(Rc::clone(profiler)));
+    }
+
+    let code = if path == "-" && !options.json {
+        run_stream(io::stdin().lock(), options, &mut evaluator)
+    } else {
+        run_script(path, options, &mut evaluator)
+    };
+
+    let Some(profiler) = profiler else {
+        return code;
+    };
+    let profiler = profiler.borrow();
+
+    if options.profile {
+        eprint!("{}", profiler.table());
+    }
+    if let Some(folded) = options.folded {
+        if let Err(err) = fs::write(folded, profiler.folded()) {
+            eprintln!("failed to write {}: {}", folded, err);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    code
+}
+
+fn run_script(path: &str, options: &RunOptions, evaluator: &mut Evaluator) -> ExitCode {
+    let input = if path == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input).map(|_| input)
+    } else {
+        fs::read_to_string(path)
+    };
+
+    let input = match input {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let Some((mut program, parser)) = load(&input, options.json) else {
+        return ExitCode::FAILURE;
+    };
+
+    if options.check_types {
+        let source_map = parser.as_ref().map(|parser| parser.source_map(&program));
+        if !typecheck(&mut checker(options.prelude), &program, source_map.as_ref()) {
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if options.optimize {
+        program = Optimizer::new().optimize(program);
+    }
+
+    // coverage is never combined with --json or --optimize, so there is a
+    // parser and its spans still match
+    let coverage = match &parser {
+        Some(parser) if options.coverage || options.lcov.is_some() => Some(Rc::new(RefCell::new(
+            Coverage::new(&program, &parser.source_map(&program)),
+        ))),
+        _ => None,
+    };
+    if let Some(coverage) = &coverage {
+        evaluator.attach(Box::new(Rc::clone(coverage)));
+    }
+
+    let environment = globals(evaluator, options.prelude);
+    let code = match evaluator.eval(&program, Rc::clone(&environment)) {
+        Ok(object) => {
+            println!("{}", object);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    };
+
+    let Some(coverage) = coverage else {
+        return code;
+    };
+    evaluator.detach();
+    let coverage = coverage.borrow();
+
+    if options.coverage {
+        eprint!("{}", coverage.annotate(&input));
+    }
+    if let Some(lcov) = options.lcov {
+        if let Err(err) = fs::write(lcov, coverage.lcov(path)) {
+            eprintln!("failed to write {}: {}", lcov, err);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    code
+}
+
+/// An evaluator for the script at `path`, importing from next to it and
+/// `module_paths`, in which the script importing itself back is a cycle.
+fn evaluator(path: &str, module_paths: &[String]) -> Evaluator {
+    let mut evaluator = Evaluator::new();
+    evaluator.set_loader(Box::new(loader(path, module_paths)));
+    match fs::canonicalize(path) {
+        Ok(id) if path != "-" => evaluator.set_script(id.to_string_lossy(), path),
+        _ => {}
+    }
+    evaluator
+}
+
+/// Finds the imports of the script at `path` next to it, then in
+/// `module_paths`. A streamed script imports from the working directory.
+fn loader(path: &str, module_paths: &[String]) -> FsLoader {
+    let directory = match Path::new(path).parent() {
+        Some(directory) if path != "-" => directory.to_path_buf(),
+        _ => PathBuf::new(),
+    };
+
+    let mut search_paths = vec![directory];
+    search_paths.extend(module_paths.iter().map(PathBuf::from));
+    FsLoader::new(search_paths)
+}
+
+/// The environment scripts and the modules they import start in, with the
+/// prelude unless left out.
+fn globals(evaluator: &mut Evaluator, prelude: bool) -> Rc<RefCell<Environment>> {
+    let environment = Environment::new();
+    if prelude {
+        prelude::load(&environment);
+    }
+    evaluator.set_globals(&environment.borrow());
+    environment
+}
+
+/// A checker knowing the types of the prelude unless it is left out.
+fn checker(prelude: bool) -> TypeChecker {
+    let mut checker = TypeChecker::new();
+    if prelude {
+        prelude::check(&mut checker);
+    }
+    checker
+}
+
+/// Runs a script with the debugger reading commands from stdin.
+fn debug(path: &str, breakpoints: Vec<usize>, prelude: bool) -> ExitCode {
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let Some((program, Some(parser))) = load(&input, false) else {
+        return ExitCode::FAILURE;
+    };
+
+    let debugger = Debugger::new(
+        io::stdin().lock(),
+        io::stdout(),
+        &input,
+        &parser.source_map(&program),
+        breakpoints,
+    );
+    let mut evaluator = evaluator(path, &[]);
+    evaluator.attach(Box::new(debugger));
+
+    let environment = globals(&mut evaluator, prelude);
+    match evaluator.eval(&program, environment) {
+        Ok(object) => {
+            println!("{}", object);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Evaluates each top-level statement as soon as it has been read, stopping
+/// at the first parser, type or evaluation error.
+fn run_stream(reader: impl Read, options: &RunOptions, evaluator: &mut Evaluator) -> ExitCode {
+    let mut checker = checker(options.prelude);
+    let environment = globals(evaluator, options.prelude);
+    let mut result = Object::Null;
+
+    for statement in StatementStream::new(reader) {
+        let statement = match statement {
+            Ok(statement) => statement,
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let mut program = Program {
+            statements: vec![statement],
+        };
+        if options.check_types && !typecheck(&mut checker, &program, None) {
+            return ExitCode::FAILURE;
+        }
+        if options.optimize {
+            program = Optimizer::new().optimize(program);
+        }
+
+        match evaluator.eval(&program, Rc::clone(&environment)) {
+            Ok(object @ Object::ReturnValue(_)) => {
+                result = object;
+                break;
+            }
+            Ok(object) => result = object,
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    println!("{}", result);
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AstFormat, CliError, Command};
+
+    fn parse(args: &[&str]) -> Result<Command, CliError> {
+        Command::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_repl() {
+        assert_eq!(parse(&[]), Ok(Command::Repl { prelude: true }));
+        assert_eq!(
+            parse(&["--no-prelude"]),
+            Ok(Command::Repl { prelude: false })
+        );
+    }
+
+    #[test]
+    fn test_parse_run() {
+        assert_eq!(
+            parse(&["run", "main.mk"]),
+            Ok(Command::Run {
+                path: "main.mk".to_string(),
+                optimize: false,
+                json: false,
+                typecheck: false,
+                profile: false,
+                folded: None,
+                coverage: false,
+                lcov: None,
+                module_paths: vec![],
+                prelude: true
+            })
+        );
+        assert_eq!(
+            parse(&["run", "--optimize", "main.mk"]),
+            Ok(Command::Run {
+                path: "main.mk".to_string(),
+                optimize: true,
+                json: false,
+                typecheck: false,
+                profile: false,
+                folded: None,
+                coverage: false,
+                lcov: None,
+                module_paths: vec![],
+                prelude: true
+            })
+        );
+        assert_eq!(
+            parse(&["run", "main.mk", "--optimize"]),
+            Ok(Command::Run {
+                path: "main.mk".to_string(),
+                optimize: true,
+                json: false,
+                typecheck: false,
+                profile: false,
+                folded: None,
+                coverage: false,
+                lcov: None,
+                module_paths: vec![],
+                prelude: true
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_fmt() {
+        assert_eq!(
+            parse(&["fmt", "a.mk", "b.mk"]),
+            Ok(Command::Fmt {
+                paths: vec!["a.mk".to_string(), "b.mk".to_string()],
+                check: false
+            })
+        );
+        assert_eq!(
+            parse(&["fmt", "--check", "a.mk"]),
+            Ok(Command::Fmt {
+                paths: vec!["a.mk".to_string()],
+                check: true
+            })
+        );
+        assert_eq!(
+            parse(&["fmt", "--check"]),
+            Err(CliError::new("missing files to format"))
+        );
+    }
+
+    #[test]
+    fn test_parse_run_typecheck() {
+        assert_eq!(
+            parse(&["run", "--typecheck", "-"]),
+            Ok(Command::Run {
+                path: "-".to_string(),
+                optimize: false,
+                json: false,
+                typecheck: true,
+                profile: false,
+                folded: None,
+                coverage: false,
+                lcov: None,
+                module_paths: vec![],
+                prelude: true
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_run_profile() {
+        assert_eq!(
+            parse(&["run", "--profile", "--folded", "out.folded", "main.mk"]),
+            Ok(Command::Run {
+                path: "main.mk".to_string(),
+                optimize: false,
+                json: false,
+                typecheck: false,
+                profile: true,
+                folded: Some("out.folded".to_string()),
+                coverage: false,
+                lcov: None,
+                module_paths: vec![],
+                prelude: true
+            })
+        );
+        assert_eq!(
+            parse(&["run", "main.mk", "--folded"]),
+            Err(CliError::new("missing file after --folded"))
+        );
+    }
+
+    #[test]
+    fn test_parse_run_module_paths() {
+        assert_eq!(
+            parse(&[
+                "run",
+                "--module-path",
+                "lib",
+                "main.mk",
+                "--module-path",
+                "vendor"
+            ]),
+            Ok(Command::Run {
+                path: "main.mk".to_string(),
+                optimize: false,
+                json: false,
+                typecheck: false,
+                profile: false,
+                folded: None,
+                coverage: false,
+                lcov: None,
+                module_paths: vec!["lib".to_string(), "vendor".to_string()],
+                prelude: true
+            })
+        );
+        assert_eq!(
+            parse(&["run", "main.mk", "--module-path"]),
+            Err(CliError::new("missing directory after --module-path"))
+        );
+    }
+
+    #[test]
+    fn test_parse_run_coverage() {
+        assert_eq!(
+            parse(&["run", "--coverage", "--lcov", "lcov.info", "main.mk"]),
+            Ok(Command::Run {
+                path: "main.mk".to_string(),
+                optimize: false,
+                json: false,
+                typecheck: false,
+                profile: false,
+                folded: None,
+                coverage: true,
+                lcov: Some("lcov.info".to_string()),
+                module_paths: vec![],
+                prelude: true
+            })
+        );
+        assert_eq!(
+            parse(&["run", "--coverage", "-"]),
+            Err(CliError::new("coverage needs a script file"))
+        );
+        assert_eq!(
+            parse(&["run", "--json", "--coverage", "main.json"]),
+            Err(CliError::new("coverage cannot be combined with --json"))
+        );
+        assert_eq!(
+            parse(&["run", "--lcov", "lcov.info", "--optimize", "main.mk"]),
+            Err(CliError::new("coverage cannot be combined with --optimize"))
+        );
+    }
+
+    #[test]
+    fn test_parse_run_json() {
+        assert_eq!(
+            parse(&["run", "--json", "main.json"]),
+            Ok(Command::Run {
+                path: "main.json".to_string(),
+                optimize: false,
+                json: true,
+                typecheck: false,
+                profile: false,
+                folded: None,
+                coverage: false,
+                lcov: None,
+                module_paths: vec![],
+                prelude: true
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_ast() {
+        assert_eq!(
+            parse(&["ast", "main.mk"]),
+            Ok(Command::Ast {
+                path: "main.mk".to_string(),
+                format: AstFormat::Json
+            })
+        );
+        assert_eq!(
+            parse(&["ast", "--format", "dot", "main.mk"]),
+            Ok(Command::Ast {
+                path: "main.mk".to_string(),
+                format: AstFormat::Dot
+            })
+        );
+        assert_eq!(
+            parse(&["ast", "main.mk", "--format", "sexpr"]),
+            Ok(Command::Ast {
+                path: "main.mk".to_string(),
+                format: AstFormat::Sexpr
+            })
+        );
+        assert_eq!(
+            parse(&["ast", "--format", "xml", "main.mk"]),
+            Err(CliError::new("unknown format xml"))
+        );
+        assert_eq!(
+            parse(&["ast", "main.mk", "--format"]),
+            Err(CliError::new("missing format after --format"))
+        );
+    }
+
+    #[test]
+    fn test_parse_tokens() {
+        assert_eq!(
+            parse(&["tokens", "main.mk"]),
+            Ok(Command::Tokens {
+                path: "main.mk".to_string()
+            })
+        );
+        assert_eq!(
+            parse(&["tokens"]),
+            Err(CliError::new("missing file to lex"))
+        );
+    }
+
+    #[test]
+    fn test_parse_check() {
+        assert_eq!(
+            parse(&["check", "a.mk", "b.mk"]),
+            Ok(Command::Check {
+                paths: vec!["a.mk".to_string(), "b.mk".to_string()]
+            })
+        );
+        assert_eq!(
+            parse(&["check"]),
+            Err(CliError::new("missing files to check"))
+        );
+    }
+
+    #[test]
+    fn test_parse_debug() {
+        assert_eq!(
+            parse(&["debug", "--break", "3", "main.mk", "--break", "10"]),
+            Ok(Command::Debug {
+                path: "main.mk".to_string(),
+                breakpoints: vec![3, 10],
+                prelude: true,
+            })
+        );
+        assert_eq!(
+            parse(&["debug", "main.mk", "--break"]),
+            Err(CliError::new("missing line number after --break"))
+        );
+        assert_eq!(
+            parse(&["debug", "--break", "0", "main.mk"]),
+            Err(CliError::new("invalid line number after --break"))
+        );
+        assert_eq!(
+            parse(&["debug"]),
+            Err(CliError::new("missing file to debug"))
+        );
+        assert_eq!(
+            parse(&["debug", "--no-prelude", "main.mk"]),
+            Ok(Command::Debug {
+                path: "main.mk".to_string(),
+                breakpoints: vec![],
+                prelude: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_lsp() {
+        assert_eq!(parse(&["lsp"]), Ok(Command::Lsp));
+        assert_eq!(
+            parse(&["lsp", "--stdio"]),
+            Err(CliError::new("unexpected argument --stdio"))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(&["run"]), Err(CliError::new("missing file to run")));
+        assert_eq!(
+            parse(&["run", "a.mk", "b.mk"]),
+            Err(CliError::new("expected a single file"))
+        );
+        assert_eq!(
+            parse(&["run", "--fast", "a.mk"]),
+            Err(CliError::new("unknown flag --fast"))
+        );
+        assert_eq!(parse(&["walk"]), Err(CliError::new("unknown command walk")));
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    rc::Rc,
+};
+
+use crate::{
+    evaluator::{environment::Environment, evaluator::EvaluationError, observer::EvalObserver},
+    lexer::location::Location,
+    parser::{
+        ast::{
+            expression::Expression,
+            program::Program,
+            statement::Statement,
+            visitor::{walk_expression, walk_statement, Visitor},
+        },
+        source_map::SourceMap,
+    },
+};
+
+/// How often each side of an `if` was taken.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Branch {
+    /// Where the `if` starts.
+    pub location: Location,
+    pub consequence: usize,
+    pub alternative: usize,
+}
+
+/// Records which statements and which `if` branches of a program ran.
+///
+/// Every statement and `if` with a span in the source map is known up
+/// front, so the ones that never ran are reported too. Nodes are looked up
+/// by address, like in the source map.
+pub struct Coverage {
+    /// Line and hits of every statement.
+    statements: HashMap<usize, (usize, usize)>,
+    /// Branches by the address of their condition.
+    branches: HashMap<usize, Branch>,
+}
+
+impl Coverage {
+    pub fn new(program: &Program, source_map: &SourceMap) -> Self {
+        let mut collector = Collector {
+            source_map,
+            coverage: Coverage {
+                statements: HashMap::new(),
+                branches: HashMap::new(),
+            },
+        };
+        collector.visit_program(program);
+
+        collector.coverage
+    }
+
+    /// Hits per line with a statement starting on it. A line with several
+    /// statements has the hits of the one run most.
+    pub fn lines(&self) -> BTreeMap<usize, usize> {
+        let mut lines = BTreeMap::new();
+
+        for &(line, hits) in self.statements.values() {
+            let entry = lines.entry(line).or_default();
+            *entry = hits.max(*entry);
+        }
+
+        lines
+    }
+
+    /// Every `if`, in source order.
+    pub fn branches(&self) -> Vec<&Branch> {
+        let mut branches: Vec<&Branch> = self.branches.values().collect();
+        branches.sort_by(|a, b| a.location.cmp(&b.location));
+        branches
+    }
+
+    /// The report in the lcov tracefile format, for the script at `path`.
+    pub fn lcov(&self, path: &str) -> String {
+        let lines = self.lines();
+        let branches = self.branches();
+        let mut report = format!("TN:\nSF:{}\n", path);
+
+        for (index, branch) in branches.iter().enumerate() {
+            let reached = branch.consequence + branch.alternative > 0;
+            for (side, taken) in [branch.consequence, branch.alternative]
+                .into_iter()
+                .enumerate()
+            {
+                let taken = if reached {
+                    taken.to_string()
+                } else {
+                    "-".to_string()
+                };
+                let _ = writeln!(
+                    report,
+                    "BRDA:{},{},{},{}",
+                    branch.location.line(),
+                    index,
+                    side,
+                    taken
+                );
+            }
+        }
+        let _ = writeln!(report, "BRF:{}", branches.len() * 2);
+        let _ = writeln!(report, "BRH:{}", taken(&branches));
+
+        for (line, hits) in &lines {
+            let _ = writeln!(report, "DA:{},{}", line, hits);
+        }
+        let _ = writeln!(report, "LF:{}", lines.len());
+        let _ = writeln!(
+            report,
+            "LH:{}",
+            lines.values().filter(|&&hits| hits > 0).count()
+        );
+        report.push_str("end_of_record\n");
+
+        report
+    }
+
+    /// `source` with the hits of every line with a statement in front,
+    /// `#####` for the ones that never ran, a note on the `if`s that only
+    /// ever took one side, and the totals at the end.
+    pub fn annotate(&self, source: &str) -> String {
+        let lines = self.lines();
+        let branches = self.branches();
+        let mut annotated = String::new();
+
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            let hits = match lines.get(&line) {
+                Some(0) => "#####".to_string(),
+                Some(hits) => hits.to_string(),
+                None => String::new(),
+            };
+
+            let notes: Vec<&str> = branches
+                .iter()
+                .filter(|branch| branch.location.line() == line)
+                .filter_map(|branch| match (branch.consequence, branch.alternative) {
+                    (0, 0) => None,
+                    (0, _) => Some("consequence never taken"),
+                    (_, 0) => Some("alternative never taken"),
+                    _ => None,
+                })
+                .collect();
+
+            let _ = write!(annotated, "{:>6} | {}", hits, text.trim_end());
+            if !notes.is_empty() {
+                let _ = write!(annotated, "  ← {}", notes.join(", "));
+            }
+            annotated.push('\n');
+        }
+
+        let covered = lines.values().filter(|&&hits| hits > 0).count();
+        let _ = writeln!(annotated, "lines: {}", ratio(covered, lines.len()));
+        let _ = writeln!(
+            annotated,
+            "branches: {}",
+            ratio(taken(&branches), branches.len() * 2)
+        );
+
+        annotated
+    }
+}
+
+impl EvalObserver for Coverage {
+    fn on_statement(
+        &mut self,
+        statement: &Statement,
+        _environment: &Rc<RefCell<Environment>>,
+    ) -> Result<(), EvaluationError> {
+        if let Some((_, hits)) = self
+            .statements
+            .get_mut(&(statement as *const Statement as usize))
+        {
+            *hits += 1;
+        }
+
+        Ok(())
+    }
+
+    fn on_branch(&mut self, condition: &Expression, taken: bool) {
+        if let Some(branch) = self
+            .branches
+            .get_mut(&(condition as *const Expression as usize))
+        {
+            if taken {
+                branch.consequence += 1;
+            } else {
+                branch.alternative += 1;
+            }
+        }
+    }
+}
+
+/// Finds every statement and `if` with a span.
+struct Collector<'a> {
+    source_map: &'a SourceMap<'a>,
+    coverage: Coverage,
+}
+
+impl Visitor for Collector<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Some(span) = self.source_map.statement(statement) {
+            self.coverage.statements.insert(
+                statement as *const Statement as usize,
+                (span.start.line(), 0),
+            );
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let (Expression::If { condition, .. }, Some(span)) =
+            (expression, self.source_map.expression(expression))
+        {
+            self.coverage.branches.insert(
+                condition.as_ref() as *const Expression as usize,
+                Branch {
+                    location: span.start.clone(),
+                    consequence: 0,
+                    alternative: 0,
+                },
+            );
+        }
+        walk_expression(self, expression);
+    }
+}
+
+/// Number of branch sides taken at least once.
+fn taken(branches: &[&Branch]) -> usize {
+    branches
+        .iter()
+        .map(|branch| (branch.consequence > 0) as usize + (branch.alternative > 0) as usize)
+        .sum()
+}
+
+fn ratio(covered: usize, total: usize) -> String {
+    if total == 0 {
+        return "0/0".to_string();
+    }
+
+    format!(
+        "{}/{} ({:.1}%)",
+        covered,
+        total,
+        covered as f64 * 100.0 / total as f64
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use indoc::indoc;
+
+    use crate::{
+        evaluator::{environment::Environment, evaluator::Evaluator},
+        lexer::lexer::Lexer,
+        parser::parser::Parser,
+    };
+
+    use super::Coverage;
+
+    const SCRIPT: &str = indoc! {"
+        let sign = fn(x) {
+            if (x > 0) {
+                return 1;
+            }
+            if (x == 0) { 0 } else { -1 }
+        };
+        let unused = fn() { 2 };
+        sign(5) + sign(7)
+    "};
+
+    fn cover(input: &str) -> Coverage {
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+        let source_map = parser.source_map(&program);
+
+        let coverage = Rc::new(RefCell::new(Coverage::new(&program, &source_map)));
+        let mut evaluator = Evaluator::new();
+        evaluator.attach(Box::new(Rc::clone(&coverage)));
+        evaluator.eval(&program, Environment::new()).unwrap();
+        drop(evaluator);
+
+        Rc::into_inner(coverage).unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_lcov() {
+        assert_eq!(
+            cover(SCRIPT).lcov("sign.mk"),
+            indoc! {"
+                TN:
+                SF:sign.mk
+                BRDA:2,0,0,2
+                BRDA:2,0,1,0
+                BRDA:5,1,0,-
+                BRDA:5,1,1,-
+                BRF:4
+                BRH:1
+                DA:1,1
+                DA:2,2
+                DA:3,2
+                DA:5,0
+                DA:7,1
+                DA:8,1
+                LF:6
+                LH:5
+                end_of_record
+            "}
+        );
+    }
+
+    #[test]
+    fn test_annotate() {
+        assert_eq!(
+            cover(SCRIPT).annotate(SCRIPT),
+            indoc! {"
+                     1 | let sign = fn(x) {
+                     2 |     if (x > 0) {  ← alternative never taken
+                     2 |         return 1;
+                       |     }
+                 ##### |     if (x == 0) { 0 } else { -1 }
+                       | };
+                     1 | let unused = fn() { 2 };
+                     1 | sign(5) + sign(7)
+                lines: 5/6 (83.3%)
+                branches: 1/4 (25.0%)
+            "}
+        );
+    }
+}
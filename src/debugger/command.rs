@@ -0,0 +1,103 @@
+/// A command typed at the debugger prompt.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DebugCommand {
+    /// Runs to the next statement, entering function calls.
+    Step,
+    /// Runs to the next statement, stepping over function calls.
+    Next,
+    /// Runs until the current function returns.
+    Finish,
+    /// Runs until the next breakpoint.
+    Continue,
+    Break(usize),
+    Clear(usize),
+    /// Prints the value of a name.
+    Print(String),
+    /// Prints the variables of every environment, innermost first.
+    Locals,
+    Help,
+    Quit,
+}
+
+pub const HELP: &str = "commands:
+    step, s              run to the next statement, entering calls
+    next, n              run to the next statement, stepping over calls
+    finish, f            run until the current function returns
+    continue, c          run until the next breakpoint
+    break, b <line>      pause before the statements on a line
+    clear <line>         remove the breakpoint on a line
+    print, p <name>      print the value of a name
+    locals, l            print the variables, up to the global ones
+    help, h              print this help
+    quit, q              stop the script
+an empty line repeats the last command";
+
+impl DebugCommand {
+    pub fn parse(input: &str) -> Result<DebugCommand, String> {
+        let mut words = input.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let argument = words.next();
+
+        if words.next().is_some() {
+            return Err(format!("too many arguments for {}", command));
+        }
+
+        let line = |argument: Option<&str>| match argument.map(str::parse::<usize>) {
+            Some(Ok(line)) if line > 0 => Ok(line),
+            Some(_) => Err(format!("invalid line number {}", argument.unwrap())),
+            None => Err(format!("{} needs a line number", command)),
+        };
+
+        let command = match (command, argument) {
+            ("step" | "s", None) => DebugCommand::Step,
+            ("next" | "n", None) => DebugCommand::Next,
+            ("finish" | "f", None) => DebugCommand::Finish,
+            ("continue" | "c", None) => DebugCommand::Continue,
+            ("locals" | "l", None) => DebugCommand::Locals,
+            ("help" | "h", None) => DebugCommand::Help,
+            ("quit" | "q", None) => DebugCommand::Quit,
+            ("break" | "b", argument) => DebugCommand::Break(line(argument)?),
+            ("clear", argument) => DebugCommand::Clear(line(argument)?),
+            ("print" | "p", Some(name)) => DebugCommand::Print(name.to_string()),
+            ("print" | "p", None) => return Err(format!("{} needs a name", command)),
+            (
+                "step" | "s" | "next" | "n" | "finish" | "f" | "continue" | "c" | "locals" | "l"
+                | "help" | "h" | "quit" | "q",
+                Some(_),
+            ) => return Err(format!("{} takes no arguments", command)),
+            (command, _) => return Err(format!("unknown command {}, try help", command)),
+        };
+
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DebugCommand;
+
+    #[test]
+    fn test_parse() {
+        let tests = vec![
+            ("step", Ok(DebugCommand::Step)),
+            (" n ", Ok(DebugCommand::Next)),
+            ("finish", Ok(DebugCommand::Finish)),
+            ("c", Ok(DebugCommand::Continue)),
+            ("b 12", Ok(DebugCommand::Break(12))),
+            ("clear 3", Ok(DebugCommand::Clear(3))),
+            ("p größe", Ok(DebugCommand::Print("größe".to_string()))),
+            ("locals", Ok(DebugCommand::Locals)),
+            ("break", Err("break needs a line number".to_string())),
+            ("break 0", Err("invalid line number 0".to_string())),
+            ("b x", Err("invalid line number x".to_string())),
+            ("print", Err("print needs a name".to_string())),
+            ("step 2", Err("step takes no arguments".to_string())),
+            ("p a b", Err("too many arguments for p".to_string())),
+            ("run", Err("unknown command run, try help".to_string())),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(DebugCommand::parse(input), expected, "parsing {:?}", input);
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    io::{BufRead, Write},
+    rc::Rc,
+};
+
+use crate::{
+    evaluator::{
+        environment::Environment, evaluator::EvaluationError, object::Object,
+        observer::EvalObserver,
+    },
+    parser::{
+        ast::{
+            expression::Expression,
+            statement::Statement,
+            visitor::{walk_statement, Visitor},
+        },
+        source_map::SourceMap,
+    },
+};
+
+use super::command::{DebugCommand, HELP};
+
+const PROMPT: &str = "(debug) ";
+
+/// When to pause next, besides at breakpoints.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Mode {
+    Step,
+    /// Pauses once the call depth is at most the one given.
+    Next(usize),
+    /// Pauses once the call depth is below the one given.
+    Finish(usize),
+    Continue,
+    /// The input is closed, so the script runs to the end.
+    Detached,
+}
+
+/// An interactive debugger, reading commands from `input` and writing to
+/// `output` whenever the evaluation pauses.
+///
+/// Breakpoints are on lines, and pause before every statement starting on
+/// them. Statements without a span in `source_map`, like ones in functions
+/// defined by earlier input, can be stepped through but not broken on.
+pub struct Debugger<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    source: String,
+    /// Start line of every statement with a span, by its address.
+    lines: HashMap<usize, usize>,
+    breakpoints: BTreeSet<usize>,
+    mode: Mode,
+    /// Number of function calls in progress.
+    depth: usize,
+    last: Option<DebugCommand>,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    /// Pauses before the first statement, or with breakpoints at the first
+    /// one hit.
+    pub fn new(
+        input: R,
+        output: W,
+        source: &str,
+        source_map: &SourceMap,
+        breakpoints: impl IntoIterator<Item = usize>,
+    ) -> Self {
+        let breakpoints: BTreeSet<usize> = breakpoints.into_iter().collect();
+        let mode = if breakpoints.is_empty() {
+            Mode::Step
+        } else {
+            Mode::Continue
+        };
+
+        Debugger {
+            input,
+            output,
+            source: source.to_string(),
+            lines: Lines::collect(source_map),
+            breakpoints,
+            mode,
+            depth: 0,
+            last: None,
+        }
+    }
+
+    fn should_pause(&self, line: Option<usize>) -> bool {
+        if self.mode == Mode::Detached {
+            return false;
+        }
+        if line.is_some_and(|line| self.breakpoints.contains(&line)) {
+            return true;
+        }
+
+        match self.mode {
+            Mode::Step => true,
+            Mode::Next(depth) => self.depth <= depth,
+            Mode::Finish(depth) => self.depth < depth,
+            Mode::Continue | Mode::Detached => false,
+        }
+    }
+
+    fn show(&mut self, statement: &Statement, line: Option<usize>) {
+        let text = match line.and_then(|line| self.source.lines().nth(line - 1)) {
+            Some(text) => format!("{:>4} | {}", line.unwrap_or_default(), text.trim_end()),
+            None => format!("     | {}", statement),
+        };
+        self.say(&text);
+    }
+
+    /// Runs commands until one resumes the evaluation.
+    fn prompt(&mut self, environment: &Rc<RefCell<Environment>>) -> Result<(), EvaluationError> {
+        loop {
+            write!(self.output, "{}", PROMPT).expect("failed to write debugger output");
+            self.output
+                .flush()
+                .expect("failed to flush debugger output");
+
+            let mut input = String::new();
+            let read = self
+                .input
+                .read_line(&mut input)
+                .expect("failed to read debugger input");
+            if read == 0 {
+                self.mode = Mode::Detached;
+                return Ok(());
+            }
+
+            let command = match (input.trim(), &self.last) {
+                ("", Some(last)) => last.clone(),
+                ("", None) => continue,
+                (input, _) => match DebugCommand::parse(input) {
+                    Ok(command) => command,
+                    Err(msg) => {
+                        self.say(&msg);
+                        continue;
+                    }
+                },
+            };
+            self.last = Some(command.clone());
+
+            match command {
+                DebugCommand::Step => self.mode = Mode::Step,
+                DebugCommand::Next => self.mode = Mode::Next(self.depth),
+                DebugCommand::Finish => self.mode = Mode::Finish(self.depth),
+                DebugCommand::Continue => self.mode = Mode::Continue,
+                DebugCommand::Quit => return Err(EvaluationError::new("stopped by the debugger")),
+                DebugCommand::Break(line) => {
+                    self.breakpoints.insert(line);
+                    self.say(&format!("breakpoint at line {}", line));
+                    continue;
+                }
+                DebugCommand::Clear(line) => {
+                    let msg = if self.breakpoints.remove(&line) {
+                        format!("cleared the breakpoint at line {}", line)
+                    } else {
+                        format!("no breakpoint at line {}", line)
+                    };
+                    self.say(&msg);
+                    continue;
+                }
+                DebugCommand::Print(name) => {
+                    let msg = match environment.borrow().get(&name) {
+                        Some(value) => format!("{} = {}", name, value.summary()),
+                        None => format!("identifier not found: {}", name),
+                    };
+                    self.say(&msg);
+                    continue;
+                }
+                DebugCommand::Locals => {
+                    self.locals(environment);
+                    continue;
+                }
+                DebugCommand::Help => {
+                    self.say(HELP);
+                    continue;
+                }
+            }
+
+            return Ok(());
+        }
+    }
+
+    /// Prints every environment up the outer chain, numbered from the
+    /// current one. Each closure wraps the environment it was created in
+    /// with one of its own, which stays empty and is left out.
+    fn locals(&mut self, environment: &Rc<RefCell<Environment>>) {
+        let mut lines = vec![];
+        let mut frame = Some(Rc::clone(environment));
+        let mut index = 0;
+
+        while let Some(environment) = frame {
+            let environment = environment.borrow();
+            let global = environment.outer().is_none();
+            frame = environment.outer();
+
+            if !global && environment.bindings().is_empty() {
+                continue;
+            }
+
+            lines.push(format!(
+                "frame {}{}:",
+                index,
+                if global { " (global)" } else { "" }
+            ));
+            for (name, value) in environment.bindings() {
+                lines.push(format!("    {} = {}", name, value.summary()));
+            }
+            index += 1;
+        }
+
+        self.say(&lines.join("\n"));
+    }
+
+    fn say(&mut self, text: &str) {
+        writeln!(self.output, "{}", text).expect("failed to write debugger output");
+    }
+}
+
+impl<R: BufRead, W: Write> EvalObserver for Debugger<R, W> {
+    fn on_statement(
+        &mut self,
+        statement: &Statement,
+        environment: &Rc<RefCell<Environment>>,
+    ) -> Result<(), EvaluationError> {
+        let line = self
+            .lines
+            .get(&(statement as *const Statement as usize))
+            .copied();
+
+        if !self.should_pause(line) {
+            return Ok(());
+        }
+
+        self.show(statement, line);
+        self.prompt(environment)
+    }
+
+    fn on_call(&mut self, _callee: &Expression, _function: &Object, _arguments: &[Object]) {
+        self.depth += 1;
+    }
+
+    fn on_return(
+        &mut self,
+        _callee: &Expression,
+        _function: &Object,
+        _result: Result<&Object, &EvaluationError>,
+    ) {
+        self.depth -= 1;
+    }
+}
+
+/// Finds the start line of every statement with a span.
+struct Lines<'a> {
+    source_map: &'a SourceMap<'a>,
+    lines: HashMap<usize, usize>,
+}
+
+impl Lines<'_> {
+    fn collect(source_map: &SourceMap) -> HashMap<usize, usize> {
+        let mut lines = Lines {
+            source_map,
+            lines: HashMap::new(),
+        };
+        lines.visit_program(source_map.program());
+
+        lines.lines
+    }
+}
+
+impl Visitor for Lines<'_> {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Some(span) = self.source_map.statement(statement) {
+            self.lines
+                .insert(statement as *const Statement as usize, span.start.line());
+        }
+        walk_statement(self, statement);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        io::{Cursor, Write},
+        rc::Rc,
+    };
+
+    use indoc::indoc;
+
+    use crate::{
+        evaluator::{environment::Environment, evaluator::Evaluator, object::Object},
+        lexer::lexer::Lexer,
+        parser::parser::Parser,
+    };
+
+    use super::Debugger;
+
+    const SCRIPT: &str = indoc! {"
+        let double = fn(x) {
+            let y = x * 2;
+            y
+        };
+        let a = double(1);
+        let b = double(a);
+        a + b
+    "};
+
+    /// Output shared with the test once the debugger is attached.
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Runs `SCRIPT` under the debugger with the given commands, returning
+    /// the result and the transcript without the prompts.
+    fn debug(commands: &str, breakpoints: Vec<usize>) -> (Result<Object, String>, String) {
+        let mut parser = Parser::new(Lexer::new(SCRIPT));
+        let program = parser.parse_program();
+        let source_map = parser.source_map(&program);
+
+        let output = Output::default();
+        let debugger = Debugger::new(
+            Cursor::new(commands.to_string()),
+            output.clone(),
+            SCRIPT,
+            &source_map,
+            breakpoints,
+        );
+
+        let mut evaluator = Evaluator::new();
+        evaluator.attach(Box::new(debugger));
+        let result = evaluator
+            .eval(&program, Environment::new())
+            .map_err(|err| err.to_string());
+
+        let transcript = String::from_utf8(output.0.take()).unwrap();
+        (result, transcript.replace("(debug) ", ""))
+    }
+
+    #[test]
+    fn test_step_and_next() {
+        let (result, transcript) = debug("s\ns\nn\n\nstep\nc\n", vec![]);
+
+        assert_eq!(result, Ok(Object::Integer(6)));
+        assert_eq!(
+            transcript.lines().collect::<Vec<&str>>(),
+            vec![
+                "   1 | let double = fn(x) {",
+                "   5 | let a = double(1);",
+                "   2 |     let y = x * 2;",
+                "   3 |     y",
+                "   6 | let b = double(a);",
+                "   2 |     let y = x * 2;",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_breakpoints_and_finish() {
+        let (result, transcript) = debug("finish\nclear 2\nb 7\nc\nq\n", vec![2]);
+
+        assert_eq!(result, Err("stopped by the debugger".to_string()));
+        assert_eq!(
+            transcript,
+            indoc! {"
+                   2 |     let y = x * 2;
+                   6 | let b = double(a);
+                cleared the breakpoint at line 2
+                breakpoint at line 7
+                   7 | a + b
+            "}
+        );
+    }
+
+    #[test]
+    fn test_inspect_variables() {
+        let (_, transcript) = debug("b 3\nc\np x\np z\nlocals\n", vec![]);
+
+        assert_eq!(
+            transcript,
+            indoc! {"
+                   1 | let double = fn(x) {
+                breakpoint at line 3
+                   3 |     y
+                x = 1
+                identifier not found: z
+                frame 0:
+                    x = 1
+                    y = 2
+                frame 1 (global):
+                    double = fn(x)
+            "}
+        );
+    }
+
+    #[test]
+    fn test_input_closed() {
+        let (result, transcript) = debug("bogus\n", vec![]);
+
+        assert_eq!(result, Ok(Object::Integer(6)));
+        assert_eq!(
+            transcript,
+            "   1 | let double = fn(x) {\nunknown command bogus, try help\n"
+        );
+    }
+}
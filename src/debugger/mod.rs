@@ -0,0 +1,3 @@
+pub mod command;
+#[allow(clippy::module_inception)]
+pub mod debugger;
@@ -0,0 +1,739 @@
+use std::{cmp::Ordering, fmt::Display, rc::Rc, sync::Arc};
+
+use crate::parser::ast::expression::Expression;
+
+use super::{
+    evaluator::{EvaluationError, Evaluator},
+    object::Object,
+};
+
+type Builtin = fn(&mut Call, Vec<Object>) -> Result<Object, EvaluationError>;
+
+/// Longest string in bytes that `repeat` and `pad_left` build, so a huge
+/// count fails the call instead of running out of memory.
+const MAX_STRING_LEN: usize = 1 << 30;
+
+/// Every builtin with the number of arguments it takes.
+const BUILTINS: &[(&str, usize, Builtin)] = &[
+    ("len", 1, len),
+    ("first", 1, first),
+    ("last", 1, last),
+    ("rest", 1, rest),
+    ("push", 2, push),
+    ("map", 2, map),
+    ("filter", 2, filter),
+    ("reduce", 3, reduce),
+    ("sort_by", 2, sort_by),
+    ("group_by", 2, group_by),
+    ("zip", 2, zip),
+    ("flatten", 1, flatten),
+    ("uniq", 1, uniq),
+    ("any", 2, any),
+    ("all", 2, all),
+    ("split", 2, split),
+    ("join", 2, join),
+    ("trim", 1, trim),
+    ("upper", 1, upper),
+    ("lower", 1, lower),
+    ("contains", 2, contains),
+    ("starts_with", 2, starts_with),
+    ("replace", 3, replace),
+    ("substring", 3, substring),
+    ("chars", 1, chars),
+    ("repeat", 2, repeat),
+    ("pad_left", 3, pad_left),
+    ("to_string", 1, to_string),
+    ("to_int", 1, to_int),
+];
+
+/// The builtin called `name`, if there is one.
+pub fn lookup(name: &str) -> Option<Object> {
+    BUILTINS
+        .iter()
+        .find(|(builtin, ..)| *builtin == name)
+        .map(|(name, ..)| Object::Builtin(name))
+}
+
+/// The number of arguments the builtin called `name` takes.
+pub fn arity(name: &str) -> Option<usize> {
+    BUILTINS
+        .iter()
+        .find(|(builtin, ..)| *builtin == name)
+        .map(|(_, arity, _)| *arity)
+}
+
+/// A call of a builtin, through which it calls back into Monkey functions.
+pub struct Call<'a> {
+    evaluator: &'a mut Evaluator,
+    name: &'static str,
+    callee: &'a Expression,
+    arguments: &'a [Expression],
+}
+
+impl Call<'_> {
+    /// Calls the function passed as the argument at `position`, so that
+    /// observers see the call as one of that argument.
+    fn call_back(
+        &mut self,
+        position: usize,
+        function: &Object,
+        arguments: Vec<Object>,
+    ) -> Result<Object, EvaluationError> {
+        let callee = self.arguments.get(position).unwrap_or(self.callee);
+        self.evaluator.call(callee, function, arguments)
+    }
+
+    fn error(&self, msg: impl Display) -> EvaluationError {
+        EvaluationError::new(format!("{}: {}", self.name, msg))
+    }
+
+    fn array(&self, object: &Object) -> Result<Rc<[Object]>, EvaluationError> {
+        match object {
+            Object::Array(elements) => Ok(Rc::clone(elements)),
+            object => Err(self.error(format!("expected an array, got {}", object.summary()))),
+        }
+    }
+
+    fn string(&self, object: &Object) -> Result<Rc<str>, EvaluationError> {
+        match object {
+            Object::String(string) => Ok(Rc::clone(string)),
+            object => Err(self.error(format!("expected a string, got {}", object.summary()))),
+        }
+    }
+
+    fn integer(&self, object: &Object) -> Result<i64, EvaluationError> {
+        match object {
+            Object::Integer(integer) => Ok(*integer),
+            object => Err(self.error(format!("expected an int, got {}", object.summary()))),
+        }
+    }
+
+    fn truthy(&self, object: &Object) -> bool {
+        self.evaluator.is_truthy(object)
+    }
+}
+
+/// Calls the builtin `name` with arguments evaluated from `arguments`.
+pub fn apply(
+    evaluator: &mut Evaluator,
+    name: &'static str,
+    callee: &Expression,
+    arguments: &[Expression],
+    values: Vec<Object>,
+) -> Result<Object, EvaluationError> {
+    let (_, arity, builtin) = BUILTINS
+        .iter()
+        .find(|(builtin, ..)| *builtin == name)
+        .expect("builtin objects are only made by lookup");
+
+    if values.len() != *arity {
+        return Err(EvaluationError::new(format!(
+            "wrong number of arguments to {}: got {}, but it wants {}",
+            name,
+            values.len(),
+            arity
+        )));
+    }
+
+    let mut call = Call {
+        evaluator,
+        name,
+        callee,
+        arguments,
+    };
+    builtin(&mut call, values)
+}
+
+/// The number of elements of an array, or of Unicode scalar values of a
+/// string.
+fn len(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let len = match &arguments[0] {
+        Object::String(string) => string.chars().count(),
+        object => call.array(object)?.len(),
+    };
+    Ok(Object::Integer(len as i64))
+}
+
+fn first(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    Ok(elements.first().cloned().unwrap_or(Object::Null))
+}
+
+fn last(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    Ok(elements.last().cloned().unwrap_or(Object::Null))
+}
+
+fn rest(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    match elements.split_first() {
+        Some((_, rest)) => Ok(Object::array(rest)),
+        None => Ok(Object::Null),
+    }
+}
+
+fn push(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    let mut elements = elements.to_vec();
+    elements.push(arguments[1].clone());
+    Ok(Object::array(elements))
+}
+
+fn map(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    let mapped = elements
+        .iter()
+        .map(|element| call.call_back(1, &arguments[1], vec![element.clone()]))
+        .collect::<Result<Vec<Object>, _>>()?;
+    Ok(Object::array(mapped))
+}
+
+fn filter(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    let mut kept = vec![];
+    for element in elements.iter() {
+        let keep = call.call_back(1, &arguments[1], vec![element.clone()])?;
+        if call.truthy(&keep) {
+            kept.push(element.clone());
+        }
+    }
+    Ok(Object::array(kept))
+}
+
+fn reduce(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    let mut accumulator = arguments[1].clone();
+    for element in elements.iter() {
+        accumulator = call.call_back(2, &arguments[2], vec![accumulator, element.clone()])?;
+    }
+    Ok(accumulator)
+}
+
+/// Sorts by keys computed once per element. The sort is stable, and the
+/// keys have to be all ints, all bools or all strings.
+fn sort_by(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    let mut keyed = elements
+        .iter()
+        .map(|element| {
+            let key = call.call_back(1, &arguments[1], vec![element.clone()])?;
+            Ok((key, element.clone()))
+        })
+        .collect::<Result<Vec<(Object, Object)>, EvaluationError>>()?;
+
+    for pair in keyed.windows(2) {
+        if compare(&pair[0].0, &pair[1].0).is_none() {
+            return Err(call.error(format!(
+                "cannot compare {} and {}",
+                pair[0].0.summary(),
+                pair[1].0.summary()
+            )));
+        }
+    }
+
+    keyed.sort_by(|(a, _), (b, _)| compare(a, b).unwrap_or(Ordering::Equal));
+    Ok(Object::array(
+        keyed
+            .into_iter()
+            .map(|(_, element)| element)
+            .collect::<Vec<Object>>(),
+    ))
+}
+
+fn compare(a: &Object, b: &Object) -> Option<Ordering> {
+    match (a, b) {
+        (Object::Integer(a), Object::Integer(b)) => Some(a.cmp(b)),
+        (Object::Boolean(a), Object::Boolean(b)) => Some(a.cmp(b)),
+        (Object::String(a), Object::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Groups the elements into `[key, elements]` pairs, in the order each key
+/// first appears.
+fn group_by(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    let mut groups: Vec<(Object, Vec<Object>)> = vec![];
+    for element in elements.iter() {
+        let key = call.call_back(1, &arguments[1], vec![element.clone()])?;
+        match groups.iter_mut().find(|(other, _)| same(other, &key)) {
+            Some((_, group)) => group.push(element.clone()),
+            None => groups.push((key, vec![element.clone()])),
+        }
+    }
+
+    Ok(Object::array(
+        groups
+            .into_iter()
+            .map(|(key, group)| Object::array(vec![key, Object::array(group)]))
+            .collect::<Vec<Object>>(),
+    ))
+}
+
+/// Pairs up the elements, up to the length of the shorter array.
+fn zip(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let a = call.array(&arguments[0])?;
+    let b = call.array(&arguments[1])?;
+    Ok(Object::array(
+        a.iter()
+            .zip(b.iter())
+            .map(|(a, b)| Object::array(vec![a.clone(), b.clone()]))
+            .collect::<Vec<Object>>(),
+    ))
+}
+
+/// Flattens one level of nesting.
+fn flatten(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    let mut flat = vec![];
+    for element in elements.iter() {
+        flat.extend(call.array(element)?.iter().cloned());
+    }
+    Ok(Object::array(flat))
+}
+
+/// Drops the elements equal to an earlier one.
+fn uniq(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    let mut unique: Vec<Object> = vec![];
+    for element in elements.iter() {
+        if !unique.iter().any(|other| same(other, element)) {
+            unique.push(element.clone());
+        }
+    }
+    Ok(Object::array(unique))
+}
+
+fn any(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    for element in elements.iter() {
+        let matched = call.call_back(1, &arguments[1], vec![element.clone()])?;
+        if call.truthy(&matched) {
+            return Ok(Object::Boolean(true));
+        }
+    }
+    Ok(Object::Boolean(false))
+}
+
+fn all(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    for element in elements.iter() {
+        let matched = call.call_back(1, &arguments[1], vec![element.clone()])?;
+        if !call.truthy(&matched) {
+            return Ok(Object::Boolean(false));
+        }
+    }
+    Ok(Object::Boolean(true))
+}
+
+fn split(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let string = call.string(&arguments[0])?;
+    let separator = call.string(&arguments[1])?;
+    if separator.is_empty() {
+        return Err(call.error("the separator is empty, use chars instead"));
+    }
+    Ok(Object::array(
+        string
+            .split(&*separator)
+            .map(Object::string)
+            .collect::<Vec<Object>>(),
+    ))
+}
+
+fn join(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let elements = call.array(&arguments[0])?;
+    let separator = call.string(&arguments[1])?;
+    let strings = elements
+        .iter()
+        .map(|element| call.string(element))
+        .collect::<Result<Vec<Rc<str>>, _>>()?;
+    Ok(Object::string(strings.join(&separator)))
+}
+
+fn trim(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    Ok(Object::string(call.string(&arguments[0])?.trim()))
+}
+
+fn upper(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    Ok(Object::string(call.string(&arguments[0])?.to_uppercase()))
+}
+
+fn lower(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    Ok(Object::string(call.string(&arguments[0])?.to_lowercase()))
+}
+
+fn contains(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let string = call.string(&arguments[0])?;
+    let part = call.string(&arguments[1])?;
+    Ok(Object::Boolean(string.contains(&*part)))
+}
+
+fn starts_with(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let string = call.string(&arguments[0])?;
+    let prefix = call.string(&arguments[1])?;
+    Ok(Object::Boolean(string.starts_with(&*prefix)))
+}
+
+/// Replaces every occurrence of a non-empty pattern.
+fn replace(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let string = call.string(&arguments[0])?;
+    let pattern = call.string(&arguments[1])?;
+    let replacement = call.string(&arguments[2])?;
+    if pattern.is_empty() {
+        return Err(call.error("the pattern is empty"));
+    }
+    Ok(Object::string(string.replace(&*pattern, &replacement)))
+}
+
+/// The Unicode scalar values from `start` up to, but not including, `end`.
+fn substring(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let string = call.string(&arguments[0])?;
+    let start = call.integer(&arguments[1])?;
+    let end = call.integer(&arguments[2])?;
+    let length = string.chars().count() as i64;
+
+    if start < 0 || end < 0 {
+        return Err(call.error(format!("negative bound {}", start.min(end))));
+    }
+    if start > end {
+        return Err(call.error(format!("start {} is after end {}", start, end)));
+    }
+    if end > length {
+        return Err(call.error(format!("end {} is past the length {}", end, length)));
+    }
+
+    Ok(Object::string(
+        string
+            .chars()
+            .skip(start as usize)
+            .take((end - start) as usize)
+            .collect::<String>(),
+    ))
+}
+
+/// The Unicode scalar values of a string, each as a string of its own.
+fn chars(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let string = call.string(&arguments[0])?;
+    Ok(Object::array(
+        string
+            .chars()
+            .map(|ch| Object::string(ch.to_string()))
+            .collect::<Vec<Object>>(),
+    ))
+}
+
+fn repeat(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let string = call.string(&arguments[0])?;
+    let count = call.integer(&arguments[1])?;
+    if count < 0 {
+        return Err(call.error(format!("negative count {}", count)));
+    }
+    if repeated_len(string.len(), count as usize).is_none() {
+        return Err(call.error(format!("the result of {} copies is too long", count)));
+    }
+    Ok(Object::string(string.repeat(count as usize)))
+}
+
+/// Pads the start of a string with a character until it is `width` long.
+fn pad_left(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let string = call.string(&arguments[0])?;
+    let width = call.integer(&arguments[1])?;
+    let padding = call.string(&arguments[2])?;
+
+    let mut pad = padding.chars();
+    let (Some(pad), None) = (pad.next(), pad.next()) else {
+        return Err(call.error(format!(
+            "the padding must be one character, got {}",
+            arguments[2].summary()
+        )));
+    };
+
+    let missing = (width.max(0) as usize).saturating_sub(string.chars().count());
+    if repeated_len(pad.len_utf8(), missing).is_none() {
+        return Err(call.error(format!("the width {} is too long", width)));
+    }
+    Ok(Object::string(format!(
+        "{}{}",
+        pad.to_string().repeat(missing),
+        string
+    )))
+}
+
+/// The byte length of `count` copies of `len` bytes, unless it is more than
+/// `MAX_STRING_LEN`.
+fn repeated_len(len: usize, count: usize) -> Option<usize> {
+    len.checked_mul(count)
+        .filter(|total| *total <= MAX_STRING_LEN)
+}
+
+/// Writes a value the way it is printed.
+fn to_string(_call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    Ok(Object::string(arguments[0].to_string()))
+}
+
+/// Reads a decimal integer, with an optional sign.
+fn to_int(call: &mut Call, arguments: Vec<Object>) -> Result<Object, EvaluationError> {
+    let string = call.string(&arguments[0])?;
+    string.parse::<i64>().map(Object::Integer).map_err(|_| {
+        call.error(format!(
+            "cannot convert {} to an int",
+            arguments[0].summary()
+        ))
+    })
+}
+
+/// Whether two values count as equal for `uniq` and `group_by`: arrays by
+/// their elements, functions and modules only when they are the same one.
+fn same(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        (Object::Array(a), Object::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same(a, b))
+        }
+        (
+            Object::Function {
+                body: a,
+                environment: a_env,
+                ..
+            },
+            Object::Function {
+                body: b,
+                environment: b_env,
+                ..
+            },
+        ) => Arc::ptr_eq(a, b) && Rc::ptr_eq(a_env, b_env),
+        (Object::Module { exports: a, .. }, Object::Module { exports: b, .. }) => Rc::ptr_eq(a, b),
+        (Object::Function { .. }, _) | (Object::Module { .. }, _) => false,
+        (a, b) => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        evaluator::{environment::Environment, evaluator::Evaluator},
+        lexer::lexer::Lexer,
+        parser::parser::Parser,
+    };
+
+    fn evaluate(input: &str) -> Result<String, String> {
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        Evaluator::new()
+            .eval(&program, Environment::new())
+            .map(|value| value.to_string())
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn test_builtins() {
+        let tests = vec![
+            ("len([1, 2, 3]) + len([])", "3"),
+            ("[first([1, 2]), last([1, 2]), first([])]", "[1, 2, null]"),
+            (
+                "[rest([1, 2, 3]), rest([1]), rest([])]",
+                "[[2, 3], [], null]",
+            ),
+            ("let a = [1]; [push(a, 2), a]", "[[1, 2], [1]]"),
+            ("map([1, 2, 3], fn(x) { x * x })", "[1, 4, 9]"),
+            ("filter([1, 2, 3, 4], fn(x) { x % 2 == 0 })", "[2, 4]"),
+            (
+                "reduce([1, 2, 3], 10, fn(acc, x) { acc * 10 + x })",
+                "10123",
+            ),
+            ("sort_by([3, -1, 2], fn(x) { x })", "[-1, 2, 3]"),
+            ("sort_by([true, false], fn(x) { x })", "[false, true]"),
+            (
+                "group_by([1, 2, 3, 4, 5], fn(x) { x % 3 })",
+                "[[1, [1, 4]], [2, [2, 5]], [0, [3]]]",
+            ),
+            ("zip([1, 2, 3], [true, false])", "[[1, true], [2, false]]"),
+            ("flatten([[1], [], [2, [3]]])", "[1, 2, [3]]"),
+            ("uniq([1, 2, 1, [1], [1], null, null])", "[1, 2, [1], null]"),
+            (
+                "[any([1, 2], fn(x) { x > 1 }), any([], fn(x) { x })]",
+                "[true, false]",
+            ),
+            (
+                "[all([1, 2], fn(x) { x > 1 }), all([], fn(x) { x })]",
+                "[false, true]",
+            ),
+            ("map([[1], [2, 3]], len)", "[1, 2]"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                evaluate(input),
+                Ok(expected.to_string()),
+                "evaluating {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_strings() {
+        let tests = vec![
+            (r#"len("größe") + len("")"#, "5"),
+            (r#"split("a,b,,c", ",")"#, "[a, b, , c]"),
+            (r#"join(split("a b c", " "), "-")"#, "a-b-c"),
+            (r#"join([], ",")"#, ""),
+            (r#"trim("  a b \n")"#, "a b"),
+            (r#"upper("straße") + lower("ÀB")"#, "STRASSEàb"),
+            (
+                r#"[contains("größe", "öß"), contains("a", "b")]"#,
+                "[true, false]",
+            ),
+            (
+                r#"[starts_with("größe", "gr"), starts_with("", "a")]"#,
+                "[true, false]",
+            ),
+            (r#"replace("a-b-c", "-", "+")"#, "a+b+c"),
+            (r#"substring("日本語です", 1, 3)"#, "本語"),
+            (r#"substring("abc", 3, 3)"#, ""),
+            (r#"chars("né!")"#, "[n, é, !]"),
+            (r#"repeat("ab", 3) + repeat("x", 0)"#, "ababab"),
+            (
+                r#"pad_left("7", 3, "0") + pad_left("1234", 3, "0")"#,
+                "0071234",
+            ),
+            (r#"pad_left("é", 2, "·")"#, "·é"),
+            (
+                r#"to_string([1, true, null]) + to_string("s")"#,
+                "[1, true, null]s",
+            ),
+            (r#"to_int("-42") + to_int("+1")"#, "-41"),
+            (r#"sort_by(["b", "a", "c"], fn(s) { s })"#, "[a, b, c]"),
+            (r#"uniq(["a", "b", "a"])"#, "[a, b]"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                evaluate(input),
+                Ok(expected.to_string()),
+                "evaluating {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_string_errors() {
+        let tests = vec![
+            (r#"substring("abc", -1, 2)"#, "substring: negative bound -1"),
+            (
+                r#"substring("abc", 2, 1)"#,
+                "substring: start 2 is after end 1",
+            ),
+            (
+                r#"substring("日本", 0, 3)"#,
+                "substring: end 3 is past the length 2",
+            ),
+            (
+                r#"substring("abc", "0", 1)"#,
+                r#"substring: expected an int, got "0""#,
+            ),
+            (r#"upper(1)"#, "upper: expected a string, got 1"),
+            (r#"join(["a", 1], ",")"#, "join: expected a string, got 1"),
+            (
+                r#"split("abc", "")"#,
+                "split: the separator is empty, use chars instead",
+            ),
+            (
+                r#"replace("abc", "", "x")"#,
+                "replace: the pattern is empty",
+            ),
+            (r#"repeat("a", -2)"#, "repeat: negative count -2"),
+            (
+                r#"repeat("ab", 9223372036854775807)"#,
+                "repeat: the result of 9223372036854775807 copies is too long",
+            ),
+            (
+                r#"pad_left("a", 9223372036854775807, "é")"#,
+                "pad_left: the width 9223372036854775807 is too long",
+            ),
+            (
+                r#"repeat("a", 9223372036854775807)"#,
+                "repeat: the result of 9223372036854775807 copies is too long",
+            ),
+            (
+                r#"pad_left("a", 9223372036854775807, "x")"#,
+                "pad_left: the width 9223372036854775807 is too long",
+            ),
+            (
+                r#"repeat("a", 1073741825)"#,
+                "repeat: the result of 1073741825 copies is too long",
+            ),
+            (
+                r#"pad_left("a", 3, "ab")"#,
+                r#"pad_left: the padding must be one character, got "ab""#,
+            ),
+            (
+                r#"to_int("4x")"#,
+                r#"to_int: cannot convert "4x" to an int"#,
+            ),
+            (r#"to_int("")"#, r#"to_int: cannot convert "" to an int"#),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                evaluate(input),
+                Err(expected.to_string()),
+                "evaluating {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_sort_by_is_stable() {
+        let input = indoc! {"
+            let pairs = [[2, 1], [1, 2], [2, 3], [1, 4], [2, 5]];
+            map(sort_by(pairs, fn(pair) { pair[0] }), fn(pair) { pair[1] })
+        "};
+
+        assert_eq!(evaluate(input), Ok("[2, 4, 1, 3, 5]".to_string()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let tests = vec![
+            ("len(1)", "len: expected an array, got 1"),
+            ("flatten([[1], 2])", "flatten: expected an array, got 2"),
+            (
+                "map([1])",
+                "wrong number of arguments to map: got 1, but it wants 2",
+            ),
+            ("map([1], 5)", "not a function: 5"),
+            (
+                "map([1], fn(a, b) { a })",
+                "wrong number of arguments: got 1, but function wants 2",
+            ),
+            (
+                "sort_by([1, true], fn(x) { x })",
+                "sort_by: cannot compare 1 and true",
+            ),
+            (
+                "sort_by([1, 2], fn(x) { fn() { x } })",
+                "sort_by: cannot compare fn() and fn()",
+            ),
+            // errors in callbacks come through unchanged
+            ("filter([1, 0], fn(x) { 10 / x })", "cannot divide by zero"),
+            (
+                "reduce([1], 0, fn(acc, x: bool) { acc })",
+                "type mismatch for parameter x: expected bool, got int",
+            ),
+            // any stops at the first match, so the error is never reached
+            ("any([1, 0], fn(x) { 10 / x > 0 })", "true"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                evaluate(input).unwrap_or_else(|err| err),
+                expected,
+                "evaluating {}",
+                input
+            );
+        }
+    }
+}
@@ -36,6 +36,22 @@ impl Environment {
     pub fn set(&mut self, name: &str, val: Object) -> Option<Object> {
         self.store.insert(name.to_string(), val)
     }
+
+    /// The names bound directly in this environment, sorted, without those
+    /// of the outer ones.
+    pub fn bindings(&self) -> Vec<(&str, &Object)> {
+        let mut bindings: Vec<(&str, &Object)> = self
+            .store
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect();
+        bindings.sort_by_key(|(name, _)| *name);
+        bindings
+    }
+
+    pub fn outer(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.outer.clone()
+    }
 }
 
 impl Environment {
@@ -43,10 +59,10 @@ impl Environment {
         if depth == 0 {
             write!(f, "...")
         } else {
-            write!(f, "Environment {{\n")?;
-            write!(
+            writeln!(f, "Environment {{")?;
+            writeln!(
                 f,
-                "{:indent$}store: {:?},\n",
+                "{:indent$}store: {:?},",
                 "",
                 self.store,
                 indent = indent + 4
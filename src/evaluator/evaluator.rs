@@ -1,13 +1,29 @@
-use std::{cell::RefCell, fmt::Display, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display},
+    rc::Rc,
+    sync::Arc,
+};
 
-use crate::parser::ast::{
-    expression::Expression,
-    node::Node,
-    operator::{InfixOperator, PrefixOperator},
-    statement::Statement,
+use crate::{
+    lexer::lexer::Lexer,
+    parser::{
+        ast::{
+            annotation::TypeAnnotation,
+            expression::{Expression, Parameter, TemplatePart},
+            node::Node,
+            operator::{InfixOperator, PrefixOperator},
+            statement::{ImportBinding, Statement},
+        },
+        parser::Parser,
+    },
 };
 
-use super::{environment::Environment, object::Object};
+use super::{
+    builtins, environment::Environment, module::ModuleLoader, object::Object,
+    observer::EvalObserver,
+};
 
 #[derive(Debug)]
 pub struct EvaluationError {
@@ -27,35 +43,88 @@ impl Display for EvaluationError {
     }
 }
 
-pub struct Evaluator {}
+#[derive(Default)]
+pub struct Evaluator {
+    observers: Vec<Box<dyn EvalObserver>>,
+    loader: Option<Box<dyn ModuleLoader>>,
+    /// Modules already evaluated, by id.
+    modules: HashMap<String, Object>,
+    /// Ids of the modules being evaluated, innermost last, with the path
+    /// they were imported as.
+    importing: Vec<(String, String)>,
+    /// Bindings every module starts with, such as the prelude.
+    globals: Vec<(String, Object)>,
+}
 
 impl Evaluator {
     pub fn new() -> Self {
-        Evaluator {}
+        Evaluator::default()
+    }
+
+    /// Lets scripts import modules found by `loader`. Without one, every
+    /// `import` fails.
+    pub fn set_loader(&mut self, loader: Box<dyn ModuleLoader>) {
+        self.loader = Some(loader);
     }
 
-    pub fn eval(
+    /// Marks the script about to run as the module with `id`, imported as
+    /// `path`, so that its imports are resolved from it and a module
+    /// importing it back is reported as a cycle.
+    pub fn set_script(&mut self, id: impl Into<String>, path: impl Into<String>) {
+        self.importing.clear();
+        self.importing.push((id.into(), path.into()));
+    }
+
+    /// Starts the modules imported from now on with the bindings of
+    /// `environment`, as scripts start with the prelude.
+    pub fn set_globals(&mut self, environment: &Environment) {
+        self.globals = environment
+            .bindings()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+    }
+
+    /// Has `observer` follow every evaluation from now on, after the ones
+    /// already attached.
+    pub fn attach(&mut self, observer: Box<dyn EvalObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Removes the observer attached last.
+    pub fn detach(&mut self) -> Option<Box<dyn EvalObserver>> {
+        self.observers.pop()
+    }
+
+    pub fn eval<'a>(
         &mut self,
-        node: impl Into<Node>,
+        node: impl Into<Node<'a>>,
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
-        let node = node.into();
-        match node {
+        let result = match node.into() {
             Node::Expression(expression) => self.eval_expression(expression, environment),
             Node::Statement(statement) => self.eval_statement(statement, environment),
-            Node::Program(program) => self.eval_statements(program.statements, environment),
+            Node::Program(program) => self.eval_statements(&program.statements, environment),
+        };
+
+        if let Err(error) = &result {
+            for observer in &mut self.observers {
+                observer.on_error(error);
+            }
         }
+
+        result
     }
 
     fn eval_statements(
         &mut self,
-        statements: Vec<Statement>,
+        statements: &[Statement],
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
         let mut result: Option<Object> = None;
 
         for statement in statements {
-            let evaluated = self.eval(statement, Rc::clone(&environment))?;
+            let evaluated = self.eval_statement(statement, Rc::clone(&environment))?;
 
             if let Object::ReturnValue(_) = evaluated {
                 return Ok(evaluated);
@@ -69,123 +138,419 @@ impl Evaluator {
 
     fn eval_statement(
         &mut self,
-        statement: Statement,
+        statement: &Statement,
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
+        for observer in &mut self.observers {
+            observer.on_statement(statement, &environment)?;
+        }
+
         match statement {
-            Statement::Let { name, value } => self.eval_let_statement(name, value, environment),
+            Statement::Let {
+                name,
+                annotation,
+                value,
+            } => self.eval_let_statement(name, annotation.as_ref(), value, environment),
             Statement::Return { value } => {
-                let value = self.eval(value, environment)?;
+                let value = self.eval_expression(value, environment)?;
                 Ok(Object::return_value(value))
             }
-            Statement::Expression(expression) => self.eval(expression, environment),
+            Statement::Expression(expression) => self.eval_expression(expression, environment),
             Statement::Block(statements) => self.eval_statements(statements, environment),
+            Statement::Import { path, binding } => self.eval_import(path, binding, environment),
+            Statement::Export(names) => {
+                for name in names {
+                    if environment.borrow().get(name).is_none() {
+                        return Err(EvaluationError::new(format!(
+                            "cannot export {}: identifier not found",
+                            name
+                        )));
+                    }
+                }
+                Ok(Object::Null)
+            }
+        }
+    }
+
+    fn eval_import(
+        &mut self,
+        path: &str,
+        binding: &ImportBinding,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Object, EvaluationError> {
+        let module = self.import_module(path)?;
+        let Object::Module { name, exports } = &module else {
+            unreachable!("imported modules are module objects");
+        };
+
+        let bindings = match binding {
+            ImportBinding::Module(alias) => vec![(alias.as_str(), module.clone())],
+            ImportBinding::Names(names) => names
+                .iter()
+                .map(|export| match exports.get(export) {
+                    Some(value) => Ok((export.as_str(), value.clone())),
+                    None => Err(EvaluationError::new(format!(
+                        "module {} does not export {}",
+                        name, export
+                    ))),
+                })
+                .collect::<Result<_, _>>()?,
+        };
+
+        for (name, value) in bindings {
+            for observer in &mut self.observers {
+                observer.on_bind(name, &value);
+            }
+            environment.borrow_mut().set(name, value);
+        }
+
+        Ok(Object::Null)
+    }
+
+    /// Evaluates the module at `path` in an environment of its own the first
+    /// time it is imported, and gives back the same module object after.
+    fn import_module(&mut self, path: &str) -> Result<Object, EvaluationError> {
+        let failed = |msg: String| EvaluationError::new(format!("cannot import {}: {}", path, msg));
+
+        let Some(loader) = &self.loader else {
+            return Err(failed("no module loader".to_string()));
+        };
+        let importer = self.importing.last().map(|(id, _)| id.as_str());
+        let id = loader.resolve(path, importer).map_err(failed)?;
+
+        if let Some(start) = self.importing.iter().position(|(other, _)| *other == id) {
+            let mut cycle: Vec<&str> = self.importing[start..]
+                .iter()
+                .map(|(_, path)| path.as_str())
+                .collect();
+            cycle.push(path);
+            return Err(EvaluationError::new(format!(
+                "circular import: {}",
+                cycle.join(" -> ")
+            )));
+        }
+        if let Some(module) = self.modules.get(&id) {
+            return Ok(module.clone());
+        }
+
+        let source = loader.load(&id).map_err(failed)?;
+        let mut parser = Parser::new(Lexer::new(&source));
+        let program = parser.parse_program();
+        if let Some(error) = parser.errors.first() {
+            return Err(failed(error.to_string().trim_end().to_string()));
         }
+
+        let environment = Environment::new();
+        for (name, value) in &self.globals {
+            environment.borrow_mut().set(name, value.clone());
+        }
+        self.importing.push((id.clone(), path.to_string()));
+        let result = self.eval_statements(&program.statements, Rc::clone(&environment));
+        self.importing.pop();
+        result?;
+
+        // only the exports at the top level of the module count
+        let mut exports = BTreeMap::new();
+        for statement in &program.statements {
+            if let Statement::Export(names) = statement {
+                for name in names {
+                    if let Some(value) = environment.borrow().get(name) {
+                        exports.insert(name.clone(), value);
+                    }
+                }
+            }
+        }
+
+        let module = Object::Module {
+            name: path.to_string(),
+            exports: Rc::new(exports),
+        };
+        self.modules.insert(id, module.clone());
+
+        Ok(module)
     }
 
     fn eval_let_statement(
         &mut self,
-        name: String,
-        value: Expression,
+        name: &str,
+        annotation: Option<&TypeAnnotation>,
+        value: &Expression,
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
-        let value = self.eval(value, Rc::clone(&environment))?;
+        let value = self.eval_expression(value, Rc::clone(&environment))?;
+        let value = check_annotation(value, annotation, format_args!("{}", name))?;
 
-        environment.borrow_mut().set(&name, value.clone());
+        for observer in &mut self.observers {
+            observer.on_bind(name, &value);
+        }
+        environment.borrow_mut().set(name, value.clone());
 
         Ok(value)
     }
 
     fn eval_expression(
         &mut self,
-        expression: Expression,
+        expression: &Expression,
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
         match expression {
-            Expression::Int(int) => Ok(Object::Integer(int)),
-            Expression::Bool(boolean) => Ok(Object::Boolean(boolean)),
+            Expression::Int(int) => Ok(Object::Integer(*int)),
+            Expression::Bool(boolean) => Ok(Object::Boolean(*boolean)),
+            Expression::String(string) => Ok(Object::string(string.as_str())),
+            Expression::Template(parts) => self.eval_template(parts, environment),
             Expression::Identifier(identifier) => self.eval_identifier(identifier, environment),
             Expression::If {
                 condition,
                 consequence,
                 alternative,
-            } => self.eval_if_expression(*condition, consequence, alternative, environment),
-            Expression::Function { parameters, body } => {
-                self.eval_function(parameters, body, environment)
+            } => {
+                self.eval_if_expression(condition, consequence, alternative.as_deref(), environment)
             }
+            Expression::Function {
+                parameters,
+                result,
+                body,
+            } => self.eval_function(parameters, result, body, environment),
             Expression::Call {
                 function,
                 arguments,
-            } => self.eval_call(*function, arguments, environment),
+            } => self.eval_call(function, arguments, environment),
             Expression::Prefix { operator, rhs } => {
-                self.eval_prefix_expression(operator, *rhs, environment)
+                self.eval_prefix_expression(operator, rhs, environment)
             }
             Expression::Infix { rhs, operator, lhs } => {
-                self.eval_infix_expression(operator, *lhs, *rhs, environment)
+                self.eval_infix_expression(operator, lhs, rhs, environment)
             }
             Expression::Null => Ok(Object::Null),
+            Expression::Member { object, name } => {
+                match self.eval_expression(object, environment)? {
+                    Object::Module {
+                        name: module,
+                        exports,
+                    } => exports.get(name).cloned().ok_or_else(|| {
+                        EvaluationError::new(format!("module {} does not export {}", module, name))
+                    }),
+                    object => Err(EvaluationError::new(format!(
+                        "not a module: {}",
+                        object.summary()
+                    ))),
+                }
+            }
+            Expression::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| self.eval_expression(element, Rc::clone(&environment)))
+                    .collect::<Result<Vec<Object>, _>>()?;
+                Ok(Object::array(elements))
+            }
+            Expression::Index { left, index } => {
+                let left = self.eval_expression(left, Rc::clone(&environment))?;
+                let index = self.eval_expression(index, environment)?;
+                self.eval_index_expression(left, index)
+            }
+        }
+    }
+
+    /// Indexes outside the array or string give null. Strings are indexed
+    /// by Unicode scalar value.
+    fn eval_index_expression(
+        &self,
+        left: Object,
+        index: Object,
+    ) -> Result<Object, EvaluationError> {
+        match (&left, &index) {
+            (Object::Array(elements), Object::Integer(index)) => Ok(usize::try_from(*index)
+                .ok()
+                .and_then(|index| elements.get(index))
+                .cloned()
+                .unwrap_or(Object::Null)),
+            (Object::String(string), Object::Integer(index)) => Ok(usize::try_from(*index)
+                .ok()
+                .and_then(|index| string.chars().nth(index))
+                .map(|ch| Object::string(ch.to_string()))
+                .unwrap_or(Object::Null)),
+            _ => Err(EvaluationError::new(format!(
+                "cannot index {} with {}",
+                left.type_name(),
+                index.type_name()
+            ))),
         }
     }
 
     fn eval_call(
         &mut self,
-        function: Expression,
-        arguments: Vec<Expression>,
+        callee: &Expression,
+        arguments: &[Expression],
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
-        let function = self.eval(function, Rc::clone(&environment))?;
+        let function = self.eval_expression(callee, Rc::clone(&environment))?;
+
+        self.invoke(
+            callee,
+            &function,
+            arguments,
+            arguments.len(),
+            |evaluator, i| evaluator.eval_expression(&arguments[i], Rc::clone(&environment)),
+        )
+    }
+
+    /// Calls `function` with arguments already evaluated, the way builtins
+    /// call back into Monkey functions. `callee` is the expression observers
+    /// see the call as.
+    pub(crate) fn call(
+        &mut self,
+        callee: &Expression,
+        function: &Object,
+        arguments: Vec<Object>,
+    ) -> Result<Object, EvaluationError> {
+        let count = arguments.len();
+        let mut arguments = arguments.into_iter();
+
+        self.invoke(callee, function, &[], count, |_, _| {
+            Ok(arguments.next().expect("one value per argument"))
+        })
+    }
 
-        let Object::Function { parameters, environment: func_environment, body } = function else {
-            return Err(EvaluationError::new(format!("not a function: {}", function)));
+    /// Calls `function` with `count` arguments, getting each one from
+    /// `argument` only once the call is known to be valid, so a function
+    /// call needs no list of its arguments unless observers are attached.
+    /// `arguments` are the argument expressions, if any, for builtins that
+    /// call back into Monkey functions.
+    fn invoke(
+        &mut self,
+        callee: &Expression,
+        function: &Object,
+        arguments: &[Expression],
+        count: usize,
+        mut argument: impl FnMut(&mut Self, usize) -> Result<Object, EvaluationError>,
+    ) -> Result<Object, EvaluationError> {
+        let Object::Function {
+            parameters,
+            result,
+            environment: func_environment,
+            body,
+        } = function
+        else {
+            return match function {
+                Object::Builtin(name) => {
+                    let values = (0..count)
+                        .map(|i| argument(self, i))
+                        .collect::<Result<Vec<Object>, _>>()?;
+                    self.call_builtin(name, callee, arguments, values)
+                }
+                _ => Err(EvaluationError::new(format!(
+                    "not a function: {}",
+                    function.summary()
+                ))),
+            };
         };
 
-        if parameters.len() != arguments.len() {
+        if parameters.len() != count {
             return Err(EvaluationError::new(format!(
                 "wrong number of arguments: got {}, but function wants {}",
-                arguments.len(),
+                count,
                 parameters.len()
             )));
         }
 
-        let local_env = Environment::with_outer(Rc::clone(&func_environment));
+        let local_env = Environment::with_outer(Rc::clone(func_environment));
+        // the arguments are only kept around for observers
+        let mut observed = (!self.observers.is_empty()).then(Vec::new);
 
-        for (parameter, argument) in parameters.iter().zip(arguments) {
-            let argument = self.eval(argument, Rc::clone(&environment))?;
+        for (i, parameter) in parameters.iter().enumerate() {
+            let value = check_annotation(
+                argument(self, i)?,
+                parameter.annotation.as_ref(),
+                format_args!("parameter {}", parameter.name),
+            )?;
 
-            local_env.borrow_mut().set(parameter, argument);
+            if let Some(observed) = &mut observed {
+                observed.push(value.clone());
+            }
+            local_env.borrow_mut().set(&parameter.name, value);
         }
 
-        let body = match self.eval(Statement::Block(body), Rc::clone(&local_env))? {
+        let Some(observed) = observed else {
+            return self.apply_function(body, result.as_ref(), local_env);
+        };
+
+        for observer in &mut self.observers {
+            observer.on_call(callee, function, &observed);
+        }
+        let value = self.apply_function(body, result.as_ref(), local_env);
+        for observer in &mut self.observers {
+            observer.on_return(callee, function, value.as_ref());
+        }
+
+        value
+    }
+
+    fn call_builtin(
+        &mut self,
+        name: &'static str,
+        callee: &Expression,
+        arguments: &[Expression],
+        values: Vec<Object>,
+    ) -> Result<Object, EvaluationError> {
+        if self.observers.is_empty() {
+            return builtins::apply(self, name, callee, arguments, values);
+        }
+
+        let function = Object::Builtin(name);
+        for observer in &mut self.observers {
+            observer.on_call(callee, &function, &values);
+        }
+        let value = builtins::apply(self, name, callee, arguments, values);
+        for observer in &mut self.observers {
+            observer.on_return(callee, &function, value.as_ref());
+        }
+
+        value
+    }
+
+    fn apply_function(
+        &mut self,
+        body: &[Statement],
+        result: Option<&TypeAnnotation>,
+        local_env: Rc<RefCell<Environment>>,
+    ) -> Result<Object, EvaluationError> {
+        let body = match self.eval_statements(body, local_env)? {
             Object::ReturnValue(value) => *value,
             value => value,
         };
-
-        Ok(body)
+        check_annotation(body, result, format_args!("return value"))
     }
 
     fn eval_function(
         &mut self,
-        parameters: Vec<String>,
-        body: Vec<Statement>,
+        parameters: &Arc<[Parameter]>,
+        result: &Option<TypeAnnotation>,
+        body: &Arc<[Statement]>,
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
         Ok(Object::Function {
-            parameters,
-            body,
+            parameters: Arc::clone(parameters),
+            result: result.clone(),
+            body: Arc::clone(body),
             environment: Environment::with_outer(Rc::clone(&environment)),
         })
     }
 
     fn eval_if_expression(
         &mut self,
-        condition: Expression,
-        consequence: Vec<Statement>,
-        alternative: Option<Vec<Statement>>,
+        condition: &Expression,
+        consequence: &[Statement],
+        alternative: Option<&[Statement]>,
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
-        let condition = self.eval(condition, Rc::clone(&environment))?;
+        let value = self.eval_expression(condition, Rc::clone(&environment))?;
+        let taken = self.is_truthy(&value);
 
-        if self.is_truthy(condition) {
+        for observer in &mut self.observers {
+            observer.on_branch(condition, taken);
+        }
+
+        if taken {
             self.eval_statements(consequence, Rc::clone(&environment))
         } else if let Some(alternative) = alternative {
             self.eval_statements(alternative, environment)
@@ -194,37 +559,59 @@ impl Evaluator {
         }
     }
 
+    fn eval_template(
+        &mut self,
+        parts: &[TemplatePart],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Object, EvaluationError> {
+        let mut string = String::new();
+
+        for part in parts {
+            match part {
+                TemplatePart::Text(text) => string.push_str(text),
+                TemplatePart::Expression(expression) => {
+                    let value = self.eval_expression(expression, Rc::clone(&environment))?;
+                    string.push_str(&value.to_string());
+                }
+            }
+        }
+
+        Ok(Object::string(string))
+    }
+
     fn eval_identifier(
         &mut self,
-        identifier: String,
+        identifier: &str,
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
-        match environment.borrow().get(&identifier) {
+        match environment.borrow().get(identifier) {
             Some(object) => Ok(object),
-            None => Err(EvaluationError::new(format!(
-                "identifier not found: {}",
-                identifier
-            ))),
+            None => builtins::lookup(identifier).ok_or_else(|| {
+                EvaluationError::new(format!("identifier not found: {}", identifier))
+            }),
         }
     }
 
-    fn is_truthy(&self, object: Object) -> bool {
+    pub(crate) fn is_truthy(&self, object: &Object) -> bool {
         match object {
-            Object::Integer(integer) => integer != 0,
-            Object::Boolean(boolean) => boolean,
+            Object::Integer(integer) => *integer != 0,
+            Object::Boolean(boolean) => *boolean,
             Object::Null => false,
-            Object::ReturnValue(value) => self.is_truthy(*value),
-            Object::Function { .. } => todo!(),
+            Object::ReturnValue(value) => self.is_truthy(value),
+            Object::Function { .. } | Object::Builtin(_) => true,
+            Object::Module { .. } => true,
+            Object::Array(elements) => !elements.is_empty(),
+            Object::String(string) => !string.is_empty(),
         }
     }
 
     fn eval_prefix_expression(
         &mut self,
-        operator: PrefixOperator,
-        rhs: Expression,
+        operator: &PrefixOperator,
+        rhs: &Expression,
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
-        let rhs = self.eval(rhs, environment)?;
+        let rhs = self.eval_expression(rhs, environment)?;
         match operator {
             PrefixOperator::Not => self.eval_bang_operator_prefix_expression(rhs),
             PrefixOperator::Negative => self.eval_negative_operator_prefix_expression(rhs),
@@ -233,15 +620,15 @@ impl Evaluator {
 
     fn eval_infix_expression(
         &mut self,
-        operator: InfixOperator,
-        lhs: Expression,
-        rhs: Expression,
+        operator: &InfixOperator,
+        lhs: &Expression,
+        rhs: &Expression,
         environment: Rc<RefCell<Environment>>,
     ) -> Result<Object, EvaluationError> {
-        let lhs = self.eval(lhs, Rc::clone(&environment))?;
-        let rhs = self.eval(rhs, Rc::clone(&environment))?;
+        let lhs = self.eval_expression(lhs, Rc::clone(&environment))?;
+        let rhs = self.eval_expression(rhs, Rc::clone(&environment))?;
 
-        match (&operator, &lhs, &rhs) {
+        match (operator, &lhs, &rhs) {
             (InfixOperator::Add, Object::Integer(int1), Object::Integer(int2)) => {
                 Ok(Object::Integer(int1 + int2))
             }
@@ -278,12 +665,21 @@ impl Evaluator {
             (InfixOperator::NotEqual, Object::Boolean(bool1), Object::Boolean(bool2)) => {
                 Ok(Object::Boolean(bool1 != bool2))
             }
-            _ => {
-                return Err(EvaluationError::new(format!(
-                    "invalid operation: {} {} {}",
-                    lhs, operator, rhs
-                )))
+            (InfixOperator::Add, Object::String(lhs), Object::String(rhs)) => {
+                Ok(Object::string(format!("{}{}", lhs, rhs)))
             }
+            (InfixOperator::Equal, Object::String(lhs), Object::String(rhs)) => {
+                Ok(Object::Boolean(lhs == rhs))
+            }
+            (InfixOperator::NotEqual, Object::String(lhs), Object::String(rhs)) => {
+                Ok(Object::Boolean(lhs != rhs))
+            }
+            _ => Err(EvaluationError::new(format!(
+                "invalid operation: {} {} {}",
+                lhs.summary(),
+                operator,
+                rhs.summary()
+            ))),
         }
     }
 
@@ -291,7 +687,10 @@ impl Evaluator {
         match rhs {
             Object::Boolean(boolean) => Ok(Object::Boolean(!boolean)),
             Object::Integer(integer) => Ok(Object::Boolean(integer == 0)),
-            x => Err(EvaluationError::new(format!("invalid operation: !{}", x))),
+            x => Err(EvaluationError::new(format!(
+                "invalid operation: !{}",
+                x.summary()
+            ))),
         }
     }
 
@@ -301,28 +700,309 @@ impl Evaluator {
     ) -> Result<Object, EvaluationError> {
         match rhs {
             Object::Integer(integer) => Ok(Object::Integer(-integer)),
-            x => Err(EvaluationError::new(format!("invalid operation: -{}", x))),
+            x => Err(EvaluationError::new(format!(
+                "invalid operation: -{}",
+                x.summary()
+            ))),
         }
     }
 }
 
+/// Fails unless `value` has the annotated type, if there is an annotation,
+/// and gives back the value with the annotation applied. `what` names the
+/// annotated binding in the error and is only formatted then.
+fn check_annotation(
+    value: Object,
+    annotation: Option<&TypeAnnotation>,
+    what: fmt::Arguments,
+) -> Result<Object, EvaluationError> {
+    match annotation {
+        None => Ok(value),
+        Some(annotation) if !value.matches(annotation) => Err(EvaluationError::new(format!(
+            "type mismatch for {}: expected {}, got {}",
+            what,
+            annotation,
+            value.type_name()
+        ))),
+        Some(annotation) => Ok(value.annotated(annotation)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::rc::Rc;
+    use std::{cell::RefCell, rc::Rc, sync::Arc, thread};
 
     use indoc::indoc;
 
     use crate::{
-        evaluator::{environment::Environment, object::Object},
+        evaluator::{
+            environment::Environment, module::MemoryLoader, object::Object, observer::EvalObserver,
+        },
         lexer::lexer::Lexer,
         parser::{
-            ast::{expression::Expression, operator::InfixOperator, statement::Statement},
+            ast::{
+                expression::{Expression, Parameter},
+                operator::InfixOperator,
+                statement::Statement,
+            },
             parser::Parser,
         },
     };
 
     use super::{EvaluationError, Evaluator};
 
+    /// Records every event into a log shared with the test.
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl EvalObserver for Recorder {
+        fn on_call(&mut self, callee: &Expression, _function: &Object, arguments: &[Object]) {
+            let arguments: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+            self.0
+                .borrow_mut()
+                .push(format!("call {}({})", callee, arguments.join(", ")));
+        }
+
+        fn on_return(
+            &mut self,
+            callee: &Expression,
+            _function: &Object,
+            result: Result<&Object, &EvaluationError>,
+        ) {
+            let result = match result {
+                Ok(value) => value.to_string(),
+                Err(err) => format!("error {}", err),
+            };
+            self.0
+                .borrow_mut()
+                .push(format!("return {} {}", callee, result));
+        }
+
+        fn on_bind(&mut self, name: &str, value: &Object) {
+            self.0.borrow_mut().push(format!("let {} {}", name, value));
+        }
+
+        fn on_error(&mut self, error: &EvaluationError) {
+            self.0.borrow_mut().push(format!("error {}", error));
+        }
+    }
+
+    #[test]
+    fn test_observers() {
+        let input = indoc! {"
+            let add = fn(a, b) { a + b };
+            let sum = add(1, add(2, 3));
+            add(sum, true)
+        "};
+        let program = Parser::new(Lexer::new(input)).parse_program();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let other = Rc::new(RefCell::new(vec![]));
+        let mut evaluator = Evaluator::new();
+        evaluator.attach(Box::new(Recorder(Rc::clone(&log))));
+        evaluator.attach(Box::new(Recorder(Rc::clone(&other))));
+
+        let result = evaluator.eval(&program, Environment::new());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid operation: 6 + true"
+        );
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                "let add fn(a, b) {\n(a + b)\n}",
+                "call add(2, 3)",
+                "return add 5",
+                "call add(1, 5)",
+                "return add 6",
+                "let sum 6",
+                "call add(6, true)",
+                "return add error invalid operation: 6 + true",
+                "error invalid operation: 6 + true",
+            ]
+        );
+        assert_eq!(*other.borrow(), *log.borrow());
+
+        assert!(evaluator.detach().is_some());
+        assert!(evaluator.detach().is_some());
+        assert!(evaluator.detach().is_none());
+        evaluator
+            .eval(&program.statements[1], Environment::new())
+            .unwrap_err();
+        assert_eq!(log.borrow().len(), 9);
+    }
+
+    fn modules() -> MemoryLoader {
+        let mut loader = MemoryLoader::new();
+        loader.insert(
+            "math",
+            indoc! {"
+                let square = fn(x) { x * x };
+                let cube = fn(x) { x * square(x) };
+                let hidden = 1;
+                export square, cube;
+            "},
+        );
+        loader.insert("a", "import \"b\" as b; let x = 1; export x;");
+        loader.insert("b", "import \"c\" as c; let y = 2; export y;");
+        loader.insert("c", "import { x } from \"a\"; export x;");
+        loader.insert("d", "import \"main\" as main;");
+        loader.insert("broken", "let = 1;");
+        loader.insert("total", "let total = sum([1, 2]); export total;");
+        loader
+    }
+
+    fn evaluate_with_modules(input: &str) -> Result<Object, EvaluationError> {
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_loader(Box::new(modules()));
+        evaluator.eval(&program, Environment::new())
+    }
+
+    #[test]
+    fn test_imports() {
+        let tests = vec![
+            (
+                "import \"math\" as m; m.square(3) + m.cube(2)",
+                Object::Integer(17),
+            ),
+            (
+                "import { cube } from \"math\"; cube(3)",
+                Object::Integer(27),
+            ),
+            // a module only sees its own bindings
+            (
+                "let square = 0; import { cube } from \"math\"; cube(2) + square",
+                Object::Integer(8),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                evaluate_with_modules(input).map_err(|err| err.to_string()),
+                Ok(expected),
+                "evaluating {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let tests = vec![
+            (
+                "import \"math\" as m; m.hidden",
+                "module math does not export hidden",
+            ),
+            (
+                "import { square, hidden } from \"math\";",
+                "module math does not export hidden",
+            ),
+            ("let m = 1; m.x", "not a module: 1"),
+            (
+                "import \"nope\" as n;",
+                "cannot import nope: module nope not found",
+            ),
+            (
+                "import \"broken\" as b;",
+                "cannot import broken: [ParserError:@1:5] expected identifier, got assign",
+            ),
+            ("import \"a\" as a;", "circular import: a -> b -> c -> a"),
+            (
+                "export nothing;",
+                "cannot export nothing: identifier not found",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let err = evaluate_with_modules(input).unwrap_err();
+            assert!(
+                err.to_string().contains(expected),
+                "evaluating {}: {}",
+                input,
+                err
+            );
+        }
+
+        let program = Parser::new(Lexer::new("import \"math\" as m;")).parse_program();
+        assert_eq!(
+            Evaluator::new()
+                .eval(&program, Environment::new())
+                .map_err(|err| err.to_string()),
+            Err(EvaluationError::new("cannot import math: no module loader").to_string())
+        );
+    }
+
+    #[test]
+    fn test_importing_the_script_is_a_cycle() {
+        let input = "import \"d\" as d;";
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        let mut loader = modules();
+        loader.insert("main", input);
+        let mut evaluator = Evaluator::new();
+        evaluator.set_loader(Box::new(loader));
+        evaluator.set_script("main", "main.mk");
+
+        assert_eq!(
+            evaluator
+                .eval(&program, Environment::new())
+                .map_err(|err| err.to_string()),
+            Err("circular import: main.mk -> d -> main".to_string())
+        );
+    }
+
+    #[test]
+    fn test_modules_start_with_the_globals() {
+        let program =
+            Parser::new(Lexer::new("import { total } from \"total\"; total")).parse_program();
+        let globals = Environment::new();
+        globals.borrow_mut().set("sum", Object::Builtin("len"));
+
+        let mut evaluator = Evaluator::new();
+        evaluator.set_loader(Box::new(modules()));
+        assert_eq!(
+            evaluator
+                .eval(&program, Environment::new())
+                .map_err(|err| err.to_string()),
+            Err("identifier not found: sum".to_string())
+        );
+
+        let mut evaluator = Evaluator::new();
+        evaluator.set_loader(Box::new(modules()));
+        evaluator.set_globals(&globals.borrow());
+        globals.borrow_mut().set("sum", Object::Null);
+        assert_eq!(
+            evaluator
+                .eval(&program, Environment::new())
+                .map_err(|err| err.to_string()),
+            Ok(Object::Integer(2))
+        );
+    }
+
+    #[test]
+    fn test_modules_are_evaluated_once() {
+        let input = indoc! {"
+            import \"math\" as m;
+            import { square } from \"math\";
+            m.square(2) + square(3)
+        "};
+        let program = Parser::new(Lexer::new(input)).parse_program();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut evaluator = Evaluator::new();
+        evaluator.set_loader(Box::new(modules()));
+        evaluator.attach(Box::new(Recorder(Rc::clone(&log))));
+        evaluator.eval(&program, Environment::new()).unwrap();
+
+        let bindings: Vec<String> = log
+            .borrow()
+            .iter()
+            .filter(|event| event.starts_with("let hidden"))
+            .cloned()
+            .collect();
+        assert_eq!(bindings, vec!["let hidden 1"]);
+        assert!(log.borrow().contains(&"let m module math".to_string()));
+    }
+
     #[test]
     fn test_recursion() {
         let input = indoc! {"
@@ -406,15 +1086,222 @@ mod tests {
         let evaluated = evaluate("fn(x) { x + 2 };");
         assert_eq!(
             evaluated.unwrap(),
-            Object::Function {
-                parameters: vec!["x".to_string()],
-                body: vec![Statement::Expression(Expression::infix(
+            Object::function(
+                vec![Parameter::from("x")],
+                vec![Statement::Expression(Expression::infix(
                     Expression::identifier("x"),
                     Expression::Int(2),
                     InfixOperator::Add
                 ))],
-                environment: Environment::with_outer(Environment::new()),
-            }
+                Environment::with_outer(Environment::new()),
+            )
+        );
+    }
+
+    #[test]
+    fn test_program_can_be_evaluated_repeatedly() {
+        let mut parser = Parser::new(Lexer::new(indoc! {"
+            let double = fn(x) { x * 2 };
+            double(input);
+        "}));
+        let program = parser.parse_program();
+        assert_eq!(parser.errors.len(), 0);
+
+        for input in 0..5 {
+            let environment = Environment::new();
+            environment
+                .borrow_mut()
+                .set("input", Object::Integer(input));
+            let evaluated = Evaluator::new().eval(&program, environment);
+            assert_eq!(evaluated.unwrap(), Object::Integer(input * 2));
+        }
+    }
+
+    #[test]
+    fn test_program_can_be_evaluated_concurrently() {
+        let mut parser = Parser::new(Lexer::new(indoc! {"
+            let factorial = fn(x) {
+                if (x == 0) { return 1; }
+                x * factorial(x - 1)
+            };
+            factorial(input);
+        "}));
+        let program = parser.parse_program();
+        assert_eq!(parser.errors.len(), 0);
+
+        let results: Vec<i64> = thread::scope(|scope| {
+            let handles: Vec<_> = (1..=4)
+                .map(|input| {
+                    let program = &program;
+                    scope.spawn(move || {
+                        let environment = Environment::new();
+                        environment
+                            .borrow_mut()
+                            .set("input", Object::Integer(input));
+                        match Evaluator::new().eval(program, environment) {
+                            Ok(Object::Integer(result)) => result,
+                            other => panic!("unexpected result {:?}", other),
+                        }
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(results, vec![1, 2, 6, 24]);
+    }
+
+    #[test]
+    fn test_function_shares_body_with_ast() {
+        let mut parser = Parser::new(Lexer::new("fn(x) { x }"));
+        let program = parser.parse_program();
+        let Statement::Expression(Expression::Function { body, .. }) = &program.statements[0]
+        else {
+            panic!("expected a function literal");
+        };
+
+        let evaluated = Evaluator::new().eval(&program, Environment::new());
+        let Ok(Object::Function {
+            body: closure_body, ..
+        }) = evaluated
+        else {
+            panic!("expected a function object");
+        };
+        assert!(Arc::ptr_eq(body, &closure_body));
+    }
+
+    #[test]
+    fn test_function_lookup_shares_body() {
+        let environment = Environment::new();
+        let mut parser = Parser::new(Lexer::new("let identity = fn(x) { x };"));
+        let program = parser.parse_program();
+        Evaluator::new()
+            .eval(&program, Rc::clone(&environment))
+            .unwrap();
+
+        let first = environment.borrow().get("identity").unwrap();
+        let second = environment.borrow().get("identity").unwrap();
+        let (
+            Object::Function {
+                parameters: first_parameters,
+                body: first_body,
+                ..
+            },
+            Object::Function {
+                parameters: second_parameters,
+                body: second_body,
+                ..
+            },
+        ) = (first, second)
+        else {
+            panic!("identity is not a function");
+        };
+        assert!(Arc::ptr_eq(&first_parameters, &second_parameters));
+        assert!(Arc::ptr_eq(&first_body, &second_body));
+    }
+
+    #[test]
+    fn test_arrays_and_index() {
+        let tests = vec![
+            ("[1, 2 * 2, 3 + 3]", "[1, 4, 6]"),
+            ("let a = [1, [true, null]]; a[1][0]", "true"),
+            ("[1, 2, 3][1 + 1]", "3"),
+            ("[1][1]", "null"),
+            ("[1][-1]", "null"),
+            ("let f = fn(x) { x * 2 }; [f][0](3)", "6"),
+            ("if ([]) { 1 } else { 2 }", "2"),
+            ("len", "builtin len"),
+            ("let len = 1; len", "1"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                evaluate(input).map(|value| value.to_string()).unwrap(),
+                expected,
+                "evaluating {}",
+                input
+            );
+        }
+
+        assert_eq!(
+            evaluate("1[0]").unwrap_err().msg,
+            "cannot index int with int"
+        );
+        assert_eq!(
+            evaluate("[1][true]").unwrap_err().msg,
+            "cannot index array with bool"
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let tests = vec![
+            (
+                r#""Hello" + ", " + "world!""#,
+                Object::string("Hello, world!"),
+            ),
+            (r#""größe"[2]"#, Object::string("ö")),
+            (r#""日本"[2]"#, Object::Null),
+            (r#""a" == "a""#, Object::Boolean(true)),
+            (r#""a" != "a""#, Object::Boolean(false)),
+            (r#"if ("") { 1 } else { 2 }"#, Object::Integer(2)),
+            (r#"let s: string = "x"; s"#, Object::string("x")),
+            (
+                r#"let name = "Ann"; let count = 2; "Hello ${name}, you have ${count + 1} items""#,
+                Object::string("Hello Ann, you have 3 items"),
+            ),
+            (r#""${"x" + "${1}"}""#, Object::string("x1")),
+            (
+                r#""${[1, "a"]} ${null} ${true} \${x}""#,
+                Object::string("[1, a] null true ${x}"),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                evaluate(input).map_err(|err| err.to_string()),
+                Ok(expected),
+                "evaluating {}",
+                input
+            );
+        }
+
+        assert_eq!(
+            evaluate(r#""a" + 1"#).unwrap_err().msg,
+            r#"invalid operation: "a" + 1"#
+        );
+        assert_eq!(
+            evaluate(r#"let s: string = 1;"#).unwrap_err().msg,
+            "type mismatch for s: expected string, got int"
+        );
+        assert_eq!(
+            evaluate(r#""a ${b}""#).unwrap_err().msg,
+            "identifier not found: b"
+        );
+    }
+
+    #[test]
+    fn test_builtin_callbacks_are_observed() {
+        let program = Parser::new(Lexer::new(
+            "let double = fn(x) { x * 2 }; map([1, 2], double)",
+        ))
+        .parse_program();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut evaluator = Evaluator::new();
+        evaluator.attach(Box::new(Recorder(Rc::clone(&log))));
+        evaluator.eval(&program, Environment::new()).unwrap();
+
+        assert_eq!(
+            log.borrow()[1..],
+            vec![
+                "call map([1, 2], fn(x) {\n(x * 2)\n})",
+                "call double(1)",
+                "return double 2",
+                "call double(2)",
+                "return double 4",
+                "return map [2, 4]",
+            ]
         );
     }
 
@@ -478,6 +1365,90 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_type_annotations() {
+        let tests = vec![
+            ("let a: int = 5; a;", Object::Integer(5)),
+            ("let f = fn(a: int, b: bool) -> int { a }; f(1, true)", Object::Integer(1)),
+            ("let f = fn(x) -> bool { return x > 1; }; f(2)", Object::Boolean(true)),
+            (
+                "let apply: fn(fn(int) -> int, int) -> int = fn(f, x) { f(x) }; apply(fn(x) { -x }, 3)",
+                Object::Integer(-3),
+            ),
+            ("let n: null = if (false) { 1 }; n", Object::Null),
+            (
+                "let f: fn() -> fn() -> int = fn() { fn() { 1 } }; f()()",
+                Object::Integer(1),
+            ),
+            (
+                "let f = fn(g: fn(string) -> int) { g(\"ab\") }; f(len)",
+                Object::Integer(2),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(evaluate(input).unwrap(), expected, "evaluating {}", input);
+        }
+    }
+
+    #[test]
+    fn test_type_annotation_errors() {
+        let tests = vec![
+            (
+                "let a: bool = 5;",
+                "type mismatch for a: expected bool, got int",
+            ),
+            (
+                "let f = fn(a: int, b: bool) { a }; f(1, 2)",
+                "type mismatch for parameter b: expected bool, got int",
+            ),
+            (
+                "let f = fn(x) -> int { if (x) { return true; } 1 }; f(true)",
+                "type mismatch for return value: expected int, got bool",
+            ),
+            (
+                "let f: fn(int) -> int = fn(a, b) { a };",
+                "type mismatch for f: expected fn(int) -> int, got function with 2 parameters",
+            ),
+            (
+                "let x: fn() -> int = fn() { true }; x()",
+                "type mismatch for return value: expected int, got bool",
+            ),
+            (
+                "let f: fn() -> fn() -> int = fn() { fn() { true } }; f()()",
+                "type mismatch for return value: expected int, got bool",
+            ),
+            (
+                "let apply = fn(f: fn(int) -> bool) { f(1) }; apply(fn(x) { x })",
+                "type mismatch for return value: expected bool, got int",
+            ),
+            // callbacks from builtins are checked like any other call
+            (
+                "map([1], fn(x: bool) { x })",
+                "type mismatch for parameter x: expected bool, got int",
+            ),
+            (
+                "map([1], fn(x) -> bool { x })",
+                "type mismatch for return value: expected bool, got int",
+            ),
+            (
+                "let f = fn(g: fn(int) -> int) { g(1) }; f(push)",
+                "type mismatch for parameter g: expected fn(int) -> int, got builtin function",
+            ),
+            (
+                "let f: fn(int) -> int = fn(x) -> bool { true };",
+                "type mismatch for f: expected fn(int) -> int, got function with 1 parameter returning bool",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                evaluate(input).unwrap_err().msg,
+                expected,
+                "evaluating {}",
+                input
+            );
+        }
+    }
+
     #[test]
     fn test_return_statements() {
         let tests = vec![
@@ -623,6 +1594,6 @@ mod tests {
         let program = parser.parse_program();
         let environment = Environment::new();
         let mut evaluator = Evaluator::new();
-        evaluator.eval(program, Rc::clone(&environment))
+        evaluator.eval(&program, Rc::clone(&environment))
     }
 }
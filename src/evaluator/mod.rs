@@ -1,3 +1,8 @@
+pub mod builtins;
 pub mod environment;
+#[allow(clippy::module_inception)]
 pub mod evaluator;
+pub mod module;
 pub mod object;
+pub mod observer;
+pub mod tracer;
@@ -0,0 +1,82 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Finds and reads the modules a script imports.
+pub trait ModuleLoader {
+    /// Turns the path of an `import` into the id of a module, the same
+    /// however the module is imported. `importer` is the id of the module
+    /// or script the import is in, or `None` for code without one, such as
+    /// the REPL and streamed scripts.
+    fn resolve(&self, path: &str, importer: Option<&str>) -> Result<String, String>;
+
+    /// The source of the module with `id`.
+    fn load(&self, id: &str) -> Result<String, String>;
+}
+
+/// Loads modules from files. A path is looked up next to the module that
+/// imports it, then in each search path in order. Ids are canonical paths.
+pub struct FsLoader {
+    search_paths: Vec<PathBuf>,
+}
+
+impl FsLoader {
+    pub fn new(search_paths: Vec<PathBuf>) -> Self {
+        FsLoader { search_paths }
+    }
+}
+
+impl ModuleLoader for FsLoader {
+    fn resolve(&self, path: &str, importer: Option<&str>) -> Result<String, String> {
+        let directory = importer.and_then(|importer| Path::new(importer).parent());
+
+        directory
+            .into_iter()
+            .chain(self.search_paths.iter().map(PathBuf::as_path))
+            .map(|directory| directory.join(path))
+            .find(|candidate| candidate.is_file())
+            .and_then(|found| found.canonicalize().ok())
+            .map(|found| found.to_string_lossy().into_owned())
+            .ok_or_else(|| format!("module {} not found", path))
+    }
+
+    fn load(&self, id: &str) -> Result<String, String> {
+        fs::read_to_string(id).map_err(|err| format!("failed to read {}: {}", id, err))
+    }
+}
+
+/// Loads modules from sources kept in memory, by name, for tests and
+/// embedders.
+#[derive(Default)]
+pub struct MemoryLoader {
+    modules: HashMap<String, String>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        MemoryLoader::default()
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.modules.insert(name.into(), source.into());
+    }
+}
+
+impl ModuleLoader for MemoryLoader {
+    fn resolve(&self, path: &str, _importer: Option<&str>) -> Result<String, String> {
+        if self.modules.contains_key(path) {
+            Ok(path.to_string())
+        } else {
+            Err(format!("module {} not found", path))
+        }
+    }
+
+    fn load(&self, id: &str) -> Result<String, String> {
+        self.modules
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("module {} not found", id))
+    }
+}
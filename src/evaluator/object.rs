@@ -1,30 +1,174 @@
 use std::{
     cell::RefCell,
+    collections::BTreeMap,
     fmt::{self, Display},
     rc::Rc,
+    sync::Arc,
 };
 
-use crate::parser::ast::statement::Statement;
+use crate::parser::{
+    ast::{annotation::TypeAnnotation, expression::Parameter, statement::Statement},
+    literal::quote_string,
+};
 
-use super::environment::Environment;
+use super::{builtins, environment::Environment};
 
+/// Runtime value produced by the evaluator.
+///
+/// Objects are cloned on every environment lookup, so anything that owns heap
+/// data (function bodies today, collections later) keeps it behind a reference
+/// count and cloning only bumps it. Function bodies are `Arc`s shared with the
+/// AST they were created from, which keeps `Program` shareable across threads.
 #[derive(PartialEq, Clone)]
 pub enum Object {
     Integer(i64),
     Boolean(bool),
+    String(Rc<str>),
     ReturnValue(Box<Object>),
     Null,
     Function {
-        parameters: Vec<String>,
-        body: Vec<Statement>,
+        parameters: Arc<[Parameter]>,
+        result: Option<TypeAnnotation>,
+        body: Arc<[Statement]>,
         environment: Rc<RefCell<Environment>>,
     },
+    /// The exports of an imported module, shared by every import of it.
+    Module {
+        name: String,
+        exports: Rc<BTreeMap<String, Object>>,
+    },
+    Array(Rc<[Object]>),
+    /// A function implemented by the interpreter, by name.
+    Builtin(&'static str),
 }
 
 impl Object {
     pub fn return_value(value: Object) -> Self {
         Object::ReturnValue(Box::new(value))
     }
+
+    pub fn string(string: impl Into<Rc<str>>) -> Self {
+        Object::String(string.into())
+    }
+
+    pub fn array(elements: impl Into<Rc<[Object]>>) -> Self {
+        Object::Array(elements.into())
+    }
+
+    pub fn function(
+        parameters: impl Into<Arc<[Parameter]>>,
+        body: impl Into<Arc<[Statement]>>,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Self {
+        Object::Function {
+            parameters: parameters.into(),
+            result: None,
+            body: body.into(),
+            environment,
+        }
+    }
+
+    /// Whether the object is a value of the annotated type. Only the
+    /// parameter count of a function and the result type it declares are
+    /// known before it is called, and only the argument count of a builtin.
+    pub fn matches(&self, annotation: &TypeAnnotation) -> bool {
+        match (self, annotation) {
+            (Object::Integer(_), TypeAnnotation::Int)
+            | (Object::Boolean(_), TypeAnnotation::Bool)
+            | (Object::String(_), TypeAnnotation::String)
+            | (Object::Null, TypeAnnotation::Null) => true,
+            (
+                Object::Function {
+                    parameters, result, ..
+                },
+                TypeAnnotation::Function {
+                    parameters: annotated,
+                    result: annotated_result,
+                },
+            ) => {
+                parameters.len() == annotated.len()
+                    && result
+                        .as_ref()
+                        .is_none_or(|result| result == &**annotated_result)
+            }
+            (Object::Builtin(name), TypeAnnotation::Function { parameters, .. }) => {
+                builtins::arity(name) == Some(parameters.len())
+            }
+            (Object::ReturnValue(value), annotation) => value.matches(annotation),
+            _ => false,
+        }
+    }
+
+    /// The object with the result type of a function annotation, so that
+    /// calling the function checks what it returns. Functions declaring a
+    /// result type of their own and other objects are kept as they are.
+    pub fn annotated(self, annotation: &TypeAnnotation) -> Object {
+        match (self, annotation) {
+            (
+                Object::Function {
+                    parameters,
+                    result: None,
+                    body,
+                    environment,
+                },
+                TypeAnnotation::Function { result, .. },
+            ) => Object::Function {
+                parameters,
+                result: Some((**result).clone()),
+                body,
+                environment,
+            },
+            (object, _) => object,
+        }
+    }
+
+    /// Describes the type of the object for error messages.
+    pub fn type_name(&self) -> String {
+        match self {
+            Object::Integer(_) => "int".to_string(),
+            Object::Boolean(_) => "bool".to_string(),
+            Object::String(_) => "string".to_string(),
+            Object::Null => "null".to_string(),
+            Object::ReturnValue(value) => value.type_name(),
+            Object::Function {
+                parameters, result, ..
+            } => {
+                let mut name = match parameters.len() {
+                    1 => "function with 1 parameter".to_string(),
+                    count => format!("function with {} parameters", count),
+                };
+                if let Some(result) = result {
+                    name.push_str(&format!(" returning {}", result));
+                }
+                name
+            }
+            Object::Module { .. } => "module".to_string(),
+            Object::Array(_) => "array".to_string(),
+            Object::Builtin(_) => "builtin function".to_string(),
+        }
+    }
+
+    /// The object on one line; functions show their signature without the
+    /// body, and strings are quoted.
+    pub fn summary(&self) -> String {
+        match self {
+            Object::String(string) => quote_string(string),
+            Object::Function {
+                parameters, result, ..
+            } => {
+                let parameters: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+                match result {
+                    Some(result) => format!("fn({}) -> {}", parameters.join(", "), result),
+                    None => format!("fn({})", parameters.join(", ")),
+                }
+            }
+            Object::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.summary()).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            value => value.to_string(),
+        }
+    }
 }
 
 impl Display for Object {
@@ -32,28 +176,41 @@ impl Display for Object {
         match self {
             Object::Integer(value) => write!(f, "{}", value),
             Object::Boolean(value) => write!(f, "{}", value),
+            Object::String(value) => write!(f, "{}", value),
             Object::ReturnValue(value) => write!(f, "{}", *value),
             Object::Null => write!(f, "null"),
             Object::Function {
-                body, parameters, ..
+                body,
+                parameters,
+                result: annotation,
+                ..
             } => {
                 let mut result = String::new();
                 result.push_str("fn");
                 result.push('(');
                 for (i, parameter) in parameters.iter().enumerate() {
-                    result.push_str(&parameter);
+                    result.push_str(&parameter.to_string());
                     if i != parameters.len() - 1 {
                         result.push_str(", ");
                     }
                 }
                 result.push(')');
+                if let Some(annotation) = annotation {
+                    result.push_str(&format!(" -> {}", annotation));
+                }
                 result.push_str(" {\n");
-                for statement in body {
+                for statement in body.iter() {
                     result.push_str(&format!("{}\n", statement));
                 }
-                result.push_str("}");
+                result.push('}');
                 write!(f, "{}", result)
             }
+            Object::Module { name, .. } => write!(f, "module {}", name),
+            Object::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Object::Builtin(name) => write!(f, "builtin {}", name),
         }
     }
 }
@@ -63,6 +220,7 @@ impl fmt::Debug for Object {
         match self {
             Object::Integer(value) => write!(f, "Integer({})", value),
             Object::Boolean(value) => write!(f, "Boolean({})", value),
+            Object::String(value) => write!(f, "String({:?})", value),
             Object::ReturnValue(value) => write!(f, "ReturnValue({:?})", *value),
             Object::Null => write!(f, "Null"),
             Object::Function {
@@ -74,6 +232,14 @@ impl fmt::Debug for Object {
                     parameters, body
                 )
             }
+            Object::Module { name, exports } => write!(
+                f,
+                "Module {{ name: {:?}, exports: {:?} }}",
+                name,
+                exports.keys().collect::<Vec<&String>>()
+            ),
+            Object::Array(elements) => write!(f, "Array({:?})", elements),
+            Object::Builtin(name) => write!(f, "Builtin({})", name),
         }
     }
 }
@@ -91,5 +257,18 @@ mod tests {
             "1"
         );
         assert_eq!(format!("{}", Object::Null), "null");
+        assert_eq!(
+            format!(
+                "{}",
+                Object::array(vec![Object::Integer(1), Object::array(vec![])])
+            ),
+            "[1, []]"
+        );
+        assert_eq!(format!("{}", Object::Builtin("len")), "builtin len");
+        assert_eq!(format!("{}", Object::string("a \"b\"")), "a \"b\"");
+        assert_eq!(
+            Object::array(vec![Object::string("a\n"), Object::Integer(1)]).summary(),
+            "[\"a\\n\", 1]"
+        );
     }
 }
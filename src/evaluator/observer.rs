@@ -0,0 +1,80 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::parser::ast::{expression::Expression, statement::Statement};
+
+use super::{environment::Environment, evaluator::EvaluationError, object::Object};
+
+/// Follows an evaluation, like a debugger, tracer or profiler. The evaluator
+/// only does the extra work of calling observers while one is attached, and
+/// every method does nothing unless overridden.
+pub trait EvalObserver {
+    /// Called before each statement runs, with the environment it runs in.
+    /// An error stops the evaluation.
+    fn on_statement(
+        &mut self,
+        _statement: &Statement,
+        _environment: &Rc<RefCell<Environment>>,
+    ) -> Result<(), EvaluationError> {
+        Ok(())
+    }
+
+    /// Called once the arguments of a call are evaluated, before the body
+    /// runs. `callee` is the expression the function came from.
+    fn on_call(&mut self, _callee: &Expression, _function: &Object, _arguments: &[Object]) {}
+
+    /// Called when the body of a call returns or fails.
+    fn on_return(
+        &mut self,
+        _callee: &Expression,
+        _function: &Object,
+        _result: Result<&Object, &EvaluationError>,
+    ) {
+    }
+
+    /// Called when an `if` picks its consequence, or with `taken` false its
+    /// alternative, whether there is one or not.
+    fn on_branch(&mut self, _condition: &Expression, _taken: bool) {}
+
+    /// Called when a `let` binds a name.
+    fn on_bind(&mut self, _name: &str, _value: &Object) {}
+
+    /// Called once when an evaluation fails, with the error it returns.
+    fn on_error(&mut self, _error: &EvaluationError) {}
+}
+
+/// Lets the host keep a handle on an observer it attached, to read what it
+/// recorded afterwards.
+impl<T: EvalObserver> EvalObserver for Rc<RefCell<T>> {
+    fn on_statement(
+        &mut self,
+        statement: &Statement,
+        environment: &Rc<RefCell<Environment>>,
+    ) -> Result<(), EvaluationError> {
+        self.borrow_mut().on_statement(statement, environment)
+    }
+
+    fn on_call(&mut self, callee: &Expression, function: &Object, arguments: &[Object]) {
+        self.borrow_mut().on_call(callee, function, arguments)
+    }
+
+    fn on_return(
+        &mut self,
+        callee: &Expression,
+        function: &Object,
+        result: Result<&Object, &EvaluationError>,
+    ) {
+        self.borrow_mut().on_return(callee, function, result)
+    }
+
+    fn on_branch(&mut self, condition: &Expression, taken: bool) {
+        self.borrow_mut().on_branch(condition, taken)
+    }
+
+    fn on_bind(&mut self, name: &str, value: &Object) {
+        self.borrow_mut().on_bind(name, value)
+    }
+
+    fn on_error(&mut self, error: &EvaluationError) {
+        self.borrow_mut().on_error(error)
+    }
+}
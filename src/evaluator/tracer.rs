@@ -0,0 +1,126 @@
+use std::io::Write;
+
+use crate::parser::ast::expression::Expression;
+
+use super::{evaluator::EvaluationError, object::Object, observer::EvalObserver};
+
+/// Writes a line to `output` for every function call, return and `let`
+/// binding, indented by the call depth.
+pub struct Tracer<W: Write> {
+    output: W,
+    depth: usize,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(output: W) -> Self {
+        Tracer { output, depth: 0 }
+    }
+
+    fn say(&mut self, text: &str) {
+        writeln!(self.output, "{}{}", "  ".repeat(self.depth), text)
+            .expect("failed to write trace output");
+    }
+}
+
+impl<W: Write> EvalObserver for Tracer<W> {
+    fn on_call(&mut self, callee: &Expression, function: &Object, arguments: &[Object]) {
+        let arguments: Vec<String> = arguments.iter().map(Object::summary).collect();
+        self.say(&format!(
+            "→ {}({})",
+            name(callee, function),
+            arguments.join(", ")
+        ));
+        self.depth += 1;
+    }
+
+    fn on_return(
+        &mut self,
+        callee: &Expression,
+        function: &Object,
+        result: Result<&Object, &EvaluationError>,
+    ) {
+        self.depth -= 1;
+        let text = match result {
+            Ok(value) => format!("← {} = {}", name(callee, function), value.summary()),
+            Err(err) => format!("← {} failed: {}", name(callee, function), err),
+        };
+        self.say(&text);
+    }
+
+    fn on_bind(&mut self, name: &str, value: &Object) {
+        self.say(&format!("let {} = {}", name, value.summary()));
+    }
+}
+
+/// What a call is shown as: function literals print their signature rather
+/// than their whole body.
+fn name(callee: &Expression, function: &Object) -> String {
+    match callee {
+        Expression::Function { .. } => function.summary(),
+        callee => callee.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, io::Write, rc::Rc};
+
+    use indoc::indoc;
+
+    use crate::{
+        evaluator::{environment::Environment, evaluator::Evaluator},
+        lexer::lexer::Lexer,
+        parser::parser::Parser,
+    };
+
+    use super::Tracer;
+
+    /// Output shared with the test once the tracer is attached.
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let input = indoc! {"
+            let twice = fn(f, x) { f(f(x)) };
+            let a = twice(fn(x) { let y = x + 1; y }, 1);
+            twice(fn(x) -> int { x == 1 }, a)
+        "};
+        let program = Parser::new(Lexer::new(input)).parse_program();
+
+        let output = Output::default();
+        let mut evaluator = Evaluator::new();
+        evaluator.attach(Box::new(Tracer::new(output.clone())));
+        assert!(evaluator.eval(&program, Environment::new()).is_err());
+
+        assert_eq!(
+            String::from_utf8(output.0.take()).unwrap(),
+            indoc! {"
+                let twice = fn(f, x)
+                → twice(fn(x), 1)
+                  → f(1)
+                    let y = 2
+                  ← f = 2
+                  → f(2)
+                    let y = 3
+                  ← f = 3
+                ← twice = 3
+                let a = 3
+                → twice(fn(x) -> int, 3)
+                  → f(3)
+                  ← f failed: type mismatch for return value: expected int, got bool
+                ← twice failed: type mismatch for return value: expected int, got bool
+            "}
+        );
+    }
+}
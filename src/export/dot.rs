@@ -0,0 +1,289 @@
+use crate::parser::{
+    ast::{
+        expression::{Expression, TemplatePart},
+        program::Program,
+        statement::{ImportBinding, Statement},
+    },
+    literal::quote_string,
+    source_map::SourceMap,
+};
+
+/// Renders `program` as a Graphviz digraph with one box per node.
+///
+/// Edges are labelled with the child's role (`lhs`, `rhs`, `condition`, ...)
+/// and nodes found in `source_map` show their span, which makes it easy to
+/// see how precedence shaped the tree.
+pub fn to_dot(program: &Program, source_map: Option<&SourceMap>) -> String {
+    let mut graph = Graph {
+        source_map,
+        lines: vec![],
+        next_id: 0,
+    };
+
+    let root = graph.node("Program", None);
+    graph.statements(root, &program.statements);
+
+    let mut output = String::from("digraph ast {\n    node [shape=box];\n");
+    for line in graph.lines {
+        output.push_str("    ");
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output.push('}');
+    output
+}
+
+struct Graph<'a> {
+    source_map: Option<&'a SourceMap<'a>>,
+    lines: Vec<String>,
+    next_id: usize,
+}
+
+impl Graph<'_> {
+    fn node(&mut self, label: &str, span: Option<String>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let label = match span {
+            Some(span) => format!("{}\\n{}", escape(label), span),
+            None => escape(label),
+        };
+        self.lines.push(format!("n{} [label=\"{}\"];", id, label));
+
+        id
+    }
+
+    fn edge(&mut self, parent: usize, child: usize, role: &str) {
+        if role.is_empty() {
+            self.lines.push(format!("n{} -> n{};", parent, child));
+        } else {
+            self.lines
+                .push(format!("n{} -> n{} [label=\"{}\"];", parent, child, role));
+        }
+    }
+
+    fn statements(&mut self, parent: usize, statements: &[Statement]) {
+        for statement in statements {
+            let child = self.statement(statement);
+            self.edge(parent, child, "");
+        }
+    }
+
+    fn block(&mut self, parent: usize, statements: &[Statement], role: &str) {
+        let block = self.node("Block", None);
+        self.edge(parent, block, role);
+        self.statements(block, statements);
+    }
+
+    fn statement(&mut self, statement: &Statement) -> usize {
+        let span = self
+            .source_map
+            .and_then(|source_map| source_map.statement(statement))
+            .map(|span| span.to_string());
+
+        match statement {
+            Statement::Let {
+                name,
+                annotation,
+                value,
+            } => {
+                let label = match annotation {
+                    Some(annotation) => format!("Let {}: {}", name, annotation),
+                    None => format!("Let {}", name),
+                };
+                let id = self.node(&label, span);
+                let value = self.expression(value);
+                self.edge(id, value, "value");
+                id
+            }
+            Statement::Return { value } => {
+                let id = self.node("Return", span);
+                let value = self.expression(value);
+                self.edge(id, value, "value");
+                id
+            }
+            Statement::Expression(expression) => {
+                let id = self.node("ExpressionStatement", span);
+                let expression = self.expression(expression);
+                self.edge(id, expression, "");
+                id
+            }
+            Statement::Block(statements) => {
+                let id = self.node("Block", span);
+                self.statements(id, statements);
+                id
+            }
+            Statement::Import {
+                path,
+                binding: ImportBinding::Module(name),
+            } => self.node(&format!("Import {} as {}", quote_string(path), name), span),
+            Statement::Import {
+                path,
+                binding: ImportBinding::Names(names),
+            } => self.node(
+                &format!("Import {} from {}", names.join(", "), quote_string(path)),
+                span,
+            ),
+            Statement::Export(names) => self.node(&format!("Export {}", names.join(", ")), span),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> usize {
+        let span = self
+            .source_map
+            .and_then(|source_map| source_map.expression(expression))
+            .map(|span| span.to_string());
+
+        match expression {
+            Expression::Int(integer) => self.node(&format!("Int {}", integer), span),
+            Expression::Bool(boolean) => self.node(&format!("Bool {}", boolean), span),
+            Expression::String(string) => {
+                self.node(&format!("String {}", quote_string(string)), span)
+            }
+            Expression::Identifier(name) => self.node(&format!("Identifier {}", name), span),
+            Expression::Null => self.node("Null", span),
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let id = self.node("If", span);
+                let condition = self.expression(condition);
+                self.edge(id, condition, "condition");
+                self.block(id, consequence, "consequence");
+                if let Some(alternative) = alternative {
+                    self.block(id, alternative, "alternative");
+                }
+                id
+            }
+            Expression::Function {
+                parameters,
+                result,
+                body,
+            } => {
+                let parameters: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+                let mut label = format!("Function({})", parameters.join(", "));
+                if let Some(result) = result {
+                    label.push_str(&format!(" -> {}", result));
+                }
+                let id = self.node(&label, span);
+                self.block(id, body, "body");
+                id
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                let id = self.node("Call", span);
+                let function = self.expression(function);
+                self.edge(id, function, "function");
+                for (i, argument) in arguments.iter().enumerate() {
+                    let argument = self.expression(argument);
+                    self.edge(id, argument, &format!("argument {}", i));
+                }
+                id
+            }
+            Expression::Prefix { rhs, operator } => {
+                let id = self.node(&format!("Prefix {}", operator), span);
+                let rhs = self.expression(rhs);
+                self.edge(id, rhs, "rhs");
+                id
+            }
+            Expression::Infix { lhs, operator, rhs } => {
+                let id = self.node(&format!("Infix {}", operator), span);
+                let lhs = self.expression(lhs);
+                self.edge(id, lhs, "lhs");
+                let rhs = self.expression(rhs);
+                self.edge(id, rhs, "rhs");
+                id
+            }
+            Expression::Member { object, name } => {
+                let id = self.node(&format!("Member {}", name), span);
+                let object = self.expression(object);
+                self.edge(id, object, "object");
+                id
+            }
+            Expression::Array(elements) => {
+                let id = self.node("Array", span);
+                for (i, element) in elements.iter().enumerate() {
+                    let element = self.expression(element);
+                    self.edge(id, element, &format!("element {}", i));
+                }
+                id
+            }
+            Expression::Template(parts) => {
+                let id = self.node("Template", span);
+                for (i, part) in parts.iter().enumerate() {
+                    let part = match part {
+                        TemplatePart::Text(text) => {
+                            self.node(&format!("Text {}", quote_string(text)), None)
+                        }
+                        TemplatePart::Expression(expression) => self.expression(expression),
+                    };
+                    self.edge(id, part, &format!("part {}", i));
+                }
+                id
+            }
+            Expression::Index { left, index } => {
+                let id = self.node("Index", span);
+                let left = self.expression(left);
+                self.edge(id, left, "left");
+                let index = self.expression(index);
+                self.edge(id, index, "index");
+                id
+            }
+        }
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{lexer::lexer::Lexer, parser::parser::Parser};
+
+    use super::to_dot;
+
+    #[test]
+    fn test_to_dot() {
+        let mut parser = Parser::new(Lexer::new("1 + 2 * x"));
+        let program = parser.parse_program();
+        let source_map = parser.source_map(&program);
+
+        assert_eq!(
+            to_dot(&program, Some(&source_map)),
+            indoc! {r#"
+                digraph ast {
+                    node [shape=box];
+                    n0 [label="Program"];
+                    n1 [label="ExpressionStatement\n@1:1-1:9"];
+                    n2 [label="Infix +\n@1:1-1:9"];
+                    n3 [label="Int 1\n@1:1-1:1"];
+                    n2 -> n3 [label="lhs"];
+                    n4 [label="Infix *\n@1:5-1:9"];
+                    n5 [label="Int 2\n@1:5-1:5"];
+                    n4 -> n5 [label="lhs"];
+                    n6 [label="Identifier x\n@1:9-1:9"];
+                    n4 -> n6 [label="rhs"];
+                    n2 -> n4 [label="rhs"];
+                    n1 -> n2;
+                    n0 -> n1;
+                }"#}
+        );
+    }
+
+    #[test]
+    fn test_to_dot_without_spans() {
+        let mut parser = Parser::new(Lexer::new("if (a) { f(b) }"));
+        let program = parser.parse_program();
+        let dot = to_dot(&program, None);
+
+        assert!(dot.contains("n2 [label=\"If\"];"));
+        assert!(dot.contains("n2 -> n4 [label=\"consequence\"];"));
+        assert!(dot.contains("n6 -> n8 [label=\"argument 0\"];"));
+    }
+}
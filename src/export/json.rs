@@ -0,0 +1,608 @@
+use std::fmt::Display;
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    lexer::location::Span,
+    parser::{
+        ast::{
+            annotation::TypeAnnotation,
+            expression::{Expression, Parameter, TemplatePart},
+            operator::{InfixOperator, PrefixOperator},
+            program::Program,
+            statement::{ImportBinding, Statement},
+        },
+        source_map::SourceMap,
+    },
+};
+
+#[derive(Debug, PartialEq)]
+pub struct JsonError {
+    msg: String,
+}
+
+impl JsonError {
+    fn new(msg: impl Into<String>) -> Self {
+        JsonError { msg: msg.into() }
+    }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[JsonError] {}", self.msg)
+    }
+}
+
+/// Serializes `program` as a JSON tree where every node is an object with a
+/// `kind`. Nodes found in `source_map` also get a `span`.
+pub fn to_json(program: &Program, source_map: Option<&SourceMap>) -> Value {
+    let writer = Writer { source_map };
+
+    json!({
+        "kind": "Program",
+        "statements": writer.statements(&program.statements),
+    })
+}
+
+/// Rebuilds a `Program` from the output of `to_json`. Spans are ignored.
+pub fn from_json(value: &Value) -> Result<Program, JsonError> {
+    if kind(value)? != "Program" {
+        return Err(JsonError::new("expected a Program at the root"));
+    }
+
+    Ok(Program {
+        statements: read_statements(field(value, "statements")?)?,
+    })
+}
+
+struct Writer<'a> {
+    source_map: Option<&'a SourceMap<'a>>,
+}
+
+impl Writer<'_> {
+    fn statements(&self, statements: &[Statement]) -> Value {
+        Value::Array(
+            statements
+                .iter()
+                .map(|statement| self.statement(statement))
+                .collect(),
+        )
+    }
+
+    fn statement(&self, statement: &Statement) -> Value {
+        let value = match statement {
+            Statement::Let {
+                name,
+                annotation,
+                value,
+            } => {
+                let mut value = json!({
+                    "kind": "Let",
+                    "name": name,
+                    "value": self.expression(value),
+                });
+                if let (Some(annotation), Value::Object(object)) = (annotation, &mut value) {
+                    object.insert("type".to_string(), annotation_json(annotation));
+                }
+                value
+            }
+            Statement::Return { value } => json!({
+                "kind": "Return",
+                "value": self.expression(value),
+            }),
+            Statement::Expression(expression) => json!({
+                "kind": "ExpressionStatement",
+                "expression": self.expression(expression),
+            }),
+            Statement::Block(statements) => json!({
+                "kind": "Block",
+                "statements": self.statements(statements),
+            }),
+            Statement::Import {
+                path,
+                binding: ImportBinding::Module(name),
+            } => json!({
+                "kind": "Import",
+                "path": path,
+                "alias": name,
+            }),
+            Statement::Import {
+                path,
+                binding: ImportBinding::Names(names),
+            } => json!({
+                "kind": "Import",
+                "path": path,
+                "names": names,
+            }),
+            Statement::Export(names) => json!({
+                "kind": "Export",
+                "names": names,
+            }),
+        };
+
+        with_span(
+            value,
+            self.source_map
+                .and_then(|source_map| source_map.statement(statement)),
+        )
+    }
+
+    fn expression(&self, expression: &Expression) -> Value {
+        let value = match expression {
+            Expression::Int(integer) => json!({ "kind": "Int", "value": integer }),
+            Expression::Bool(boolean) => json!({ "kind": "Bool", "value": boolean }),
+            Expression::String(string) => json!({ "kind": "String", "value": string }),
+            Expression::Identifier(name) => json!({ "kind": "Identifier", "name": name }),
+            Expression::Null => json!({ "kind": "Null" }),
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => json!({
+                "kind": "If",
+                "condition": self.expression(condition),
+                "consequence": self.statements(consequence),
+                "alternative": alternative
+                    .as_ref()
+                    .map(|alternative| self.statements(alternative)),
+            }),
+            Expression::Function {
+                parameters,
+                result,
+                body,
+            } => {
+                let mut value = json!({
+                    "kind": "Function",
+                    "parameters": parameters.iter().map(parameter_json).collect::<Vec<Value>>(),
+                    "body": self.statements(body),
+                });
+                if let (Some(result), Value::Object(object)) = (result, &mut value) {
+                    object.insert("result".to_string(), annotation_json(result));
+                }
+                value
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => json!({
+                "kind": "Call",
+                "function": self.expression(function),
+                "arguments": arguments
+                    .iter()
+                    .map(|argument| self.expression(argument))
+                    .collect::<Vec<Value>>(),
+            }),
+            Expression::Prefix { rhs, operator } => json!({
+                "kind": "Prefix",
+                "operator": operator.to_string(),
+                "rhs": self.expression(rhs),
+            }),
+            Expression::Infix { lhs, operator, rhs } => json!({
+                "kind": "Infix",
+                "operator": operator.to_string(),
+                "lhs": self.expression(lhs),
+                "rhs": self.expression(rhs),
+            }),
+            Expression::Member { object, name } => json!({
+                "kind": "Member",
+                "object": self.expression(object),
+                "name": name,
+            }),
+            Expression::Array(elements) => json!({
+                "kind": "Array",
+                "elements": elements
+                    .iter()
+                    .map(|element| self.expression(element))
+                    .collect::<Vec<Value>>(),
+            }),
+            Expression::Template(parts) => json!({
+                "kind": "Template",
+                "parts": parts
+                    .iter()
+                    .map(|part| match part {
+                        TemplatePart::Text(text) => json!(text),
+                        TemplatePart::Expression(expression) => self.expression(expression),
+                    })
+                    .collect::<Vec<Value>>(),
+            }),
+            Expression::Index { left, index } => json!({
+                "kind": "Index",
+                "left": self.expression(left),
+                "index": self.expression(index),
+            }),
+        };
+
+        with_span(
+            value,
+            self.source_map
+                .and_then(|source_map| source_map.expression(expression)),
+        )
+    }
+}
+
+/// An unannotated parameter is just its name, as before annotations existed.
+fn parameter_json(parameter: &Parameter) -> Value {
+    match &parameter.annotation {
+        Some(annotation) => json!({
+            "name": parameter.name,
+            "type": annotation_json(annotation),
+        }),
+        None => json!(parameter.name),
+    }
+}
+
+fn annotation_json(annotation: &TypeAnnotation) -> Value {
+    match annotation {
+        TypeAnnotation::Function { parameters, result } => json!({
+            "parameters": parameters.iter().map(annotation_json).collect::<Vec<Value>>(),
+            "result": annotation_json(result),
+        }),
+        annotation => json!(annotation.to_string()),
+    }
+}
+
+fn with_span(mut value: Value, span: Option<&Span>) -> Value {
+    if let (Some(span), Value::Object(object)) = (span, &mut value) {
+        object.insert(
+            "span".to_string(),
+            json!({
+                "start": { "line": span.start.line(), "column": span.start.column() },
+                "end": { "line": span.end.line(), "column": span.end.column() },
+            }),
+        );
+    }
+
+    value
+}
+
+fn read_statements(value: &Value) -> Result<Vec<Statement>, JsonError> {
+    array(value)?.iter().map(read_statement).collect()
+}
+
+fn read_statement(value: &Value) -> Result<Statement, JsonError> {
+    match kind(value)? {
+        "Let" => Ok(Statement::annotated_let(
+            string(field(value, "name")?)?,
+            optional(value, "type", read_annotation)?,
+            read_expression(field(value, "value")?)?,
+        )),
+        "Return" => Ok(Statement::r#return(read_expression(field(
+            value, "value",
+        )?)?)),
+        "ExpressionStatement" => Ok(Statement::expression(read_expression(field(
+            value,
+            "expression",
+        )?)?)),
+        "Block" => Ok(Statement::Block(read_statements(field(
+            value,
+            "statements",
+        )?)?)),
+        "Import" => {
+            let binding = match object(value)?.get("alias") {
+                Some(alias) => ImportBinding::Module(string(alias)?.to_string()),
+                None => ImportBinding::Names(read_names(field(value, "names")?)?),
+            };
+            Ok(Statement::import(string(field(value, "path")?)?, binding))
+        }
+        "Export" => Ok(Statement::Export(read_names(field(value, "names")?)?)),
+        kind => Err(JsonError::new(format!("unknown statement kind {}", kind))),
+    }
+}
+
+fn read_expression(value: &Value) -> Result<Expression, JsonError> {
+    match kind(value)? {
+        "Int" => field(value, "value")?
+            .as_i64()
+            .map(Expression::Int)
+            .ok_or_else(|| JsonError::new("expected an integer value")),
+        "Bool" => field(value, "value")?
+            .as_bool()
+            .map(Expression::Bool)
+            .ok_or_else(|| JsonError::new("expected a boolean value")),
+        "String" => Ok(Expression::String(
+            string(field(value, "value")?)?.to_string(),
+        )),
+        "Identifier" => Ok(Expression::identifier(string(field(value, "name")?)?)),
+        "Null" => Ok(Expression::Null),
+        "If" => {
+            let alternative = match object(value)?.get("alternative") {
+                None | Some(Value::Null) => None,
+                Some(alternative) => Some(read_statements(alternative)?),
+            };
+            Ok(Expression::r#if(
+                read_expression(field(value, "condition")?)?,
+                read_statements(field(value, "consequence")?)?,
+                alternative,
+            ))
+        }
+        "Function" => Ok(Expression::annotated_function(
+            array(field(value, "parameters")?)?
+                .iter()
+                .map(read_parameter)
+                .collect::<Result<Vec<Parameter>, JsonError>>()?,
+            optional(value, "result", read_annotation)?,
+            read_statements(field(value, "body")?)?,
+        )),
+        "Call" => Ok(Expression::call(
+            read_expression(field(value, "function")?)?,
+            array(field(value, "arguments")?)?
+                .iter()
+                .map(read_expression)
+                .collect::<Result<_, _>>()?,
+        )),
+        "Prefix" => {
+            let operator = match string(field(value, "operator")?)? {
+                "!" => PrefixOperator::Not,
+                "-" => PrefixOperator::Negative,
+                operator => {
+                    return Err(JsonError::new(format!(
+                        "unknown prefix operator {}",
+                        operator
+                    )))
+                }
+            };
+            Ok(Expression::prefix(
+                read_expression(field(value, "rhs")?)?,
+                operator,
+            ))
+        }
+        "Infix" => {
+            let operator = match string(field(value, "operator")?)? {
+                "+" => InfixOperator::Add,
+                "-" => InfixOperator::Sub,
+                "*" => InfixOperator::Mult,
+                "/" => InfixOperator::Div,
+                "%" => InfixOperator::Modulo,
+                "==" => InfixOperator::Equal,
+                "!=" => InfixOperator::NotEqual,
+                ">" => InfixOperator::GreaterThan,
+                "<" => InfixOperator::LessThan,
+                operator => {
+                    return Err(JsonError::new(format!(
+                        "unknown infix operator {}",
+                        operator
+                    )))
+                }
+            };
+            Ok(Expression::infix(
+                read_expression(field(value, "lhs")?)?,
+                read_expression(field(value, "rhs")?)?,
+                operator,
+            ))
+        }
+        "Member" => Ok(Expression::member(
+            read_expression(field(value, "object")?)?,
+            string(field(value, "name")?)?,
+        )),
+        "Array" => Ok(Expression::Array(
+            array(field(value, "elements")?)?
+                .iter()
+                .map(read_expression)
+                .collect::<Result<_, _>>()?,
+        )),
+        "Template" => Ok(Expression::Template(
+            array(field(value, "parts")?)?
+                .iter()
+                .map(read_template_part)
+                .collect::<Result<_, _>>()?,
+        )),
+        "Index" => Ok(Expression::index(
+            read_expression(field(value, "left")?)?,
+            read_expression(field(value, "index")?)?,
+        )),
+        kind => Err(JsonError::new(format!("unknown expression kind {}", kind))),
+    }
+}
+
+fn read_names(value: &Value) -> Result<Vec<String>, JsonError> {
+    array(value)?
+        .iter()
+        .map(|name| string(name).map(str::to_string))
+        .collect()
+}
+
+fn read_parameter(value: &Value) -> Result<Parameter, JsonError> {
+    match value {
+        Value::String(name) => Ok(Parameter::from(name.as_str())),
+        value => Ok(Parameter::new(
+            string(field(value, "name")?)?,
+            Some(read_annotation(field(value, "type")?)?),
+        )),
+    }
+}
+
+fn read_template_part(value: &Value) -> Result<TemplatePart, JsonError> {
+    match value {
+        Value::String(text) => Ok(TemplatePart::Text(text.clone())),
+        value => Ok(TemplatePart::Expression(read_expression(value)?)),
+    }
+}
+
+fn read_annotation(value: &Value) -> Result<TypeAnnotation, JsonError> {
+    match value {
+        Value::String(name) => match name.as_str() {
+            "int" => Ok(TypeAnnotation::Int),
+            "bool" => Ok(TypeAnnotation::Bool),
+            "string" => Ok(TypeAnnotation::String),
+            "null" => Ok(TypeAnnotation::Null),
+            name => Err(JsonError::new(format!("unknown type {}", name))),
+        },
+        value => Ok(TypeAnnotation::function(
+            array(field(value, "parameters")?)?
+                .iter()
+                .map(read_annotation)
+                .collect::<Result<_, _>>()?,
+            read_annotation(field(value, "result")?)?,
+        )),
+    }
+}
+
+/// Reads `name` with `read`, treating a missing field or `null` as absent.
+fn optional<T>(
+    value: &Value,
+    name: &str,
+    read: impl Fn(&Value) -> Result<T, JsonError>,
+) -> Result<Option<T>, JsonError> {
+    match object(value)?.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => read(value).map(Some),
+    }
+}
+
+fn object(value: &Value) -> Result<&Map<String, Value>, JsonError> {
+    value
+        .as_object()
+        .ok_or_else(|| JsonError::new(format!("expected an object, got {}", value)))
+}
+
+fn field<'a>(value: &'a Value, name: &str) -> Result<&'a Value, JsonError> {
+    object(value)?
+        .get(name)
+        .ok_or_else(|| JsonError::new(format!("missing field {}", name)))
+}
+
+fn kind(value: &Value) -> Result<&str, JsonError> {
+    string(field(value, "kind")?)
+}
+
+fn string(value: &Value) -> Result<&str, JsonError> {
+    value
+        .as_str()
+        .ok_or_else(|| JsonError::new(format!("expected a string, got {}", value)))
+}
+
+fn array(value: &Value) -> Result<&Vec<Value>, JsonError> {
+    value
+        .as_array()
+        .ok_or_else(|| JsonError::new(format!("expected an array, got {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use serde_json::json;
+
+    use crate::{lexer::lexer::Lexer, parser::parser::Parser};
+
+    use super::{from_json, to_json, JsonError};
+
+    #[test]
+    fn test_to_json_with_spans() {
+        let mut parser = Parser::new(Lexer::new("let x = -1 + y;"));
+        let program = parser.parse_program();
+        let source_map = parser.source_map(&program);
+
+        let span = |start: (usize, usize), end: (usize, usize)| {
+            json!({
+                "start": { "line": start.0, "column": start.1 },
+                "end": { "line": end.0, "column": end.1 },
+            })
+        };
+
+        assert_eq!(
+            to_json(&program, Some(&source_map)),
+            json!({
+                "kind": "Program",
+                "statements": [{
+                    "kind": "Let",
+                    "name": "x",
+                    "span": span((1, 1), (1, 15)),
+                    "value": {
+                        "kind": "Infix",
+                        "operator": "+",
+                        "span": span((1, 9), (1, 14)),
+                        "lhs": {
+                            "kind": "Prefix",
+                            "operator": "-",
+                            "span": span((1, 9), (1, 10)),
+                            "rhs": { "kind": "Int", "value": 1, "span": span((1, 10), (1, 10)) },
+                        },
+                        "rhs": { "kind": "Identifier", "name": "y", "span": span((1, 14), (1, 14)) },
+                    },
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut parser = Parser::new(Lexer::new(indoc! {"
+            let f = fn(a, b) {
+                if (a > b) { return a % b; } else { !true }
+            };
+            f(10, 3) != null;
+            [1, [f]][1][0];
+            let s: string = \"a\\\"b\" + \"größe\";
+            \"${s}: ${\"${f(1, 2)}\"}!\";
+        "}));
+        let program = parser.parse_program();
+        let source_map = parser.source_map(&program);
+
+        let loaded = from_json(&to_json(&program, Some(&source_map))).unwrap();
+        assert_eq!(loaded.statements, program.statements);
+    }
+
+    #[test]
+    fn test_modules_round_trip() {
+        let mut parser = Parser::new(Lexer::new(indoc! {r#"
+            import "lib/m\"q\".mk" as m;
+            import { a, b } from "x";
+            export a;
+            m.f(a).g;
+        "#}));
+        let program = parser.parse_program();
+        let value = to_json(&program, None);
+
+        assert_eq!(
+            value["statements"][1],
+            json!({ "kind": "Import", "path": "x", "names": ["a", "b"] })
+        );
+        assert_eq!(from_json(&value).unwrap().statements, program.statements);
+    }
+
+    #[test]
+    fn test_annotations_round_trip() {
+        let mut parser = Parser::new(Lexer::new(
+            "let f: fn(int, bool) -> int = fn(a: int, b) -> int { a };",
+        ));
+        let program = parser.parse_program();
+        let value = to_json(&program, None);
+
+        assert_eq!(
+            value["statements"][0]["type"],
+            json!({ "parameters": ["int", "bool"], "result": "int" })
+        );
+        assert_eq!(
+            value["statements"][0]["value"]["parameters"],
+            json!([{ "name": "a", "type": "int" }, "b"])
+        );
+        assert_eq!(from_json(&value).unwrap().statements, program.statements);
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        assert_eq!(
+            from_json(&json!({ "kind": "Statement" })).map(|_| ()),
+            Err(JsonError::new("expected a Program at the root"))
+        );
+        assert_eq!(
+            from_json(&json!({
+                "kind": "Program",
+                "statements": [{ "kind": "Return" }],
+            }))
+            .map(|_| ()),
+            Err(JsonError::new("missing field value"))
+        );
+        assert_eq!(
+            from_json(&json!({
+                "kind": "Program",
+                "statements": [{
+                    "kind": "ExpressionStatement",
+                    "expression": { "kind": "Prefix", "operator": "~", "rhs": { "kind": "Null" } },
+                }],
+            }))
+            .map(|_| ()),
+            Err(JsonError::new("unknown prefix operator ~"))
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod dot;
+pub mod json;
+pub mod sexpr;
@@ -0,0 +1,248 @@
+use crate::parser::{
+    ast::{
+        annotation::TypeAnnotation,
+        expression::{Expression, Parameter, TemplatePart},
+        program::Program,
+        statement::{ImportBinding, Statement},
+    },
+    literal::quote_string,
+};
+
+/// Renders `program` as an S-expression, one top-level statement per line.
+///
+/// Operators are written in prefix position, so `1 + 2 * 3` becomes
+/// `(+ 1 (* 2 3))` and the grouping chosen by the parser is explicit.
+pub fn to_sexpr(program: &Program) -> String {
+    let mut output = String::from("(program");
+
+    for statement in &program.statements {
+        output.push_str("\n  ");
+        output.push_str(&statement_sexpr(statement));
+    }
+
+    output.push(')');
+    output
+}
+
+fn statements_sexpr(head: &str, statements: &[Statement]) -> String {
+    let mut output = format!("({}", head);
+
+    for statement in statements {
+        output.push(' ');
+        output.push_str(&statement_sexpr(statement));
+    }
+
+    output.push(')');
+    output
+}
+
+fn statement_sexpr(statement: &Statement) -> String {
+    match statement {
+        Statement::Let {
+            name,
+            annotation,
+            value,
+        } => format!(
+            "(let {} {})",
+            annotated_sexpr(name, annotation.as_ref()),
+            expression_sexpr(value)
+        ),
+        Statement::Return { value } => format!("(return {})", expression_sexpr(value)),
+        Statement::Expression(expression) => expression_sexpr(expression),
+        Statement::Block(statements) => statements_sexpr("block", statements),
+        Statement::Import {
+            path,
+            binding: ImportBinding::Module(name),
+        } => format!("(import {} {})", quote_string(path), name),
+        Statement::Import {
+            path,
+            binding: ImportBinding::Names(names),
+        } => format!("(import {} ({}))", quote_string(path), names.join(" ")),
+        Statement::Export(names) => format!("(export {})", names.join(" ")),
+    }
+}
+
+fn expression_sexpr(expression: &Expression) -> String {
+    match expression {
+        Expression::Int(integer) => integer.to_string(),
+        Expression::Bool(boolean) => boolean.to_string(),
+        Expression::String(string) => quote_string(string),
+        Expression::Identifier(name) => name.clone(),
+        Expression::Null => "null".to_string(),
+        Expression::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            let mut output = format!(
+                "(if {} {}",
+                expression_sexpr(condition),
+                statements_sexpr("block", consequence)
+            );
+            if let Some(alternative) = alternative {
+                output.push(' ');
+                output.push_str(&statements_sexpr("block", alternative));
+            }
+            output.push(')');
+            output
+        }
+        Expression::Function {
+            parameters,
+            result,
+            body,
+        } => {
+            let parameters: Vec<String> = parameters
+                .iter()
+                .map(|Parameter { name, annotation }| annotated_sexpr(name, annotation.as_ref()))
+                .collect();
+            let result = result
+                .as_ref()
+                .map(|result| format!("(-> {}) ", type_sexpr(result)))
+                .unwrap_or_default();
+            format!(
+                "(fn ({}) {}{})",
+                parameters.join(" "),
+                result,
+                statements_sexpr("block", body)
+            )
+        }
+        Expression::Call {
+            function,
+            arguments,
+        } => {
+            let mut output = format!("(call {}", expression_sexpr(function));
+            for argument in arguments {
+                output.push(' ');
+                output.push_str(&expression_sexpr(argument));
+            }
+            output.push(')');
+            output
+        }
+        Expression::Prefix { rhs, operator } => {
+            format!("({} {})", operator, expression_sexpr(rhs))
+        }
+        Expression::Infix { lhs, operator, rhs } => format!(
+            "({} {} {})",
+            operator,
+            expression_sexpr(lhs),
+            expression_sexpr(rhs)
+        ),
+        Expression::Member { object, name } => format!("(. {} {})", expression_sexpr(object), name),
+        Expression::Array(elements) => {
+            let mut output = "(array".to_string();
+            for element in elements {
+                output.push(' ');
+                output.push_str(&expression_sexpr(element));
+            }
+            output.push(')');
+            output
+        }
+        Expression::Template(parts) => {
+            let mut output = "(template".to_string();
+            for part in parts {
+                output.push(' ');
+                match part {
+                    TemplatePart::Text(text) => output.push_str(&quote_string(text)),
+                    TemplatePart::Expression(expression) => {
+                        output.push_str(&expression_sexpr(expression))
+                    }
+                }
+            }
+            output.push(')');
+            output
+        }
+        Expression::Index { left, index } => format!(
+            "(index {} {})",
+            expression_sexpr(left),
+            expression_sexpr(index)
+        ),
+    }
+}
+
+/// A name, or `(: name type)` when it is annotated.
+fn annotated_sexpr(name: &str, annotation: Option<&TypeAnnotation>) -> String {
+    match annotation {
+        Some(annotation) => format!("(: {} {})", name, type_sexpr(annotation)),
+        None => name.to_string(),
+    }
+}
+
+fn type_sexpr(annotation: &TypeAnnotation) -> String {
+    match annotation {
+        TypeAnnotation::Function { parameters, result } => format!(
+            "(fn ({}) {})",
+            parameters
+                .iter()
+                .map(type_sexpr)
+                .collect::<Vec<String>>()
+                .join(" "),
+            type_sexpr(result)
+        ),
+        annotation => annotation.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{lexer::lexer::Lexer, parser::parser::Parser};
+
+    use super::to_sexpr;
+
+    #[test]
+    fn test_to_sexpr() {
+        let mut parser = Parser::new(Lexer::new(indoc! {"
+            let f = fn(a, b) { if (a < b) { return -a; } else { b } };
+            f(1 + 2 * 3, !true) == null;
+        "}));
+        let program = parser.parse_program();
+
+        assert_eq!(
+            to_sexpr(&program),
+            indoc! {"
+                (program
+                  (let f (fn (a b) (block (if (< a b) (block (return (- a))) (block b)))))
+                  (== (call f (+ 1 (* 2 3)) (! true)) null))"}
+        );
+    }
+
+    #[test]
+    fn test_arrays_to_sexpr() {
+        let mut parser = Parser::new(Lexer::new("[1, [a]][0]; []; \"a${b}\""));
+        let program = parser.parse_program();
+
+        assert_eq!(
+            to_sexpr(&program),
+            "(program\n  (index (array 1 (array a)) 0)\n  (array)\n  (template \"a\" b))"
+        );
+    }
+
+    #[test]
+    fn test_modules_to_sexpr() {
+        let mut parser = Parser::new(Lexer::new(
+            "import \"m\" as m; import { a, b } from \"x\"; export a; m.f(a).g",
+        ));
+        let program = parser.parse_program();
+
+        assert_eq!(
+            to_sexpr(&program),
+            "(program\n  (import \"m\" m)\n  (import \"x\" (a b))\n  (export a)\n  \
+             (. (call (. m f) a) g))"
+        );
+    }
+
+    #[test]
+    fn test_annotations_to_sexpr() {
+        let mut parser = Parser::new(Lexer::new(
+            "let apply: fn(fn(int) -> bool, int) -> bool = fn(f, x: int) -> bool { f(x) };",
+        ));
+        let program = parser.parse_program();
+
+        assert_eq!(
+            to_sexpr(&program),
+            "(program\n  (let (: apply (fn ((fn (int) bool) int) bool)) \
+             (fn (f (: x int)) (-> bool) (block (call f x)))))"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+/// Layout description consumed by `render`, in the style of Wadler's
+/// "prettier printer".
+///
+/// A `Group` is printed flat (every `Line` as a space, every `SoftLine` as
+/// nothing) when its text up to the next forced line break fits in the
+/// remaining width, otherwise all of its own lines break.
+#[derive(Debug, Clone)]
+pub enum Doc {
+    Text(String),
+    /// A space when flat, a line break when broken.
+    Line,
+    /// Nothing when flat, a line break when broken.
+    SoftLine,
+    /// Always a line break.
+    HardLine,
+    /// A line break without indentation, used for blank lines.
+    BlankLine,
+    Concat(Vec<Doc>),
+    /// Indents the lines of a group when that group breaks.
+    Nest(Box<Doc>),
+    /// Always indents its lines, used for the contents of blocks.
+    Indent(Box<Doc>),
+    Group(Box<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Doc {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    pub fn nest(doc: Doc) -> Doc {
+        Doc::Nest(Box::new(doc))
+    }
+
+    pub fn indent(doc: Doc) -> Doc {
+        Doc::Indent(Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn join(docs: impl IntoIterator<Item = Doc>, separator: Doc) -> Doc {
+        let mut joined = vec![];
+
+        for (i, doc) in docs.into_iter().enumerate() {
+            if i > 0 {
+                joined.push(separator.clone());
+            }
+            joined.push(doc);
+        }
+
+        Doc::Concat(joined)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Lays `doc` out within `width` columns, indenting nested docs by `indent`.
+pub fn render(doc: &Doc, width: usize, indent: usize) -> String {
+    let mut output = String::new();
+    let mut column = 0;
+    let mut stack = vec![(0, Mode::Break, doc)];
+
+    while let Some((level, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                output.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if let Doc::Line = doc {
+                    output.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                output.push('\n');
+                output.push_str(&" ".repeat(level));
+                column = level;
+            }
+            Doc::BlankLine => {
+                output.push('\n');
+                column = 0;
+            }
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((level, mode, doc));
+                }
+            }
+            // a flat group never breaks its own lines, so its nesting would
+            // only shift the blocks inside it
+            Doc::Nest(doc) if mode == Mode::Flat => stack.push((level, mode, doc)),
+            Doc::Nest(doc) | Doc::Indent(doc) => stack.push((level + indent, mode, doc)),
+            Doc::Group(doc) => {
+                let mode = if fits(doc, width.saturating_sub(column), &stack) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.push((level, mode, doc));
+            }
+        }
+    }
+
+    // blank lines and broken groups can leave indentation on empty lines
+    output
+        .lines()
+        .map(|line| line.trim_end())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// Whether `doc`, printed flat and followed by the rest of the output, reaches
+/// its next line break within `width` columns.
+fn fits(doc: &Doc, width: usize, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut remaining = isize::try_from(width).unwrap_or(isize::MAX);
+    let mut stack: Vec<(Mode, &Doc)> = vec![(Mode::Flat, doc)];
+    let mut rest = rest.iter().rev();
+
+    loop {
+        let (mode, doc) = match stack.pop() {
+            Some(entry) => entry,
+            None => match rest.next() {
+                Some((_, mode, doc)) => (*mode, *doc),
+                None => return true,
+            },
+        };
+
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine | Doc::BlankLine => return true,
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((mode, doc));
+                }
+            }
+            Doc::Nest(doc) | Doc::Indent(doc) | Doc::Group(doc) => stack.push((mode, doc)),
+        }
+
+        if remaining < 0 {
+            return false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, Doc};
+
+    fn call(name: &str, arguments: &[&str]) -> Doc {
+        Doc::group(Doc::concat([
+            Doc::text(format!("{}(", name)),
+            Doc::nest(Doc::concat([
+                Doc::SoftLine,
+                Doc::join(
+                    arguments.iter().map(|a| Doc::text(*a)),
+                    Doc::concat([Doc::text(","), Doc::Line]),
+                ),
+            ])),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ]))
+    }
+
+    #[test]
+    fn test_group_fits() {
+        let doc = call("add", &["one", "two"]);
+        assert_eq!(render(&doc, 20, 4), "add(one, two)");
+    }
+
+    #[test]
+    fn test_group_breaks() {
+        let doc = call("add", &["one", "two"]);
+        assert_eq!(render(&doc, 10, 4), "add(\n    one,\n    two\n)");
+    }
+
+    #[test]
+    fn test_hard_line_inside_flat_group() {
+        let block = Doc::concat([
+            Doc::text("{"),
+            Doc::indent(Doc::concat([Doc::HardLine, call("f", &["a", "b"])])),
+            Doc::HardLine,
+            Doc::text("}"),
+        ]);
+        let doc = Doc::group(Doc::concat([
+            Doc::text("g("),
+            Doc::nest(Doc::concat([Doc::SoftLine, block])),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ]));
+        assert_eq!(render(&doc, 80, 2), "g({\n  f(a, b)\n})");
+    }
+
+    #[test]
+    fn test_blank_line_has_no_indentation() {
+        let doc = Doc::nest(Doc::concat([
+            Doc::HardLine,
+            Doc::text("a"),
+            Doc::BlankLine,
+            Doc::HardLine,
+            Doc::text("b"),
+        ]));
+        assert_eq!(render(&doc, 80, 4), "\n    a\n\n    b");
+    }
+}
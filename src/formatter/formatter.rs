@@ -0,0 +1,744 @@
+use crate::{
+    lexer::{
+        lexer::Lexer,
+        location::{Location, Span},
+        token::Comment,
+    },
+    parser::{
+        ast::{
+            expression::{Expression, TemplatePart},
+            operator::PrefixOperator,
+            program::Program,
+            statement::Statement,
+        },
+        literal::{escape_string, quote_string},
+        parser::{Parser, ParserError},
+        precedence::Precedence,
+        source_map::SourceMap,
+    },
+};
+
+use super::doc::{render, Doc};
+
+/// Pretty-prints Monkey source in its canonical layout.
+///
+/// Output is minimally parenthesized, indented by four spaces and wrapped at
+/// `width` columns. Comments are kept next to the statement they precede or
+/// follow, and formatting already formatted code gives back the same text.
+pub struct Formatter {
+    width: usize,
+    indent: usize,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Formatter::new()
+    }
+}
+
+impl Formatter {
+    pub fn new() -> Self {
+        Formatter {
+            width: 100,
+            indent: 4,
+        }
+    }
+
+    pub fn with_width(width: usize) -> Self {
+        Formatter {
+            width,
+            ..Formatter::new()
+        }
+    }
+
+    pub fn format(&self, input: &str) -> Result<String, Vec<ParserError>> {
+        let mut parser = Parser::new(Lexer::with_comments(input));
+        let program = parser.parse_program();
+
+        if !parser.errors.is_empty() {
+            return Err(parser.errors);
+        }
+
+        let mut printer = Printer {
+            source_map: parser.source_map(&program),
+            comments: parser.comments().iter().cloned().map(Some).collect(),
+            indent: self.indent,
+        };
+        let doc = printer.program(&program);
+        let output = render(&doc, self.width, self.indent);
+
+        if output.is_empty() {
+            Ok(output)
+        } else {
+            Ok(output + "\n")
+        }
+    }
+}
+
+/// Something printed on its own line(s), with the source lines it came from
+/// so blank lines between items can be preserved.
+struct Item {
+    first_line: usize,
+    last_line: usize,
+    doc: Doc,
+}
+
+struct Printer<'a> {
+    source_map: SourceMap<'a>,
+    comments: Vec<Option<Comment<'a>>>,
+    indent: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn program(&mut self, program: &Program) -> Doc {
+        let whole_file = Span::new(Location::new(0, 0), Location::new(usize::MAX, usize::MAX));
+        let items = self.items(&program.statements, Some(&whole_file));
+
+        Doc::concat(self.join_items(items).into_iter().skip(1))
+    }
+
+    fn block(&mut self, statements: &[Statement], region: Option<&Span>) -> Doc {
+        let items = self.items(statements, region);
+
+        if items.is_empty() {
+            return Doc::text("{}");
+        }
+
+        Doc::concat([
+            Doc::text("{"),
+            Doc::indent(Doc::concat(self.join_items(items))),
+            Doc::HardLine,
+            Doc::text("}"),
+        ])
+    }
+
+    /// Items separated by line breaks, keeping at most one blank line where
+    /// the source had any. Every item is preceded by its line break.
+    fn join_items(&self, items: Vec<Item>) -> Vec<Doc> {
+        let mut docs = vec![];
+        let mut previous_line = None;
+
+        for item in items {
+            if let Some(previous_line) = previous_line {
+                if item.first_line > previous_line + 1 {
+                    docs.push(Doc::BlankLine);
+                }
+            }
+            previous_line = Some(item.last_line);
+
+            docs.push(Doc::HardLine);
+            docs.push(item.doc);
+        }
+
+        docs
+    }
+
+    /// Statements of a program or block together with the comments found in
+    /// `region`, the span that encloses them.
+    fn items(&mut self, statements: &[Statement], region: Option<&Span>) -> Vec<Item> {
+        let mut items = vec![];
+        let mut previous_end = region.map(|region| region.start.clone());
+
+        for (i, statement) in statements.iter().enumerate() {
+            let span = self.source_map.statement(statement).cloned();
+
+            if let (Some(previous_end), Some(span)) = (&previous_end, &span) {
+                for comment in self.take_comments(previous_end, &span.start) {
+                    items.push(Item {
+                        first_line: comment.location.line(),
+                        last_line: comment.location.line(),
+                        doc: Doc::text(comment.text),
+                    });
+                }
+            }
+
+            let mut doc = self.statement(statement, statements.get(i + 1));
+
+            let Some(span) = span else {
+                let line = previous_end.as_ref().map_or(0, |end| end.line());
+                items.push(Item {
+                    first_line: line,
+                    last_line: line,
+                    doc,
+                });
+                continue;
+            };
+
+            // comments inside the statement that no nested block claimed
+            // cannot stay where they were, so they move above it
+            for comment in self.take_comments(&span.start, &span.end) {
+                items.push(Item {
+                    first_line: span.start.line(),
+                    last_line: span.start.line(),
+                    doc: Doc::text(comment.text),
+                });
+            }
+
+            // a comment later on the same line trails the statement, unless
+            // the block it is in closes before it
+            let mut end_of_line = Location::new(span.end.line(), usize::MAX);
+            if let Some(region) = region {
+                end_of_line = end_of_line.min(region.end.clone());
+            }
+            let mut last_line = span.end.line();
+            for comment in self.take_comments(&span.end, &end_of_line) {
+                doc = Doc::concat([doc, Doc::text(" "), Doc::text(comment.text)]);
+                last_line = comment.location.line();
+            }
+
+            items.push(Item {
+                first_line: span.start.line(),
+                last_line,
+                doc,
+            });
+            previous_end = Some(span.end);
+        }
+
+        if let (Some(previous_end), Some(region)) = (&previous_end, region) {
+            for comment in self.take_comments(previous_end, &region.end) {
+                items.push(Item {
+                    first_line: comment.location.line(),
+                    last_line: comment.location.line(),
+                    doc: Doc::text(comment.text),
+                });
+            }
+        }
+
+        items
+    }
+
+    /// Removes and returns the comments strictly between `after` and
+    /// `before`.
+    fn take_comments(&mut self, after: &Location, before: &Location) -> Vec<Comment<'a>> {
+        self.comments
+            .iter_mut()
+            .filter(|comment| {
+                comment
+                    .as_ref()
+                    .is_some_and(|comment| *after < comment.location && comment.location < *before)
+            })
+            .filter_map(|comment| comment.take())
+            .collect()
+    }
+
+    fn statement(&mut self, statement: &Statement, next: Option<&Statement>) -> Doc {
+        match statement {
+            Statement::Let {
+                name,
+                annotation,
+                value,
+            } => Doc::concat([
+                match annotation {
+                    Some(annotation) => Doc::text(format!("let {}: {} = ", name, annotation)),
+                    None => Doc::text(format!("let {} = ", name)),
+                },
+                self.expression(value),
+                Doc::text(";"),
+            ]),
+            Statement::Return { value } => {
+                Doc::concat([Doc::text("return "), self.expression(value), Doc::text(";")])
+            }
+            // an `if` closes with a brace and reads better without a
+            // semicolon, unless the next statement would then continue it
+            Statement::Expression(expression @ Expression::If { .. })
+                if !next.is_some_and(|next| self.continues_expression(next)) =>
+            {
+                self.expression(expression)
+            }
+            Statement::Expression(expression) => {
+                Doc::concat([self.expression(expression), Doc::text(";")])
+            }
+            Statement::Block(statements) => {
+                let docs: Vec<Doc> = statements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, statement)| self.statement(statement, statements.get(i + 1)))
+                    .collect();
+                Doc::join(docs, Doc::HardLine)
+            }
+            Statement::Import { .. } | Statement::Export(_) => Doc::text(format!("{};", statement)),
+        }
+    }
+
+    /// Whether `statement` would be parsed as part of the expression before it
+    /// if that expression was not terminated by a semicolon.
+    fn continues_expression(&self, statement: &Statement) -> bool {
+        match statement {
+            Statement::Expression(expression) => {
+                self.starts_with_operator(expression, Precedence::LOWEST)
+            }
+            _ => false,
+        }
+    }
+
+    fn starts_with_operator(&self, expression: &Expression, min: Precedence) -> bool {
+        if self.needs_parens(expression, min, false) {
+            return true;
+        }
+
+        match expression {
+            Expression::Infix { lhs, operator, .. } => {
+                self.starts_with_operator(lhs, Precedence::from(operator))
+            }
+            Expression::Call { function, .. } => {
+                self.starts_with_operator(function, Precedence::CALL)
+            }
+            Expression::Member { object, .. } => {
+                self.starts_with_operator(object, Precedence::CALL)
+            }
+            Expression::Index { left, .. } => self.starts_with_operator(left, Precedence::INDEX),
+            // `[` would index the expression before
+            Expression::Array(_) => true,
+            Expression::Prefix { operator, .. } => *operator == PrefixOperator::Negative,
+            Expression::Int(integer) => *integer < 0,
+            _ => false,
+        }
+    }
+
+    fn precedence(&self, expression: &Expression) -> Option<Precedence> {
+        match expression {
+            Expression::Infix { operator, .. } => Some(Precedence::from(operator)),
+            Expression::Prefix { .. } => Some(Precedence::PREFIX),
+            Expression::Int(integer) if *integer < 0 => Some(Precedence::PREFIX),
+            Expression::Call { .. } | Expression::Member { .. } => Some(Precedence::CALL),
+            Expression::Index { .. } => Some(Precedence::INDEX),
+            _ => None,
+        }
+    }
+
+    /// Whether `expression` must be parenthesized where an operand binding at
+    /// least as tightly as `min` is expected. Infix operators are left
+    /// associative, so their right operand also needs parentheses at equal
+    /// precedence.
+    fn needs_parens(&self, expression: &Expression, min: Precedence, right: bool) -> bool {
+        match self.precedence(expression) {
+            Some(precedence) if right => precedence <= min,
+            Some(precedence) => precedence < min,
+            None => false,
+        }
+    }
+
+    fn operand(&mut self, expression: &Expression, min: Precedence, right: bool) -> Doc {
+        let doc = self.expression(expression);
+
+        if self.needs_parens(expression, min, right) {
+            Doc::concat([Doc::text("("), doc, Doc::text(")")])
+        } else {
+            doc
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Doc {
+        match expression {
+            Expression::Int(integer) => Doc::text(integer.to_string()),
+            Expression::Bool(boolean) => Doc::text(boolean.to_string()),
+            Expression::String(string) => Doc::text(quote_string(string)),
+            Expression::Identifier(identifier) => Doc::text(identifier),
+            Expression::Null => Doc::text("null"),
+            Expression::Prefix { rhs, operator } => Doc::concat([
+                Doc::text(operator.to_string()),
+                self.operand(rhs, Precedence::PREFIX, false),
+            ]),
+            Expression::Infix { operator, .. } => {
+                let precedence = Precedence::from(operator);
+
+                // a chain like `a + b - c` is one group, so it breaks after
+                // every operator or after none
+                let mut chain = vec![];
+                let mut first = expression;
+                while let Expression::Infix { lhs, operator, rhs } = first {
+                    if Precedence::from(operator) != precedence {
+                        break;
+                    }
+                    chain.push((operator, rhs));
+                    first = lhs;
+                }
+
+                let mut rest = vec![];
+                for (operator, rhs) in chain.into_iter().rev() {
+                    rest.push(Doc::text(format!(" {}", operator)));
+                    rest.push(Doc::Line);
+                    rest.push(self.operand(rhs, precedence, true));
+                }
+
+                Doc::group(Doc::concat([
+                    self.operand(first, precedence, false),
+                    Doc::nest(Doc::concat(rest)),
+                ]))
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                let function = self.operand(function, Precedence::CALL, false);
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.expression(argument))
+                    .collect::<Vec<Doc>>();
+                Doc::concat([function, self.list("(", ")", arguments)])
+            }
+            Expression::Member { object, name } => Doc::concat([
+                self.operand(object, Precedence::CALL, false),
+                Doc::text(format!(".{}", name)),
+            ]),
+            Expression::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| self.expression(element))
+                    .collect::<Vec<Doc>>();
+                self.list("[", "]", elements)
+            }
+            Expression::Template(parts) => {
+                let mut text = "\"".to_string();
+                for part in parts {
+                    match part {
+                        TemplatePart::Text(string) => text.push_str(&escape_string(string)),
+                        TemplatePart::Expression(expression) => {
+                            // interpolations never wrap to fit the width, only the
+                            // blocks inside them break
+                            let doc = self.expression(expression);
+                            text.push_str("${");
+                            text.push_str(&render(&doc, usize::MAX, self.indent));
+                            text.push('}');
+                        }
+                    }
+                }
+                text.push('"');
+                Doc::text(text)
+            }
+            Expression::Index { left, index } => Doc::concat([
+                self.operand(left, Precedence::INDEX, false),
+                Doc::text("["),
+                self.expression(index),
+                Doc::text("]"),
+            ]),
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let blocks = self.source_map.blocks(expression).to_vec();
+                let mut docs = vec![
+                    Doc::text("if ("),
+                    self.expression(condition),
+                    Doc::text(") "),
+                    self.block(consequence, blocks.first()),
+                ];
+
+                if let Some(alternative) = alternative {
+                    docs.push(Doc::text(" else "));
+                    docs.push(self.block(alternative, blocks.get(1)));
+                }
+
+                Doc::concat(docs)
+            }
+            Expression::Function {
+                parameters,
+                result,
+                body,
+            } => {
+                let blocks = self.source_map.blocks(expression).to_vec();
+                let parameters = parameters
+                    .iter()
+                    .map(|parameter| Doc::text(parameter.to_string()))
+                    .collect::<Vec<Doc>>();
+                let result = match result {
+                    Some(result) => Doc::text(format!(" -> {} ", result)),
+                    None => Doc::text(" "),
+                };
+                Doc::concat([
+                    Doc::text("fn"),
+                    self.list("(", ")", parameters),
+                    result,
+                    self.block(body, blocks.first()),
+                ])
+            }
+        }
+    }
+
+    /// A comma separated list between `open` and `close` that puts one
+    /// element per line when it does not fit.
+    fn list(&self, open: &str, close: &str, elements: Vec<Doc>) -> Doc {
+        if elements.is_empty() {
+            return Doc::text(format!("{}{}", open, close));
+        }
+
+        Doc::group(Doc::concat([
+            Doc::text(open),
+            Doc::nest(Doc::concat([
+                Doc::SoftLine,
+                Doc::join(elements, Doc::concat([Doc::text(","), Doc::Line])),
+            ])),
+            Doc::SoftLine,
+            Doc::text(close),
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::Formatter;
+
+    fn format(input: &str) -> String {
+        let formatted = Formatter::new().format(input).unwrap();
+        assert_eq!(
+            Formatter::new().format(&formatted).unwrap(),
+            formatted,
+            "formatting is not idempotent for {}",
+            input
+        );
+        formatted
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        let tests = vec![
+            ("(1 + 2)", "1 + 2;\n"),
+            ("((1 + 2) * 3)", "(1 + 2) * 3;\n"),
+            ("1 + (2 * 3)", "1 + 2 * 3;\n"),
+            ("a - (b - c)", "a - (b - c);\n"),
+            ("(a - b) - c", "a - b - c;\n"),
+            ("-(a + b)", "-(a + b);\n"),
+            ("-(-a)", "--a;\n"),
+            ("!(a == b)", "!(a == b);\n"),
+            ("(a < b) == (c > d)", "a < b == c > d;\n"),
+            ("(f(x))(y)", "f(x)(y);\n"),
+            ("a + -b", "a + -b;\n"),
+        ];
+
+        for test in tests {
+            assert_eq!(format(test.0), test.1, "formatting {}", test.0);
+        }
+    }
+
+    #[test]
+    fn test_statements() {
+        let input = indoc! {"
+            let x=5
+            let add=fn(a,b){return a+b}
+            add(x ,10)
+            if(x<10){x}else{let y=x*2;y}
+        "};
+
+        assert_eq!(
+            format(input),
+            indoc! {"
+                let x = 5;
+                let add = fn(a, b) {
+                    return a + b;
+                };
+                add(x, 10);
+                if (x < 10) {
+                    x;
+                } else {
+                    let y = x * 2;
+                    y;
+                }
+            "}
+        );
+    }
+
+    #[test]
+    fn test_modules() {
+        assert_eq!(
+            format("import{a,b}from\"x\"\nimport \"m\"  as  m\nexport a,b\n(m).f(a). g"),
+            indoc! {r#"
+                import { a, b } from "x";
+                import "m" as m;
+                export a, b;
+                m.f(a).g;
+            "#}
+        );
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(format("[ 1,2 ,[ ] ] [0]"), "[1, 2, []][0];\n");
+        assert_eq!(format("(-a)[0] + (a + b)[1]"), "(-a)[0] + (a + b)[1];\n");
+        assert_eq!(format("if (x) { 1 }; [2];"), "if (x) {\n    1;\n};\n[2];\n");
+        assert_eq!(
+            format("map([1000000, 2000000, 3000000], fn(x) { x * 1000000 + 1000000 })"),
+            indoc! {"
+                map([1000000, 2000000, 3000000], fn(x) {
+                    x * 1000000 + 1000000;
+                });
+            "}
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(
+            format(r#"let s:string="a\"b\n"   "x"+ s"#),
+            "let s: string = \"a\\\"b\\n\";\n\"x\" + s;\n"
+        );
+        assert_eq!(
+            format(r#""a${ b+1 }\${c} ${ f("}", 1000000 * 1000000) }""#),
+            "\"a${b + 1}\\${c} ${f(\"}\", 1000000 * 1000000)}\";\n"
+        );
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        assert_eq!(
+            format("let f:fn(int)->bool=fn(a:int,b)->bool{a>b}"),
+            indoc! {"
+                let f: fn(int) -> bool = fn(a: int, b) -> bool {
+                    a > b;
+                };
+            "}
+        );
+    }
+
+    #[test]
+    fn test_empty_blocks_and_lists() {
+        assert_eq!(format("let f = fn() {};"), "let f = fn() {};\n");
+        assert_eq!(format("if (x) {} else {}"), "if (x) {} else {}\n");
+        assert_eq!(format("f()"), "f();\n");
+        assert_eq!(format(""), "");
+    }
+
+    #[test]
+    fn test_if_keeps_semicolon_when_needed() {
+        assert_eq!(format("if (x) { 1 }; -1;"), "if (x) {\n    1;\n};\n-1;\n");
+        assert_eq!(
+            format("if (x) { 1 }; (a + b) * c;"),
+            "if (x) {\n    1;\n};\n(a + b) * c;\n"
+        );
+        assert_eq!(format("if (x) { 1 }; !a;"), "if (x) {\n    1;\n}\n!a;\n");
+    }
+
+    #[test]
+    fn test_preserves_blank_lines() {
+        let input = indoc! {"
+            let a = 1;
+
+
+
+            let b = 2;
+            let c = 3;
+        "};
+
+        assert_eq!(format(input), "let a = 1;\n\nlet b = 2;\nlet c = 3;\n");
+    }
+
+    #[test]
+    fn test_line_wrapping() {
+        let input = "let result = compute(first_argument, second_argument, third_argument);";
+
+        assert_eq!(
+            Formatter::with_width(40).format(input).unwrap(),
+            indoc! {"
+                let result = compute(
+                    first_argument,
+                    second_argument,
+                    third_argument
+                );
+            "}
+        );
+        assert_eq!(
+            Formatter::with_width(30)
+                .format("total + first * second + third")
+                .unwrap(),
+            "total +\n    first * second +\n    third;\n"
+        );
+        assert_eq!(
+            Formatter::with_width(20)
+                .format("alpha + beta - gamma + delta + epsilon")
+                .unwrap(),
+            "alpha +\n    beta -\n    gamma +\n    delta +\n    epsilon;\n"
+        );
+        assert_eq!(
+            Formatter::with_width(20)
+                .format("\"${compute(first_argument, second_argument)}\"")
+                .unwrap(),
+            "\"${compute(first_argument, second_argument)}\";\n"
+        );
+    }
+
+    #[test]
+    fn test_callback_argument_stays_on_the_call_line() {
+        let input = "map(items, fn(x) { x * 2 })";
+
+        assert_eq!(
+            format(input),
+            indoc! {"
+                map(items, fn(x) {
+                    x * 2;
+                });
+            "}
+        );
+    }
+
+    #[test]
+    fn test_preserves_comments() {
+        let input = indoc! {"
+            // header comment
+
+            let x = 5; // five
+            let f = fn(a) {
+                // inside body
+                a + x // sum
+                // before closing brace
+            };
+            if (x) { 1 } else {
+                // only a comment
+            }
+            let y = add(1, // hoisted
+                2);
+            // trailing comment
+        "};
+
+        assert_eq!(
+            format(input),
+            indoc! {"
+                // header comment
+
+                let x = 5; // five
+                let f = fn(a) {
+                    // inside body
+                    a + x; // sum
+                    // before closing brace
+                };
+                if (x) {
+                    1;
+                } else {
+                    // only a comment
+                }
+                // hoisted
+                let y = add(1, 2);
+                // trailing comment
+            "}
+        );
+    }
+
+    #[test]
+    fn test_trailing_comment_stays_in_its_block() {
+        let input = "if (x) { return 1; } else { let y = 2 // two\n y }";
+
+        assert_eq!(
+            format(input),
+            indoc! {"
+                if (x) {
+                    return 1;
+                } else {
+                    let y = 2; // two
+                    y;
+                }
+            "}
+        );
+    }
+
+    #[test]
+    fn test_only_comments() {
+        assert_eq!(format("// a\n\n// b"), "// a\n\n// b\n");
+    }
+
+    #[test]
+    fn test_parser_errors() {
+        let errors = Formatter::new().format("let = 5;").unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -1,161 +1,433 @@
-use std::iter::Peekable;
-use std::str::Chars;
+use unicode_xid::UnicodeXID;
 
 use super::location::Location;
-use super::token::{Token, TokenType};
+use super::token::{Comment, StringPart, Token, TokenType, Trivia, TriviaKind, TriviaToken};
 
+/// Scans the input byte by byte. Tokens borrow their text from the input, so
+/// lexing never allocates; chars are only decoded for non-ASCII input.
 pub struct Lexer<'a> {
-    chars: Peekable<Chars<'a>>,
-    ch: Option<char>,
+    input: &'a str,
+    /// Byte offset of the current byte.
+    position: usize,
+    /// Location of the current byte.
     line: usize,
     column: usize,
+    /// Whether the iterator already yielded `EOF`.
+    finished: bool,
+    /// Comments skipped so far, when they are kept.
+    comments: Option<Vec<Comment<'a>>>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        Lexer::continuing_from(input, Location::new(1, 0))
+    }
+
+    /// Like `new`, but keeps the comments it skips for tools like the
+    /// formatter and the linter.
+    pub fn with_comments(input: &'a str) -> Self {
+        Lexer::new(input).keeping_comments()
+    }
+
+    /// Keeps the comments skipped from now on.
+    pub fn keeping_comments(mut self) -> Self {
+        self.comments.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Lexes `input` as the text that directly follows the char at
+    /// `location`, so locations carry on from there. Byte offsets are still
+    /// relative to `input`.
+    pub fn continuing_from(input: &'a str, location: Location) -> Self {
         let mut lexer = Lexer {
-            chars: input.chars().peekable(),
-            ch: None,
-            line: 1,
-            column: 0,
+            input,
+            position: 0,
+            line: location.line(),
+            column: location.column(),
+            finished: false,
+            comments: None,
         };
 
-        lexer.read_char();
+        lexer.enter_byte();
 
-        return lexer;
+        lexer
     }
 
-    pub fn next_token(&mut self) -> Token {
+    pub fn next_token(&mut self) -> Token<'a> {
         self.skip_whitespace();
 
+        while self.at_comment() {
+            self.skip_comment();
+            self.skip_whitespace();
+        }
+
         let location = Location::new(self.line, self.column);
+        let start = self.position;
+        let token_type = self.read_token_type();
+
+        Token::new(token_type, location, start..self.position)
+    }
 
-        let token_type = match self.ch {
-            Some(',') => TokenType::Comma,
-            Some('(') => TokenType::LParen,
-            Some(')') => TokenType::RParen,
-            Some('{') => TokenType::LBrace,
-            Some('}') => TokenType::RBrace,
-            Some(';') => TokenType::Semicolon,
-            Some('!') => match self.peek_char() {
-                Some('=') => {
-                    self.read_char();
+    fn read_token_type(&mut self) -> TokenType<'a> {
+        let token_type = match self.current() {
+            Some(b',') => TokenType::Comma,
+            Some(b'(') => TokenType::LParen,
+            Some(b')') => TokenType::RParen,
+            Some(b'{') => TokenType::LBrace,
+            Some(b'}') => TokenType::RBrace,
+            Some(b'[') => TokenType::LBracket,
+            Some(b']') => TokenType::RBracket,
+            Some(b';') => TokenType::Semicolon,
+            Some(b'!') => match self.peek() {
+                Some(b'=') => {
+                    self.advance();
                     TokenType::NotEq
                 }
                 _ => TokenType::Bang,
             },
-            Some('=') => {
-                if let Some('=') = self.peek_char() {
-                    self.read_char();
+            Some(b'=') => {
+                if let Some(b'=') = self.peek() {
+                    self.advance();
                     TokenType::Eq
                 } else {
                     TokenType::Assign
                 }
             }
-            Some('*') => TokenType::Asterisk,
-            Some('/') => TokenType::Slash,
-            Some('+') => TokenType::Plus,
-            Some('-') => TokenType::Minus,
-            Some('<') => TokenType::LT,
-            Some('>') => TokenType::GT,
-            Some('0'..='9') => {
-                let token_type = TokenType::integer(self.read_integer());
-                return Token::new(token_type, location);
-            }
-            Some('a'..='z') | Some('A'..='Z') | Some('_') => {
-                let word = self.read_word();
-
-                let token_type = match word.as_str() {
-                    "let" => TokenType::Let,
-                    "fn" => TokenType::Function,
-                    "true" => TokenType::True,
-                    "false" => TokenType::False,
-                    "if" => TokenType::If,
-                    "else" => TokenType::Else,
-                    "return" => TokenType::Return,
-                    "null" => TokenType::Null,
-                    _ => TokenType::identifier(word),
-                };
-
-                return Token::new(token_type, location);
+            Some(b'*') => TokenType::Asterisk,
+            Some(b'/') => TokenType::Slash,
+            Some(b'+') => TokenType::Plus,
+            Some(b'-') => match self.peek() {
+                Some(b'>') => {
+                    self.advance();
+                    TokenType::Arrow
+                }
+                _ => TokenType::Minus,
+            },
+            Some(b':') => TokenType::Colon,
+            Some(b'.') => TokenType::Dot,
+            Some(b'"') => return self.read_string(),
+            Some(b'<') => TokenType::LT,
+            Some(b'>') => TokenType::GT,
+            Some(b'0'..=b'9') => return TokenType::Integer(self.read_number()),
+            Some(byte) if Lexer::is_identifier_start(byte) => return self.read_identifier(),
+            Some(b'%') => TokenType::Modulo,
+            Some(byte) if byte.is_ascii() => TokenType::Illegal(byte as char),
+            Some(_) => {
+                let ch = self.current_char();
+                if ch.is_xid_start() {
+                    return self.read_identifier();
+                }
+                for _ in 1..ch.len_utf8() {
+                    self.advance();
+                }
+                TokenType::Illegal(ch)
             }
-            Some('%') => TokenType::Modulo,
-            Some(ch) => TokenType::Illegal(ch),
             None => TokenType::EOF,
         };
 
-        self.read_char();
-        return Token::new(token_type, location);
+        self.advance();
+        token_type
     }
 
-    fn peek_char(&mut self) -> Option<&char> {
-        self.chars.peek()
+    /// The text being lexed, which token offsets index into.
+    pub fn input(&self) -> &'a str {
+        self.input
     }
 
-    fn read_char(&mut self) {
-        match self.chars.next() {
-            Some(ch) => {
-                if ch == '\n' {
-                    self.line += 1;
-                    self.column = 0;
-                } else {
-                    self.column += 1;
-                }
+    /// Comments skipped so far, in source order, if the lexer keeps them.
+    pub fn comments(&self) -> &[Comment<'a>] {
+        self.comments.as_deref().unwrap_or_default()
+    }
 
-                self.ch = Some(ch);
-            }
-            None => {
-                self.ch = None;
-            }
+    /// Like `next_token`, but also returns the exact source text of the token
+    /// and of the whitespace and comments before it. Concatenating every
+    /// trivia and token text up to `EOF` reproduces the input byte for byte.
+    pub fn next_token_with_trivia(&mut self) -> TriviaToken<'a> {
+        let mut leading_trivia = vec![];
+
+        loop {
+            let start = self.position;
+            let kind = if self.at_whitespace() {
+                self.skip_whitespace();
+                TriviaKind::Whitespace
+            } else if self.at_comment() {
+                self.skip_comment();
+                TriviaKind::Comment
+            } else {
+                break;
+            };
+
+            leading_trivia.push(Trivia {
+                kind,
+                text: &self.input[start..self.position],
+            });
+        }
+
+        let token = self.next_token();
+        let text = token.text(self.input);
+
+        TriviaToken {
+            leading_trivia,
+            token,
+            text,
         }
     }
 
-    fn skip_whitespace(&mut self) {
-        while let Some(ch) = self.ch {
-            if !([' ', '\t', '\n', '\r'].contains(&ch)) {
-                break;
+    fn current(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+
+    /// The char starting at the current byte, which must not be the end of
+    /// the input.
+    fn current_char(&self) -> char {
+        self.input[self.position..]
+            .chars()
+            .next()
+            .unwrap_or_default()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position + 1).copied()
+    }
+
+    /// Moves to the next byte, doing nothing at the end of the input.
+    fn advance(&mut self) {
+        if self.position < self.input.len() {
+            self.position += 1;
+            self.enter_byte();
+        }
+    }
+
+    /// Updates the location for the byte just moved to. Columns count chars,
+    /// so a tab is one column and UTF-8 continuation bytes share their char's
+    /// column. A newline starts the next line, which makes `\r\n` a single
+    /// line break, and the end of the input sits just past the last char.
+    fn enter_byte(&mut self) {
+        match self.current() {
+            Some(b'\n') => {
+                self.line += 1;
+                self.column = 0;
             }
+            Some(byte) if byte & 0b1100_0000 == 0b1000_0000 => {}
+            Some(_) | None => self.column += 1,
+        }
+    }
+
+    fn read_while(&mut self, predicate: fn(u8) -> bool) -> &'a str {
+        let start = self.position;
 
-            self.read_char();
+        while self.current().is_some_and(predicate) {
+            self.advance();
         }
+
+        &self.input[start..self.position]
     }
 
-    fn read_integer(&mut self) -> String {
-        let mut number = String::new();
+    /// Reads an identifier or keyword. Identifiers follow Unicode XID: a
+    /// letter or `_`, then any letters, digits, `_` and combining marks.
+    fn read_identifier(&mut self) -> TokenType<'a> {
+        let start = self.position;
 
-        while let Some(ch) = self.ch {
-            if !Lexer::is_digit(ch) {
-                break;
+        while let Some(byte) = self.current() {
+            if byte.is_ascii() {
+                if !Lexer::is_identifier_continue(byte) {
+                    break;
+                }
+                self.advance();
+            } else {
+                let ch = self.current_char();
+                if !ch.is_xid_continue() {
+                    break;
+                }
+                for _ in 0..ch.len_utf8() {
+                    self.advance();
+                }
             }
+        }
+
+        match &self.input[start..self.position] {
+            "let" => TokenType::Let,
+            "fn" => TokenType::Function,
+            "true" => TokenType::True,
+            "false" => TokenType::False,
+            "if" => TokenType::If,
+            "else" => TokenType::Else,
+            "return" => TokenType::Return,
+            "null" => TokenType::Null,
+            "import" => TokenType::Import,
+            "export" => TokenType::Export,
+            word => TokenType::Identifier(word),
+        }
+    }
 
-            number.push(ch);
-            self.read_char();
+    /// Reads a string literal up to the closing quote, skipping over the
+    /// char after every backslash and over interpolations. Escapes and
+    /// interpolations are left to the parser. A string that runs to the end
+    /// of the input is illegal.
+    fn read_string(&mut self) -> TokenType<'a> {
+        self.advance();
+        let start = self.position;
+        let end = string_end(self.input.as_bytes(), start);
+
+        while self.position < end.unwrap_or(self.input.len()) {
+            self.advance();
+        }
+        if end.is_none() {
+            return TokenType::Illegal('"');
         }
 
-        return number;
+        let string = &self.input[start..self.position];
+        self.advance();
+
+        TokenType::String(string)
     }
 
-    fn read_word(&mut self) -> String {
-        let mut word = String::new();
+    /// Reads an integer literal as written, leaving its validation to the
+    /// parser: after a radix prefix every letter and digit is taken, so `0b2`
+    /// or `0xfg` stay a single malformed literal.
+    fn read_number(&mut self) -> &'a str {
+        let start = self.position;
+
+        if self.current() == Some(b'0')
+            && matches!(self.peek(), Some(b'x' | b'X' | b'b' | b'B' | b'o' | b'O'))
+        {
+            self.advance();
+            self.advance();
+            self.read_while(|byte| byte.is_ascii_alphanumeric() || byte == b'_');
+        } else {
+            self.read_while(|byte| Lexer::is_digit(byte) || byte == b'_');
+        }
 
-        while let Some(ch) = self.ch {
-            if !Lexer::is_letter(ch) {
-                break;
-            }
+        &self.input[start..self.position]
+    }
+
+    fn at_whitespace(&self) -> bool {
+        matches!(self.current(), Some(b' ' | b'\t' | b'\n' | b'\r'))
+    }
+
+    fn at_comment(&self) -> bool {
+        self.current() == Some(b'/') && self.peek() == Some(b'/')
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.at_whitespace() {
+            self.advance();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        let location = Location::new(self.line, self.column);
+        let text = self.read_while(|byte| byte != b'\n');
 
-            word.push(ch);
-            self.read_char();
+        if let Some(comments) = &mut self.comments {
+            comments.push(Comment {
+                text: text.trim_end(),
+                location,
+            });
         }
+    }
+
+    fn is_identifier_start(byte: u8) -> bool {
+        byte.is_ascii_alphabetic() || byte == b'_'
+    }
 
-        return word;
+    fn is_identifier_continue(byte: u8) -> bool {
+        byte.is_ascii_alphanumeric() || byte == b'_'
     }
 
-    fn is_letter(ch: char) -> bool {
-        ('a'..='z').contains(&ch) || ('A'..='Z').contains(&ch) || ch == '_'
+    fn is_digit(byte: u8) -> bool {
+        byte.is_ascii_digit()
+    }
+}
+
+/// Splits the text of a string literal, as read by the lexer, into text and
+/// the code of its `${...}` interpolations. Empty text is left out, but a
+/// literal without interpolations is always one text part.
+pub fn split_string(literal: &str) -> Vec<StringPart<'_>> {
+    let bytes = literal.as_bytes();
+    let mut parts = vec![];
+    let mut text_start = 0;
+    let mut position = 0;
+
+    while position < bytes.len() {
+        match bytes[position] {
+            b'\\' => position += 2,
+            b'$' if bytes.get(position + 1) == Some(&b'{') => {
+                let code_start = position + 2;
+                let Some(end) = interpolation_end(bytes, code_start) else {
+                    break;
+                };
+
+                if text_start < position {
+                    parts.push(StringPart::Text(&literal[text_start..position]));
+                }
+                parts.push(StringPart::Interpolation(
+                    &literal[code_start..end],
+                    code_start,
+                ));
+                position = end + 1;
+                text_start = position;
+            }
+            _ => position += 1,
+        }
     }
 
-    fn is_digit(ch: char) -> bool {
-        ('0'..='9').contains(&ch)
+    if text_start < literal.len() || parts.is_empty() {
+        parts.push(StringPart::Text(&literal[text_start..]));
+    }
+
+    parts
+}
+
+/// Byte offset of the quote closing the string whose text starts at
+/// `position`.
+fn string_end(bytes: &[u8], mut position: usize) -> Option<usize> {
+    while position < bytes.len() {
+        match bytes[position] {
+            b'"' => return Some(position),
+            b'\\' => position += 2,
+            b'$' if bytes.get(position + 1) == Some(&b'{') => {
+                position = interpolation_end(bytes, position + 2)? + 1;
+            }
+            _ => position += 1,
+        }
+    }
+
+    None
+}
+
+/// Byte offset of the brace closing the interpolation whose code starts at
+/// `position`. Strings inside it, and the braces in them, are skipped.
+fn interpolation_end(bytes: &[u8], mut position: usize) -> Option<usize> {
+    let mut depth = 0;
+
+    while position < bytes.len() {
+        match bytes[position] {
+            b'"' => position = string_end(bytes, position + 1)?,
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return Some(position),
+            b'}' => depth -= 1,
+            _ => {}
+        }
+        position += 1;
+    }
+
+    None
+}
+
+/// Yields every token up to and including `EOF`.
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let token = self.next_token();
+        self.finished = token.token_type == TokenType::EOF;
+
+        Some(token)
     }
 }
 
@@ -171,11 +443,96 @@ mod tests {
 
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::Eq, Location::new(1, 1))
+            Token::new(TokenType::Eq, Location::new(1, 1), 0..2)
         );
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::NotEq, Location::new(2, 1))
+            Token::new(TokenType::NotEq, Location::new(2, 1), 3..5)
+        );
+    }
+
+    #[test]
+    fn test_read_colon_and_arrow() {
+        let mut lexer = Lexer::new("x: fn() -> - >");
+
+        let token_types: Vec<_> = std::iter::from_fn(|| match lexer.next_token().token_type {
+            TokenType::EOF => None,
+            token_type => Some(token_type),
+        })
+        .collect();
+
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Identifier("x"),
+                TokenType::Colon,
+                TokenType::Function,
+                TokenType::LParen,
+                TokenType::RParen,
+                TokenType::Arrow,
+                TokenType::Minus,
+                TokenType::GT,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_imports_and_strings() {
+        let mut lexer = Lexer::new(r#"import { a } from "lib/\"x\".mk"; export a; m.f "open"#);
+
+        let token_types: Vec<_> = std::iter::from_fn(|| match lexer.next_token().token_type {
+            TokenType::EOF => None,
+            token_type => Some(token_type),
+        })
+        .collect();
+
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Import,
+                TokenType::LBrace,
+                TokenType::Identifier("a"),
+                TokenType::RBrace,
+                TokenType::Identifier("from"),
+                TokenType::String(r#"lib/\"x\".mk"#),
+                TokenType::Semicolon,
+                TokenType::Export,
+                TokenType::Identifier("a"),
+                TokenType::Semicolon,
+                TokenType::Identifier("m"),
+                TokenType::Dot,
+                TokenType::Identifier("f"),
+                TokenType::Illegal('"'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_interpolated_strings() {
+        let mut lexer = Lexer::new(r#""a ${f("}")} ${ {} }" "${" x"#);
+
+        assert_eq!(
+            lexer.next_token().token_type,
+            TokenType::String(r#"a ${f("}")} ${ {} }"#)
+        );
+        assert_eq!(lexer.next_token().token_type, TokenType::Illegal('"'));
+    }
+
+    #[test]
+    fn test_split_string() {
+        assert_eq!(split_string(""), vec![StringPart::Text("")]);
+        assert_eq!(
+            split_string(r"a \${b} $c"),
+            vec![StringPart::Text(r"a \${b} $c")]
+        );
+        assert_eq!(
+            split_string(r#"a ${f("}")}${x}!"#),
+            vec![
+                StringPart::Text("a "),
+                StringPart::Interpolation(r#"f("}")"#, 4),
+                StringPart::Interpolation("x", 13),
+                StringPart::Text("!"),
+            ]
         );
     }
 
@@ -185,27 +542,61 @@ mod tests {
 
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::identifier("banana"), Location::new(1, 1))
+            Token::new(TokenType::identifier("banana"), Location::new(1, 1), 0..6)
         );
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::identifier("pera"), Location::new(1, 8))
+            Token::new(TokenType::identifier("pera"), Location::new(1, 8), 7..11)
         );
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::identifier("uva"), Location::new(2, 1))
+            Token::new(TokenType::identifier("uva"), Location::new(2, 1), 12..15)
         );
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::EOF, Location::new(2, 3))
+            Token::new(TokenType::EOF, Location::new(2, 4), 15..15)
         );
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::EOF, Location::new(2, 3))
+            Token::new(TokenType::EOF, Location::new(2, 4), 15..15)
         );
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::EOF, Location::new(2, 3))
+            Token::new(TokenType::EOF, Location::new(2, 4), 15..15)
+        );
+    }
+
+    #[test]
+    fn test_read_unicode_identifiers() {
+        let input = "x1 _tmp2 größe 名前 e\u{301}t 1x";
+        let texts: Vec<&str> = Lexer::new(input).map(|token| token.text(input)).collect();
+
+        assert_eq!(
+            texts,
+            vec!["x1", "_tmp2", "größe", "名前", "e\u{301}t", "1", "x", ""]
+        );
+    }
+
+    #[test]
+    fn test_locations() {
+        let input = "let é = 1;\r\n\tx1 € ü\n";
+        let tokens: Vec<(TokenType, Location)> = Lexer::new(input)
+            .map(|token| (token.token_type, token.location))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenType::Let, Location::new(1, 1)),
+                (TokenType::identifier("é"), Location::new(1, 5)),
+                (TokenType::Assign, Location::new(1, 7)),
+                (TokenType::integer("1"), Location::new(1, 9)),
+                (TokenType::Semicolon, Location::new(1, 10)),
+                (TokenType::identifier("x1"), Location::new(2, 2)),
+                (TokenType::Illegal('€'), Location::new(2, 5)),
+                (TokenType::identifier("ü"), Location::new(2, 7)),
+                (TokenType::EOF, Location::new(3, 1)),
+            ]
         );
     }
 
@@ -215,11 +606,33 @@ mod tests {
 
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::integer("1234"), Location::new(1, 1))
+            Token::new(TokenType::integer("1234"), Location::new(1, 1), 0..4)
         );
         assert_eq!(
             lexer.next_token(),
-            Token::new(TokenType::integer("6789"), Location::new(2, 1))
+            Token::new(TokenType::integer("6789"), Location::new(2, 1), 5..9)
+        );
+    }
+
+    #[test]
+    fn test_read_prefixed_integer() {
+        let mut lexer = Lexer::new("0xFF+0b1_0 0o7__ 1_000");
+
+        let literals: Vec<_> = std::iter::from_fn(|| match lexer.next_token().token_type {
+            TokenType::EOF => None,
+            token_type => Some(token_type),
+        })
+        .collect();
+
+        assert_eq!(
+            literals,
+            vec![
+                TokenType::integer("0xFF"),
+                TokenType::Plus,
+                TokenType::integer("0b1_0"),
+                TokenType::integer("0o7__"),
+                TokenType::integer("1_000"),
+            ]
         );
     }
 
@@ -227,73 +640,73 @@ mod tests {
     fn test_skip_whitespace() {
         let mut lexer = Lexer::new("    ,\t\n\r.");
 
-        assert_eq!(lexer.ch, Some(' '));
-        assert_eq!(lexer.peek_char(), Some(&' '));
+        assert_eq!(lexer.current(), Some(b' '));
+        assert_eq!(lexer.peek(), Some(b' '));
         lexer.skip_whitespace();
-        assert_eq!(lexer.ch, Some(','));
-        assert_eq!(lexer.peek_char(), Some(&'\t'));
+        assert_eq!(lexer.current(), Some(b','));
+        assert_eq!(lexer.peek(), Some(b'\t'));
         lexer.next_token();
-        assert_eq!(lexer.ch, Some('\t'));
-        assert_eq!(lexer.peek_char(), Some(&'\n'));
+        assert_eq!(lexer.current(), Some(b'\t'));
+        assert_eq!(lexer.peek(), Some(b'\n'));
         lexer.skip_whitespace();
-        assert_eq!(lexer.ch, Some('.'));
-        assert_eq!(lexer.peek_char(), None);
+        assert_eq!(lexer.current(), Some(b'.'));
+        assert_eq!(lexer.peek(), None);
     }
 
     #[test]
-    fn test_peek_char_empty() {
+    fn test_peek_empty() {
         let mut lexer = Lexer::new("");
 
-        assert_eq!(lexer.ch, None);
-        assert_eq!(lexer.peek_char(), None);
-        assert_eq!(lexer.peek_char(), None);
+        assert_eq!(lexer.current(), None);
+        assert_eq!(lexer.peek(), None);
+        assert_eq!(lexer.peek(), None);
         lexer.next_token();
-        assert_eq!(lexer.ch, None);
-        assert_eq!(lexer.peek_char(), None);
-        assert_eq!(lexer.peek_char(), None);
+        assert_eq!(lexer.current(), None);
+        assert_eq!(lexer.peek(), None);
+        assert_eq!(lexer.peek(), None);
     }
 
     #[test]
-    fn test_peek_char_single_char() {
+    fn test_peek_single_char() {
         let mut lexer = Lexer::new(".");
 
-        assert_eq!(lexer.ch, Some('.'));
-        assert_eq!(lexer.peek_char(), None);
-        assert_eq!(lexer.peek_char(), None);
+        assert_eq!(lexer.current(), Some(b'.'));
+        assert_eq!(lexer.peek(), None);
+        assert_eq!(lexer.peek(), None);
         lexer.next_token();
-        assert_eq!(lexer.ch, None);
-        assert_eq!(lexer.peek_char(), None);
-        assert_eq!(lexer.peek_char(), None);
+        assert_eq!(lexer.current(), None);
+        assert_eq!(lexer.peek(), None);
+        assert_eq!(lexer.peek(), None);
     }
 
     #[test]
-    fn test_peek_char() {
+    fn test_peek() {
         let mut lexer = Lexer::new(".,*");
 
-        assert_eq!(lexer.ch, Some('.'));
-        assert_eq!(lexer.peek_char(), Some(&','));
-        assert_eq!(lexer.peek_char(), Some(&','));
+        assert_eq!(lexer.current(), Some(b'.'));
+        assert_eq!(lexer.peek(), Some(b','));
+        assert_eq!(lexer.peek(), Some(b','));
         lexer.next_token();
-        assert_eq!(lexer.ch, Some(','));
-        assert_eq!(lexer.peek_char(), Some(&'*'));
-        assert_eq!(lexer.peek_char(), Some(&'*'));
+        assert_eq!(lexer.current(), Some(b','));
+        assert_eq!(lexer.peek(), Some(b'*'));
+        assert_eq!(lexer.peek(), Some(b'*'));
         lexer.next_token();
-        assert_eq!(lexer.ch, Some('*'));
-        assert_eq!(lexer.peek_char(), None);
-        assert_eq!(lexer.peek_char(), None);
+        assert_eq!(lexer.current(), Some(b'*'));
+        assert_eq!(lexer.peek(), None);
+        assert_eq!(lexer.peek(), None);
     }
 
     #[test]
     fn test_read_char_iterator_empty() {
         let mut lexer = Lexer::new("");
 
-        assert_eq!(lexer.ch, None);
+        assert_eq!(lexer.current(), None);
         lexer.next_token();
-        assert_eq!(lexer.ch, None);
+        assert_eq!(lexer.current(), None);
         lexer.next_token();
-        assert_eq!(lexer.ch, None);
+        assert_eq!(lexer.current(), None);
         lexer.next_token();
-        assert_eq!(lexer.ch, None);
+        assert_eq!(lexer.current(), None);
         lexer.next_token();
     }
 
@@ -301,21 +714,68 @@ mod tests {
     fn test_read_char_iterator() {
         let mut lexer = Lexer::new(",,,,");
 
-        assert_eq!(lexer.ch, Some(','));
+        assert_eq!(lexer.current(), Some(b','));
         lexer.next_token();
-        assert_eq!(lexer.ch, Some(','));
+        assert_eq!(lexer.current(), Some(b','));
         lexer.next_token();
-        assert_eq!(lexer.ch, Some(','));
+        assert_eq!(lexer.current(), Some(b','));
         lexer.next_token();
-        assert_eq!(lexer.ch, Some(','));
+        assert_eq!(lexer.current(), Some(b','));
         lexer.next_token();
-        assert_eq!(lexer.ch, None);
+        assert_eq!(lexer.current(), None);
         lexer.next_token();
-        assert_eq!(lexer.ch, None);
+        assert_eq!(lexer.current(), None);
         lexer.next_token();
-        assert_eq!(lexer.ch, None);
+        assert_eq!(lexer.current(), None);
         lexer.next_token();
-        assert_eq!(lexer.ch, None);
+        assert_eq!(lexer.current(), None);
+    }
+
+    #[test]
+    fn test_skip_comments() {
+        let mut lexer = Lexer::with_comments(indoc! {"
+            // leading
+            let x = 10 / 2; // trailing
+            //
+            x
+        "});
+
+        let token_types: Vec<TokenType> = std::iter::from_fn(|| {
+            let token = lexer.next_token();
+            (token.token_type != TokenType::EOF).then_some(token.token_type)
+        })
+        .collect();
+
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Let,
+                TokenType::identifier("x"),
+                TokenType::Assign,
+                TokenType::integer("10"),
+                TokenType::Slash,
+                TokenType::integer("2"),
+                TokenType::Semicolon,
+                TokenType::identifier("x"),
+            ]
+        );
+        assert_eq!(
+            lexer.comments(),
+            &[
+                Comment {
+                    text: "// leading",
+                    location: Location::new(1, 1),
+                },
+                Comment {
+                    text: "// trailing",
+                    location: Location::new(2, 17),
+                },
+                Comment {
+                    text: "//",
+                    location: Location::new(3, 1),
+                },
+            ]
+        );
     }
 
     #[test]
@@ -356,39 +816,39 @@ mod tests {
 
         let expected_token_types = vec![
             TokenType::Let,
-            TokenType::Identifier(String::from("five")),
+            TokenType::Identifier("five"),
             TokenType::Assign,
             TokenType::integer("5"),
             TokenType::Semicolon,
             TokenType::Let,
-            TokenType::Identifier(String::from("ten")),
+            TokenType::Identifier("ten"),
             TokenType::Assign,
             TokenType::integer("10"),
             TokenType::Semicolon,
             TokenType::Let,
-            TokenType::Identifier(String::from("add")),
+            TokenType::Identifier("add"),
             TokenType::Assign,
             TokenType::Function,
             TokenType::LParen,
-            TokenType::Identifier(String::from("x")),
+            TokenType::Identifier("x"),
             TokenType::Comma,
-            TokenType::Identifier(String::from("y")),
+            TokenType::Identifier("y"),
             TokenType::RParen,
             TokenType::LBrace,
-            TokenType::Identifier(String::from("x")),
+            TokenType::Identifier("x"),
             TokenType::Plus,
-            TokenType::Identifier(String::from("y")),
+            TokenType::Identifier("y"),
             TokenType::Semicolon,
             TokenType::RBrace,
             TokenType::Semicolon,
             TokenType::Let,
-            TokenType::Identifier(String::from("result")),
+            TokenType::Identifier("result"),
             TokenType::Assign,
-            TokenType::Identifier(String::from("add")),
+            TokenType::Identifier("add"),
             TokenType::LParen,
-            TokenType::Identifier(String::from("five")),
+            TokenType::Identifier("five"),
             TokenType::Comma,
-            TokenType::Identifier(String::from("ten")),
+            TokenType::Identifier("ten"),
             TokenType::RParen,
             TokenType::Semicolon,
             TokenType::Bang,
@@ -447,4 +907,106 @@ mod tests {
             assert_eq!(token.token_type, expected_token);
         }
     }
+
+    #[test]
+    fn test_next_token_with_trivia() {
+        let input = "let  x = 10; // ten\r\n\t!= y\n";
+        let mut lexer = Lexer::new(input);
+        let mut tokens = vec![];
+
+        loop {
+            let token = lexer.next_token_with_trivia();
+            let done = token.token.token_type == TokenType::EOF;
+            tokens.push(token);
+            if done {
+                break;
+            }
+        }
+
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text).collect();
+        assert_eq!(texts, vec!["let", "x", "=", "10", ";", "!=", "y", ""]);
+
+        let semicolon = &tokens[4];
+        assert_eq!(semicolon.leading_trivia, vec![]);
+        let not_equal = &tokens[5];
+        assert_eq!(
+            not_equal.leading_trivia,
+            vec![
+                Trivia {
+                    kind: TriviaKind::Whitespace,
+                    text: " "
+                },
+                Trivia {
+                    kind: TriviaKind::Comment,
+                    text: "// ten\r"
+                },
+                Trivia {
+                    kind: TriviaKind::Whitespace,
+                    text: "\n\t"
+                },
+            ]
+        );
+
+        let source: String = tokens
+            .iter()
+            .flat_map(|token| {
+                token
+                    .leading_trivia
+                    .iter()
+                    .map(|trivia| trivia.text)
+                    .chain([token.text])
+            })
+            .collect();
+        assert_eq!(source, input);
+    }
+
+    #[test]
+    fn test_iterator_stops_after_eof() {
+        let tokens: Vec<TokenType> = Lexer::new("let x = 5;")
+            .map(|token| token.token_type)
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Let,
+                TokenType::identifier("x"),
+                TokenType::Assign,
+                TokenType::integer("5"),
+                TokenType::Semicolon,
+                TokenType::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_byte_offsets() {
+        let input = "// é\nlet café = x € 10;";
+        let tokens: Vec<Token> = Lexer::new(input).collect();
+
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text(input)).collect();
+        assert_eq!(texts, vec!["let", "café", "=", "x", "€", "10", ";", ""]);
+
+        assert_eq!(tokens[0].offsets, 6..9);
+        assert_eq!(tokens[1].offsets, 10..15);
+        assert_eq!(tokens[4].token_type, TokenType::Illegal('€'));
+        assert_eq!(tokens[4].offsets, 20..23);
+        assert_eq!(tokens[7].offsets, input.len()..input.len());
+    }
+
+    #[test]
+    fn test_tokens_borrow_input() {
+        let input = String::from("let total = count + 42;");
+        let tokens: Vec<Token> = Lexer::new(&input).collect();
+
+        let TokenType::Identifier(total) = tokens[1].token_type else {
+            panic!("expected an identifier");
+        };
+        let TokenType::Integer(integer) = tokens[5].token_type else {
+            panic!("expected an integer");
+        };
+
+        assert_eq!(total.as_ptr(), input[4..].as_ptr());
+        assert_eq!(integer.as_ptr(), input[20..].as_ptr());
+    }
 }
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct Location {
     line: usize,
     column: usize,
@@ -10,6 +10,31 @@ impl Location {
     pub fn new(line: usize, column: usize) -> Self {
         Self { line, column }
     }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// The location of the last char of `text`, when `text` directly
+    /// follows the char at this location.
+    pub fn after(&self, text: &str) -> Location {
+        let mut location = self.clone();
+
+        for ch in text.chars() {
+            if ch == '\n' {
+                location.line += 1;
+                location.column = 0;
+            } else {
+                location.column += 1;
+            }
+        }
+
+        location
+    }
 }
 
 impl Display for Location {
@@ -26,3 +51,27 @@ impl Clone for Location {
         }
     }
 }
+
+/// Region of source covered by a node, from the first char of its first token
+/// to the last char of its last token.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    pub fn new(start: Location, end: Location) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains(&self, location: &Location) -> bool {
+        self.start <= *location && *location <= self.end
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}:{}", self.start, self.end.line, self.end.column)
+    }
+}
@@ -1,28 +1,83 @@
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
 
 use super::location::Location;
 
 #[derive(Debug, PartialEq, Clone)]
-pub struct Token {
-    pub token_type: TokenType,
+pub struct Token<'a> {
+    pub token_type: TokenType<'a>,
     pub location: Location,
+    /// Byte range of the token in the source, empty for `EOF`.
+    pub offsets: Range<usize>,
 }
 
-impl Token {
-    pub fn new(token_type: TokenType, location: Location) -> Self {
+impl<'a> Token<'a> {
+    pub fn new(token_type: TokenType<'a>, location: Location, offsets: Range<usize>) -> Self {
         Self {
             token_type,
             location,
+            offsets,
         }
     }
+
+    /// The slice of `source` this token was read from.
+    pub fn text<'s>(&self, source: &'s str) -> &'s str {
+        &source[self.offsets.clone()]
+    }
+
+    /// The location of the last char of this token, or its own location for
+    /// `EOF`.
+    pub fn end(&self, source: &str) -> Location {
+        let mut chars = self.text(source).chars();
+        chars.next();
+        self.location.after(chars.as_str())
+    }
+}
+
+/// A `//` comment, which the lexer skips but keeps for tools like the
+/// formatter.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comment<'a> {
+    pub text: &'a str,
+    pub location: Location,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+}
+
+/// Source text between tokens that carries no meaning for the parser.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia<'a> {
+    pub kind: TriviaKind,
+    pub text: &'a str,
+}
+
+/// A token together with its exact source text and the trivia before it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TriviaToken<'a> {
+    pub leading_trivia: Vec<Trivia<'a>>,
+    pub token: Token<'a>,
+    pub text: &'a str,
+}
+
+/// A piece of the text of a string literal.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StringPart<'a> {
+    /// Text as written, escapes included.
+    Text(&'a str),
+    /// The code between `${` and `}`, with its byte offset in the literal.
+    Interpolation(&'a str, usize),
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone)]
-pub enum TokenType {
+pub enum TokenType<'a> {
     Let,
-    Identifier(String),
+    Identifier(&'a str),
     Assign,
-    Integer(String),
+    Integer(&'a str),
     Comma,
     Function,
     LParen,
@@ -48,19 +103,28 @@ pub enum TokenType {
     NotEq,
     Modulo,
     Null,
+    Colon,
+    Arrow,
+    /// The text between the quotes, escapes and interpolations included.
+    String(&'a str),
+    Dot,
+    LBracket,
+    RBracket,
+    Import,
+    Export,
 }
 
-impl TokenType {
-    pub fn identifier(ident: impl Into<String>) -> TokenType {
-        TokenType::Identifier(ident.into())
+impl<'a> TokenType<'a> {
+    pub fn identifier(ident: &'a str) -> TokenType<'a> {
+        TokenType::Identifier(ident)
     }
 
-    pub fn integer(integer: impl Into<String>) -> TokenType {
-        TokenType::Integer(integer.into())
+    pub fn integer(integer: &'a str) -> TokenType<'a> {
+        TokenType::Integer(integer)
     }
 }
 
-impl Display for TokenType {
+impl Display for TokenType<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
             TokenType::Let => write!(f, "let"),
@@ -92,6 +156,14 @@ impl Display for TokenType {
             TokenType::NotEq => write!(f, "!="),
             TokenType::Modulo => write!(f, "%"),
             TokenType::Null => write!(f, "null"),
+            TokenType::Colon => write!(f, ":"),
+            TokenType::Arrow => write!(f, "->"),
+            TokenType::String(string) => write!(f, "string \"{}\"", string),
+            TokenType::Dot => write!(f, "."),
+            TokenType::LBracket => write!(f, "["),
+            TokenType::RBracket => write!(f, "]"),
+            TokenType::Import => write!(f, "import"),
+            TokenType::Export => write!(f, "export"),
         }
     }
 }
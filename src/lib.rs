@@ -0,0 +1,15 @@
+pub mod cli;
+pub mod coverage;
+pub mod debugger;
+pub mod evaluator;
+pub mod export;
+pub mod formatter;
+pub mod lexer;
+pub mod linter;
+pub mod lsp;
+pub mod optimizer;
+pub mod parser;
+pub mod prelude;
+pub mod profiler;
+pub mod repl;
+pub mod typechecker;
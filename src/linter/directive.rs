@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use crate::lexer::token::Comment;
+
+use super::warning::{Warning, WarningCode};
+
+/// Warnings silenced by comment directives.
+///
+/// `// check: allow(W001, shadowed-name)` silences the listed warnings on
+/// its own line when it follows code, or otherwise on the next line with
+/// code. `// check: allow-file(...)` silences them in the whole file.
+#[derive(Debug, Default)]
+pub struct Suppressions {
+    file: Vec<WarningCode>,
+    lines: HashMap<usize, Vec<WarningCode>>,
+}
+
+impl Suppressions {
+    pub fn new(source: &str, comments: &[Comment]) -> Self {
+        let lines: Vec<&str> = source.lines().collect();
+        let mut suppressions = Suppressions::default();
+
+        for comment in comments {
+            let Some((file_wide, codes)) = parse_directive(comment.text) else {
+                continue;
+            };
+
+            if file_wide {
+                suppressions.file.extend(codes);
+                continue;
+            }
+
+            let line = comment.location.line();
+            let target = if follows_code(&lines, line, comment.location.column()) {
+                line
+            } else {
+                next_code_line(&lines, line)
+            };
+            suppressions.lines.entry(target).or_default().extend(codes);
+        }
+
+        suppressions
+    }
+
+    pub fn is_suppressed(&self, warning: &Warning) -> bool {
+        self.file.contains(&warning.code)
+            || self
+                .lines
+                .get(&warning.span.start.line())
+                .is_some_and(|codes| codes.contains(&warning.code))
+    }
+}
+
+/// Parses `// check: allow(...)` or `// check: allow-file(...)`, telling
+/// whether the directive is file wide. Unknown codes are ignored.
+fn parse_directive(comment: &str) -> Option<(bool, Vec<WarningCode>)> {
+    let directive = comment
+        .strip_prefix("//")?
+        .trim()
+        .strip_prefix("check:")?
+        .trim();
+
+    let (file_wide, list) = if let Some(list) = directive.strip_prefix("allow-file(") {
+        (true, list)
+    } else {
+        (false, directive.strip_prefix("allow(")?)
+    };
+
+    let codes = list
+        .strip_suffix(')')?
+        .split(',')
+        .filter_map(|code| WarningCode::parse(code.trim()))
+        .collect();
+
+    Some((file_wide, codes))
+}
+
+/// Whether the comment at `line` and `column` has code before it.
+fn follows_code(lines: &[&str], line: usize, column: usize) -> bool {
+    lines
+        .get(line - 1)
+        .is_some_and(|text| text.chars().take(column - 1).any(|ch| !ch.is_whitespace()))
+}
+
+/// The first line after `line` that is neither blank nor only a comment.
+fn next_code_line(lines: &[&str], line: usize) -> usize {
+    lines
+        .iter()
+        .enumerate()
+        .skip(line)
+        .find(|(_, text)| {
+            let text = text.trim();
+            !text.is_empty() && !text.starts_with("//")
+        })
+        .map(|(index, _)| index + 1)
+        .unwrap_or(line + 1)
+}
@@ -0,0 +1,612 @@
+use crate::{
+    lexer::{lexer::Lexer, location::Span},
+    parser::{
+        ast::{
+            expression::{Expression, Parameter, TemplatePart},
+            operator::InfixOperator,
+            program::Program,
+            statement::{ImportBinding, Statement},
+            visitor::{walk_expression, walk_statement, Visitor},
+        },
+        parser::{Parser, ParserError},
+        source_map::SourceMap,
+    },
+};
+
+use super::{
+    directive::Suppressions,
+    warning::{Warning, WarningCode},
+};
+
+/// Finds likely mistakes in a script without running it.
+pub struct Linter {}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Linter::new()
+    }
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Linter {}
+    }
+
+    /// Parses and checks `input`, leaving out the warnings silenced by
+    /// `// check:` directives.
+    pub fn check(&self, input: &str) -> Result<Vec<Warning>, Vec<ParserError>> {
+        let mut parser = Parser::new(Lexer::with_comments(input));
+        let program = parser.parse_program();
+
+        if !parser.errors.is_empty() {
+            return Err(parser.errors);
+        }
+
+        let suppressions = Suppressions::new(input, parser.comments());
+        let mut warnings = self.check_program(&program, &parser.source_map(&program));
+        warnings.retain(|warning| !suppressions.is_suppressed(warning));
+
+        Ok(warnings)
+    }
+
+    /// Every warning for `program`, sorted by position.
+    pub fn check_program(&self, program: &Program, source_map: &SourceMap) -> Vec<Warning> {
+        let mut checker = Checker {
+            source_map,
+            scopes: vec![],
+            path: vec![],
+            branches: 0,
+            warnings: vec![],
+        };
+        checker.visit_program(program);
+
+        let mut warnings = checker.warnings;
+        warnings.sort_by(|a, b| a.span.start.cmp(&b.span.start));
+        warnings
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum BindingKind {
+    Variable,
+    Parameter,
+}
+
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    kind: BindingKind,
+    /// Parameter count when the value is a function literal.
+    arity: Option<usize>,
+    /// Branches of `if` expressions the binding was made in.
+    path: Vec<usize>,
+    span: Span,
+    used: bool,
+    /// Whether a later binding of the same name always replaces this one.
+    replaced: bool,
+}
+
+/// Only function bodies open a scope, like in the evaluator; a `let` in an
+/// `if` branch binds in the enclosing function. Branch paths tell bindings
+/// that always run before some code from ones that only might.
+struct Checker<'a> {
+    source_map: &'a SourceMap<'a>,
+    scopes: Vec<Vec<Binding>>,
+    path: Vec<usize>,
+    branches: usize,
+    warnings: Vec<Warning>,
+}
+
+impl Checker<'_> {
+    fn warn(&mut self, code: WarningCode, msg: String, span: Span) {
+        self.warnings.push(Warning::new(code, msg, span));
+    }
+
+    fn statement_span(&self, statement: &Statement) -> Span {
+        self.source_map
+            .statement(statement)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn expression_span(&self, expression: &Expression) -> Span {
+        self.source_map
+            .expression(expression)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn parameter_span(&self, parameter: &Parameter) -> Span {
+        self.source_map
+            .parameter(parameter)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn declare(&mut self, name: &str, kind: BindingKind, arity: Option<usize>, span: Span) {
+        let shadowed = self.scopes.iter().flatten().any(|binding| {
+            binding.name == name && !binding.replaced && self.path.starts_with(&binding.path)
+        });
+        if shadowed && !name.starts_with('_') {
+            self.warn(
+                WarningCode::ShadowedName,
+                format!("{} shadows an earlier binding", name),
+                span.clone(),
+            );
+        }
+
+        let path = self.path.clone();
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        for binding in scope.iter_mut() {
+            if binding.name == name && binding.path.starts_with(&path) {
+                binding.replaced = true;
+            }
+        }
+        scope.push(Binding {
+            name: name.to_string(),
+            kind,
+            arity,
+            path,
+            span,
+            used: false,
+            replaced: false,
+        });
+    }
+
+    /// The bindings `name` may refer to: the live ones of the innermost
+    /// scope that has any.
+    fn resolve(&mut self, name: &str) -> Vec<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .map(|scope| {
+                scope
+                    .iter_mut()
+                    .filter(|binding| binding.name == name && !binding.replaced)
+                    .collect::<Vec<_>>()
+            })
+            .find(|bindings| !bindings.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Parameter count of the function `name` refers to, if it is known.
+    fn arity(&mut self, name: &str) -> Option<usize> {
+        let arities: Vec<Option<usize>> = self
+            .resolve(name)
+            .into_iter()
+            .map(|binding| binding.arity)
+            .collect();
+
+        match arities.first() {
+            Some(&arity) if arities.iter().all(|other| *other == arity) => arity,
+            _ => None,
+        }
+    }
+
+    fn pop_scope(&mut self) {
+        for binding in self.scopes.pop().unwrap_or_default() {
+            if binding.used || binding.name.starts_with('_') {
+                continue;
+            }
+
+            let (code, msg) = match binding.kind {
+                BindingKind::Variable => (
+                    WarningCode::UnusedVariable,
+                    format!("{} is never used", binding.name),
+                ),
+                BindingKind::Parameter => (
+                    WarningCode::UnusedParameter,
+                    format!("parameter {} is never used", binding.name),
+                ),
+            };
+            self.warn(code, msg, binding.span);
+        }
+    }
+
+    fn branch(&mut self, statements: &[Statement]) {
+        self.branches += 1;
+        self.path.push(self.branches);
+        self.visit_statements(statements);
+        self.path.pop();
+    }
+
+    fn check_call(&mut self, call: &Expression, function: &Expression, arguments: &[Expression]) {
+        let arity = match function {
+            Expression::Int(_)
+            | Expression::Bool(_)
+            | Expression::String(_)
+            | Expression::Template(_)
+            | Expression::Null
+            | Expression::Array(_) => {
+                self.warn(
+                    WarningCode::NotCallable,
+                    format!("{} is not a function", function),
+                    self.expression_span(call),
+                );
+                return;
+            }
+            Expression::Identifier(name) => self.arity(name),
+            Expression::Function { parameters, .. } => Some(parameters.len()),
+            _ => None,
+        };
+
+        if let Some(arity) = arity.filter(|arity| *arity != arguments.len()) {
+            self.warn(
+                WarningCode::WrongArgumentCount,
+                format!(
+                    "wrong number of arguments to {}: got {}, but function wants {}",
+                    function,
+                    arguments.len(),
+                    arity
+                ),
+                self.expression_span(call),
+            );
+        }
+    }
+
+    fn check_comparison(
+        &mut self,
+        comparison: &Expression,
+        lhs: &Expression,
+        operator: &InfixOperator,
+        rhs: &Expression,
+    ) {
+        let always = match operator {
+            InfixOperator::Equal => true,
+            InfixOperator::NotEqual | InfixOperator::LessThan | InfixOperator::GreaterThan => false,
+            _ => return,
+        };
+
+        if lhs == rhs && is_pure(lhs) {
+            self.warn(
+                WarningCode::SelfComparison,
+                format!("comparing {} with itself is always {}", lhs, always),
+                self.expression_span(comparison),
+            );
+        }
+    }
+}
+
+/// Whether evaluating `expression` twice always gives the same value.
+fn is_pure(expression: &Expression) -> bool {
+    match expression {
+        Expression::Int(_)
+        | Expression::Bool(_)
+        | Expression::String(_)
+        | Expression::Identifier(_)
+        | Expression::Null => true,
+        Expression::Prefix { rhs, .. } => is_pure(rhs),
+        Expression::Infix { lhs, rhs, .. } => is_pure(lhs) && is_pure(rhs),
+        Expression::Array(elements) => elements.iter().all(is_pure),
+        Expression::Template(parts) => parts.iter().all(|part| match part {
+            TemplatePart::Text(_) => true,
+            TemplatePart::Expression(expression) => is_pure(expression),
+        }),
+        Expression::Index { left, index } => is_pure(left) && is_pure(index),
+        _ => false,
+    }
+}
+
+impl Visitor for Checker<'_> {
+    fn visit_program(&mut self, program: &Program) {
+        self.scopes.push(vec![]);
+        self.visit_statements(&program.statements);
+        self.pop_scope();
+    }
+
+    fn visit_statements(&mut self, statements: &[Statement]) {
+        let mut returned = false;
+
+        for statement in statements {
+            if returned {
+                self.warn(
+                    WarningCode::UnreachableCode,
+                    "unreachable statement after return".to_string(),
+                    self.statement_span(statement),
+                );
+                returned = false;
+            }
+
+            self.visit_statement(statement);
+
+            if let Statement::Return { .. } = statement {
+                returned = true;
+            }
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        let (name, value) = match statement {
+            Statement::Let { name, value, .. } => (name, value),
+            Statement::Import { binding, .. } => {
+                let span = self.statement_span(statement);
+                let names = match binding {
+                    ImportBinding::Module(alias) => std::slice::from_ref(alias),
+                    ImportBinding::Names(names) => names.as_slice(),
+                };
+                for name in names {
+                    self.declare(name, BindingKind::Variable, None, span.clone());
+                }
+                return;
+            }
+            // an exported name is used by the modules importing it
+            Statement::Export(names) => {
+                for name in names {
+                    for binding in self.resolve(name) {
+                        binding.used = true;
+                    }
+                }
+                return;
+            }
+            _ => return walk_statement(self, statement),
+        };
+
+        let span = self.statement_span(statement);
+
+        // declared before the body, so that a recursive call resolves to this
+        // binding and has its argument count checked against the parameters
+        if let Expression::Function { parameters, .. } = value {
+            self.declare(name, BindingKind::Variable, Some(parameters.len()), span);
+            self.visit_expression(value);
+        } else {
+            self.visit_expression(value);
+            self.declare(name, BindingKind::Variable, None, span);
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(name) => {
+                for binding in self.resolve(name) {
+                    binding.used = true;
+                }
+            }
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.visit_expression(condition);
+                self.branch(consequence);
+                if let Some(alternative) = alternative {
+                    self.branch(alternative);
+                }
+            }
+            Expression::Function {
+                parameters, body, ..
+            } => {
+                self.scopes.push(vec![]);
+                for parameter in parameters.iter() {
+                    let span = self.parameter_span(parameter);
+                    self.declare(&parameter.name, BindingKind::Parameter, None, span);
+                }
+                self.visit_statements(body);
+                self.pop_scope();
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                self.check_call(expression, function, arguments);
+                walk_expression(self, expression);
+            }
+            Expression::Infix { lhs, operator, rhs } => {
+                self.check_comparison(expression, lhs, operator, rhs);
+                walk_expression(self, expression);
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::lexer::location::{Location, Span};
+
+    use super::{Linter, WarningCode};
+
+    fn codes(input: &str) -> Vec<(WarningCode, usize)> {
+        Linter::new()
+            .check(input)
+            .unwrap()
+            .into_iter()
+            .map(|warning| (warning.code, warning.span.start.line()))
+            .collect()
+    }
+
+    #[test]
+    fn test_unused_bindings() {
+        let input = indoc! {"
+            let unused = 1;
+            let f = fn(a, b, _c) { a };
+            f(1, 2, 3);
+            let _ignored = 2;
+        "};
+
+        assert_eq!(
+            codes(input),
+            vec![
+                (WarningCode::UnusedVariable, 1),
+                (WarningCode::UnusedParameter, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_parameter_span() {
+        let input = indoc! {"
+            let f = fn(a, b: int) { a };
+            f(1, 2);
+        "};
+
+        let warnings = Linter::new().check(input).unwrap();
+        let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec!["@1:15-1:20 warning[W002]: parameter b is never used (unused-parameter)"]
+        );
+    }
+
+    #[test]
+    fn test_recursive_function_is_used() {
+        let input = indoc! {"
+            let count = fn(n) { if (n > 0) { count(n - 1) } else { 0 } };
+            count(3);
+        "};
+
+        assert_eq!(codes(input), vec![]);
+    }
+
+    #[test]
+    fn test_shadowed_names() {
+        let input = indoc! {"
+            let x = 1;
+            let f = fn(x) { x };
+            let y = f(x);
+            let y = y + 1;
+            if (y > 1) { let z = 1; z } else { let z = 2; z };
+            y;
+        "};
+
+        assert_eq!(
+            codes(input),
+            vec![
+                (WarningCode::ShadowedName, 2),
+                (WarningCode::ShadowedName, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conditional_rebinding_counts_as_use() {
+        let input = indoc! {"
+            let x = 1;
+            if (true) { let x = 2; };
+            x;
+        "};
+
+        assert_eq!(codes(input), vec![(WarningCode::ShadowedName, 2)]);
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        let input = indoc! {"
+            let f = fn() {
+                return 1;
+                let a = 2;
+                a
+            };
+            f();
+        "};
+
+        assert_eq!(codes(input), vec![(WarningCode::UnreachableCode, 3)]);
+    }
+
+    #[test]
+    fn test_calls() {
+        let input = indoc! {"
+            let add = fn(a, b) { a + b };
+            add(1);
+            add(1, 2);
+            fn(a) { a }(1, 2);
+            5(1);
+            true();
+            \"${add}\"();
+        "};
+
+        let warnings = Linter::new().check(input).unwrap();
+        let messages: Vec<String> = warnings.iter().map(|w| w.msg.clone()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "wrong number of arguments to add: got 1, but function wants 2",
+                "wrong number of arguments to fn(a) { a }: got 2, but function wants 1",
+                "5 is not a function",
+                "true is not a function",
+                "\"${add}\" is not a function",
+            ]
+        );
+        assert_eq!(
+            warnings[0].span,
+            Span::new(Location::new(2, 1), Location::new(2, 6))
+        );
+    }
+
+    #[test]
+    fn test_unknown_arity_is_not_checked() {
+        let input = indoc! {"
+            let f = fn(a) { a };
+            if (true) { let f = fn(a, b) { a + b }; };
+            f(1);
+        "};
+
+        assert_eq!(codes(input), vec![(WarningCode::ShadowedName, 2)]);
+    }
+
+    #[test]
+    fn test_self_comparison() {
+        let input = indoc! {"
+            let x = 1;
+            x == x;
+            -x < -x;
+            x == 1;
+            f() == f();
+        "};
+
+        let warnings = Linter::new().check(input).unwrap();
+        let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "@2:1-2:6 warning[W007]: comparing x with itself is always true (self-comparison)",
+                "@3:1-3:7 warning[W007]: comparing (-x) with itself is always false (self-comparison)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suppression_directives() {
+        let input = indoc! {"
+            // check: allow-file(shadowed-name)
+            let a = 1; // check: allow(W001)
+            // check: allow(unused-variable, W002)
+
+            let f = fn(x) { 1 };
+            let a = 2;
+            f(a) == f(a);
+        "};
+
+        assert_eq!(codes(input), vec![]);
+    }
+
+    #[test]
+    fn test_imports_and_exports() {
+        let input = indoc! {"
+            import \"math\" as math;
+            import { min, max } from \"math\";
+            let helper = fn(x) { x };
+            let clamp = fn(x) { math.abs(min(x, 10)) };
+            export clamp;
+        "};
+
+        assert_eq!(
+            codes(input),
+            vec![
+                (WarningCode::UnusedVariable, 2),
+                (WarningCode::UnusedVariable, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_errors() {
+        assert_eq!(Linter::new().check("let = 1;").unwrap_err().len(), 1);
+    }
+}
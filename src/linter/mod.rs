@@ -0,0 +1,4 @@
+pub mod directive;
+#[allow(clippy::module_inception)]
+pub mod linter;
+pub mod warning;
@@ -0,0 +1,95 @@
+use std::fmt::Display;
+
+use crate::lexer::location::Span;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WarningCode {
+    UnusedVariable,
+    UnusedParameter,
+    ShadowedName,
+    UnreachableCode,
+    WrongArgumentCount,
+    NotCallable,
+    SelfComparison,
+}
+
+impl WarningCode {
+    pub const ALL: [WarningCode; 7] = [
+        WarningCode::UnusedVariable,
+        WarningCode::UnusedParameter,
+        WarningCode::ShadowedName,
+        WarningCode::UnreachableCode,
+        WarningCode::WrongArgumentCount,
+        WarningCode::NotCallable,
+        WarningCode::SelfComparison,
+    ];
+
+    /// The short code, like `W001`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            WarningCode::UnusedVariable => "W001",
+            WarningCode::UnusedParameter => "W002",
+            WarningCode::ShadowedName => "W003",
+            WarningCode::UnreachableCode => "W004",
+            WarningCode::WrongArgumentCount => "W005",
+            WarningCode::NotCallable => "W006",
+            WarningCode::SelfComparison => "W007",
+        }
+    }
+
+    /// The descriptive name, like `unused-variable`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WarningCode::UnusedVariable => "unused-variable",
+            WarningCode::UnusedParameter => "unused-parameter",
+            WarningCode::ShadowedName => "shadowed-name",
+            WarningCode::UnreachableCode => "unreachable-code",
+            WarningCode::WrongArgumentCount => "wrong-argument-count",
+            WarningCode::NotCallable => "not-callable",
+            WarningCode::SelfComparison => "self-comparison",
+        }
+    }
+
+    /// Looks a code up by its short code or its name.
+    pub fn parse(code: &str) -> Option<WarningCode> {
+        WarningCode::ALL
+            .into_iter()
+            .find(|candidate| candidate.code() == code || candidate.name() == code)
+    }
+}
+
+impl Display for WarningCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub msg: String,
+    pub span: Span,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, msg: impl Into<String>, span: Span) -> Self {
+        Warning {
+            code,
+            msg: msg.into(),
+            span,
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} warning[{}]: {} ({})",
+            self.span,
+            self.code,
+            self.msg,
+            self.code.name()
+        )
+    }
+}
@@ -0,0 +1,658 @@
+use crate::{
+    lexer::{
+        lexer::{split_string, Lexer},
+        location::Location,
+        token::{StringPart, TokenType, TriviaToken},
+    },
+    parser::cst::{
+        cst::{SyntaxElement, SyntaxKind, SyntaxNode},
+        lower::lower_annotation,
+        parser::CstParser,
+    },
+};
+
+/// Source range from the first character up to, but not including, `end`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextRange {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl TextRange {
+    pub fn new(start: Location, end: Location) -> Self {
+        TextRange { start, end }
+    }
+
+    fn of_token(token: &TriviaToken) -> Self {
+        let start = token.token.location.clone();
+        let end = Location::new(start.line(), start.column() + token.text.chars().count());
+        TextRange::new(start, end)
+    }
+
+    fn of_node(node: &SyntaxNode) -> Self {
+        match (node.first_token(), node.last_token()) {
+            (Some(first), Some(last)) => {
+                TextRange::new(first.token.location.clone(), TextRange::of_token(last).end)
+            }
+            _ => TextRange::new(Location::default(), Location::default()),
+        }
+    }
+
+    /// Whether `location` is in the range or right at its end, where a
+    /// cursor after the last character still touches it.
+    pub fn touches(&self, location: &Location) -> bool {
+        self.start <= *location && *location <= self.end
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DefinitionKind {
+    Variable,
+    Function,
+    Parameter,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    /// The name where it is bound.
+    pub range: TextRange,
+    /// The whole `let` statement, or the parameter with its annotation.
+    pub declaration: TextRange,
+    /// How the binding is written, like `let f = fn(a: int) -> int`.
+    pub detail: String,
+    scope: usize,
+    /// Where the name starts to be visible in its scope.
+    visible_from: Location,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Reference {
+    pub name: String,
+    pub range: TextRange,
+    pub definition: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Scope {
+    range: TextRange,
+}
+
+/// Names bound and used in a document, found on the concrete syntax tree so
+/// that documents with syntax errors can still be navigated.
+///
+/// Like in the evaluator, only function bodies open a scope. Functions see
+/// their enclosing environments when they are called rather than when they
+/// are written, so a name a function body uses before it is bound resolves
+/// to a later binding in an enclosing scope.
+#[derive(Debug)]
+pub struct Analysis {
+    pub definitions: Vec<Definition>,
+    pub references: Vec<Reference>,
+    scopes: Vec<Scope>,
+    /// Every token, in order.
+    tokens: Vec<TextRange>,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let tree = CstParser::new(Lexer::new(source)).parse_program();
+
+        let mut analysis = Analysis {
+            definitions: vec![],
+            references: vec![],
+            scopes: vec![],
+            tokens: vec![],
+        };
+        let mut walker = Walker {
+            analysis: &mut analysis,
+            stack: vec![],
+            unresolved: vec![],
+        };
+        walker.walk_program(&tree);
+
+        analysis
+    }
+
+    /// The definition of the name at `location`, which is either used or
+    /// bound there.
+    pub fn definition_at(&self, location: &Location) -> Option<&Definition> {
+        let reference = self
+            .references
+            .iter()
+            .find(|reference| reference.range.touches(location));
+
+        match reference {
+            Some(reference) => reference.definition.map(|index| &self.definitions[index]),
+            None => self
+                .definitions
+                .iter()
+                .find(|definition| definition.range.touches(location)),
+        }
+    }
+
+    /// Top-level `let` bindings, in order.
+    pub fn symbols(&self) -> impl Iterator<Item = &Definition> {
+        self.definitions
+            .iter()
+            .filter(|definition| definition.scope == 0)
+    }
+
+    /// The definitions a name written at `location` could refer to, the
+    /// innermost and latest first, one per name.
+    pub fn visible_at(&self, location: &Location) -> Vec<&Definition> {
+        let mut visible: Vec<&Definition> = vec![];
+
+        for definition in self.definitions.iter().rev() {
+            if self.scopes[definition.scope].range.touches(location)
+                && definition.visible_from <= *location
+                && !visible.iter().any(|other| other.name == definition.name)
+            {
+                visible.push(definition);
+            }
+        }
+
+        visible
+    }
+
+    /// Where the token starting at `start` ends, or one character past
+    /// `start` when no token starts there.
+    pub fn token_end(&self, start: &Location) -> Location {
+        match self.tokens.binary_search_by(|token| token.start.cmp(start)) {
+            Ok(index) if self.tokens[index].end > *start => self.tokens[index].end.clone(),
+            _ => Location::new(start.line(), start.column() + 1),
+        }
+    }
+}
+
+struct Walker<'w> {
+    analysis: &'w mut Analysis,
+    /// Open scopes, each with the definitions made in it so far.
+    stack: Vec<(usize, Vec<usize>)>,
+    /// References not bound when they were found, with the scopes open then.
+    unresolved: Vec<(usize, Vec<usize>)>,
+}
+
+impl Walker<'_> {
+    fn walk_program(&mut self, program: &SyntaxNode) {
+        self.collect_tokens(program);
+
+        let range = TextRange::of_node(program);
+        self.open_scope(TextRange::new(Location::new(1, 1), range.end));
+        self.walk_children(program);
+        self.stack.pop();
+
+        self.resolve_late();
+    }
+
+    fn collect_tokens(&mut self, node: &SyntaxNode) {
+        for child in &node.children {
+            match child {
+                SyntaxElement::Token(token) => {
+                    self.analysis.tokens.push(TextRange::of_token(token))
+                }
+                SyntaxElement::Node(node) => self.collect_tokens(node),
+            }
+        }
+    }
+
+    fn open_scope(&mut self, range: TextRange) {
+        self.analysis.scopes.push(Scope { range });
+        self.stack.push((self.analysis.scopes.len() - 1, vec![]));
+    }
+
+    fn walk_children(&mut self, node: &SyntaxNode) {
+        for child in node.nodes() {
+            self.walk(child);
+        }
+    }
+
+    fn walk(&mut self, node: &SyntaxNode) {
+        match node.kind {
+            SyntaxKind::LetStatement => self.walk_let(node),
+            SyntaxKind::ImportStatement => self.walk_import(node),
+            SyntaxKind::ExportStatement => {
+                for token in node.tokens() {
+                    if let TokenType::Identifier(_) = token.token.token_type {
+                        self.reference(token);
+                    }
+                }
+            }
+            SyntaxKind::FunctionLiteral => self.walk_function(node),
+            SyntaxKind::Identifier => {
+                if let Some(token) = node.first_token() {
+                    self.reference(token);
+                }
+            }
+            SyntaxKind::String => {
+                if let Some(token) = node.first_token() {
+                    self.walk_string(token);
+                }
+            }
+            SyntaxKind::TypeAnnotation => {}
+            _ => self.walk_children(node),
+        }
+    }
+
+    /// Walks the code of the interpolations in a string literal, which the
+    /// tree keeps as a single token.
+    fn walk_string(&mut self, token: &TriviaToken) {
+        let TokenType::String(literal) = token.token.token_type else {
+            return;
+        };
+
+        for part in split_string(literal) {
+            if let StringPart::Interpolation(code, offset) = part {
+                let start = token.token.location.after(&literal[..offset]);
+                let tree = CstParser::new(Lexer::continuing_from(code, start)).parse_program();
+                self.walk_children(&tree);
+            }
+        }
+    }
+
+    fn walk_let(&mut self, node: &SyntaxNode) {
+        let name = node
+            .tokens()
+            .find(|token| matches!(token.token.token_type, TokenType::Identifier(_)));
+        let annotation = node
+            .nodes()
+            .find(|child| child.kind == SyntaxKind::TypeAnnotation)
+            .map(annotation_text);
+        let value = node
+            .nodes()
+            .filter(|child| child.kind != SyntaxKind::TypeAnnotation)
+            .last();
+
+        let Some(name) = name else {
+            return self.walk_children(node);
+        };
+
+        let declaration = TextRange::of_node(node);
+        let mut definition = Definition {
+            name: name.text.to_string(),
+            kind: DefinitionKind::Variable,
+            range: TextRange::of_token(name),
+            declaration: declaration.clone(),
+            detail: match annotation {
+                Some(annotation) => format!("let {}: {}", name.text, annotation),
+                None => format!("let {}", name.text),
+            },
+            scope: 0,
+            visible_from: declaration.end,
+        };
+
+        // a function is visible from its own name on, so that go to definition
+        // and hover resolve the recursive calls in its body
+        match value {
+            Some(value) if value.kind == SyntaxKind::FunctionLiteral => {
+                definition.kind = DefinitionKind::Function;
+                definition.detail = format!("{} = {}", definition.detail, signature(value));
+                definition.visible_from = definition.range.start.clone();
+                self.define(definition);
+                self.walk(value);
+            }
+            _ => {
+                self.walk_children(node);
+                self.define(definition);
+            }
+        }
+    }
+
+    fn walk_import(&mut self, node: &SyntaxNode) {
+        let path = node
+            .tokens()
+            .find(|token| matches!(token.token.token_type, TokenType::String(_)))
+            .map(|token| token.text)
+            .unwrap_or_default();
+        let declaration = TextRange::of_node(node);
+
+        // either the names in braces, or the alias after `as`
+        let names: Vec<(&TriviaToken, String)> = match node.nodes().next() {
+            Some(list) => list
+                .tokens()
+                .filter(|token| matches!(token.token.token_type, TokenType::Identifier(_)))
+                .map(|name| (name, format!("import {{ {} }} from {}", name.text, path)))
+                .collect(),
+            None => node
+                .tokens()
+                .filter(|token| matches!(token.token.token_type, TokenType::Identifier(_)))
+                .skip(1)
+                .map(|alias| (alias, format!("import {} as {}", path, alias.text)))
+                .collect(),
+        };
+
+        for (name, detail) in names {
+            self.define(Definition {
+                name: name.text.to_string(),
+                kind: DefinitionKind::Variable,
+                range: TextRange::of_token(name),
+                declaration: declaration.clone(),
+                detail,
+                scope: 0,
+                visible_from: declaration.end.clone(),
+            });
+        }
+    }
+
+    fn walk_function(&mut self, node: &SyntaxNode) {
+        self.open_scope(TextRange::of_node(node));
+
+        let parameters = node
+            .nodes()
+            .find(|child| child.kind == SyntaxKind::ParameterList);
+        for (name, annotation) in parameters.into_iter().flat_map(parameters_of) {
+            let range = TextRange::of_token(name);
+            let declaration = match annotation {
+                Some(annotation) => {
+                    TextRange::new(range.start.clone(), TextRange::of_node(annotation).end)
+                }
+                None => range.clone(),
+            };
+            self.define(Definition {
+                name: name.text.to_string(),
+                kind: DefinitionKind::Parameter,
+                detail: match annotation {
+                    Some(annotation) => format!("{}: {}", name.text, annotation_text(annotation)),
+                    None => name.text.to_string(),
+                },
+                visible_from: range.start.clone(),
+                range,
+                declaration,
+                scope: 0,
+            });
+        }
+
+        for child in node.nodes() {
+            if child.kind == SyntaxKind::Block {
+                self.walk(child);
+            }
+        }
+
+        self.stack.pop();
+    }
+
+    fn define(&mut self, mut definition: Definition) {
+        let (scope, definitions) = self.stack.last_mut().expect("a scope is always open");
+        definition.scope = *scope;
+        definitions.push(self.analysis.definitions.len());
+        self.analysis.definitions.push(definition);
+    }
+
+    fn reference(&mut self, token: &TriviaToken) {
+        let definition = self
+            .stack
+            .iter()
+            .rev()
+            .flat_map(|(_, definitions)| definitions.iter().rev())
+            .find(|index| self.analysis.definitions[**index].name == token.text)
+            .copied();
+
+        if definition.is_none() {
+            let scopes = self.stack.iter().map(|(scope, _)| *scope).collect();
+            self.unresolved
+                .push((self.analysis.references.len(), scopes));
+        }
+
+        self.analysis.references.push(Reference {
+            name: token.text.to_string(),
+            range: TextRange::of_token(token),
+            definition,
+        });
+    }
+
+    /// Binds the names used inside functions before they were defined in an
+    /// enclosing scope, to the first definition made there afterwards.
+    fn resolve_late(&mut self) {
+        let analysis = &mut *self.analysis;
+
+        for (index, scopes) in self.unresolved.drain(..) {
+            let name = &analysis.references[index].name;
+
+            // the scope the reference is directly in has run up to it already
+            let enclosing = &scopes[..scopes.len().saturating_sub(1)];
+            let definition = enclosing.iter().rev().find_map(|scope| {
+                analysis
+                    .definitions
+                    .iter()
+                    .position(|definition| definition.scope == *scope && definition.name == *name)
+            });
+
+            analysis.references[index].definition = definition;
+        }
+    }
+}
+
+/// The parameter names of a `SyntaxKind::ParameterList`, each with the
+/// annotation after it.
+fn parameters_of<'n, 'a>(
+    parameters: &'n SyntaxNode<'a>,
+) -> Vec<(&'n TriviaToken<'a>, Option<&'n SyntaxNode<'a>>)> {
+    let mut result: Vec<(&TriviaToken, Option<&SyntaxNode>)> = vec![];
+
+    for child in &parameters.children {
+        match child {
+            SyntaxElement::Token(token) => {
+                if let TokenType::Identifier(_) = token.token.token_type {
+                    result.push((token, None));
+                }
+            }
+            SyntaxElement::Node(node) if node.kind == SyntaxKind::TypeAnnotation => {
+                if let Some(last) = result.last_mut() {
+                    last.1 = Some(node);
+                }
+            }
+            SyntaxElement::Node(_) => {}
+        }
+    }
+
+    result
+}
+
+/// A function literal without its body, like `fn(a: int, b) -> int`.
+fn signature(function: &SyntaxNode) -> String {
+    let parameters: Vec<String> = function
+        .nodes()
+        .filter(|child| child.kind == SyntaxKind::ParameterList)
+        .flat_map(parameters_of)
+        .map(|(name, annotation)| match annotation {
+            Some(annotation) => format!("{}: {}", name.text, annotation_text(annotation)),
+            None => name.text.to_string(),
+        })
+        .collect();
+
+    let result = function
+        .nodes()
+        .find(|child| child.kind == SyntaxKind::TypeAnnotation)
+        .map(|result| format!(" -> {}", annotation_text(result)))
+        .unwrap_or_default();
+
+    format!("fn({}){}", parameters.join(", "), result)
+}
+
+/// The annotation as written, normalized when it has no errors.
+fn annotation_text(annotation: &SyntaxNode) -> String {
+    match lower_annotation(annotation) {
+        Ok(annotation) => annotation.to_string(),
+        Err(_) => annotation.to_string().trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::lexer::location::Location;
+
+    use super::{Analysis, DefinitionKind};
+
+    const SOURCE: &str = indoc! {"
+        let base: int = 10;
+        let scale = fn(x: int, f) -> int {
+            let y = x * base;
+            f(y) + later
+        };
+        let later = scale(1, fn(n) { n });
+    "};
+
+    /// The line and column where a definition is bound, if any.
+    fn definition_at(analysis: &Analysis, line: usize, column: usize) -> Option<(usize, usize)> {
+        analysis
+            .definition_at(&Location::new(line, column))
+            .map(|definition| {
+                (
+                    definition.range.start.line(),
+                    definition.range.start.column(),
+                )
+            })
+    }
+
+    #[test]
+    fn test_definitions() {
+        let analysis = Analysis::new(SOURCE);
+
+        let tests = vec![
+            // `base` in the body
+            ((3, 17), Some((1, 5))),
+            // `x` in the body, also from right after the name
+            ((3, 13), Some((2, 16))),
+            ((3, 14), Some((2, 16))),
+            // `f` and `y`
+            ((4, 5), Some((2, 24))),
+            ((4, 7), Some((3, 9))),
+            // `later` is bound after the function but before it is called
+            ((4, 12), Some((6, 5))),
+            // `scale` in its own call, and a name at its definition
+            ((6, 13), Some((2, 5))),
+            ((1, 6), Some((1, 5))),
+            // `n` in the callback, and a keyword
+            ((6, 30), Some((6, 25))),
+            ((1, 1), None),
+        ];
+
+        for ((line, column), expected) in tests {
+            assert_eq!(
+                definition_at(&analysis, line, column),
+                expected,
+                "at {}:{}",
+                line,
+                column
+            );
+        }
+    }
+
+    #[test]
+    fn test_details() {
+        let analysis = Analysis::new(SOURCE);
+
+        let details: Vec<(&str, DefinitionKind, &str)> = analysis
+            .definitions
+            .iter()
+            .map(|definition| {
+                (
+                    definition.name.as_str(),
+                    definition.kind,
+                    definition.detail.as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            details,
+            vec![
+                ("base", DefinitionKind::Variable, "let base: int"),
+                (
+                    "scale",
+                    DefinitionKind::Function,
+                    "let scale = fn(x: int, f) -> int"
+                ),
+                ("x", DefinitionKind::Parameter, "x: int"),
+                ("f", DefinitionKind::Parameter, "f"),
+                ("y", DefinitionKind::Variable, "let y"),
+                ("n", DefinitionKind::Parameter, "n"),
+                ("later", DefinitionKind::Variable, "let later"),
+            ]
+        );
+
+        let symbols: Vec<&str> = analysis
+            .symbols()
+            .map(|definition| definition.name.as_str())
+            .collect();
+        assert_eq!(symbols, vec!["base", "scale", "later"]);
+    }
+
+    #[test]
+    fn test_visible_at() {
+        let analysis = Analysis::new(SOURCE);
+
+        let visible = |line, column| -> Vec<String> {
+            analysis
+                .visible_at(&Location::new(line, column))
+                .iter()
+                .map(|definition| definition.name.clone())
+                .collect()
+        };
+
+        assert_eq!(visible(1, 1), Vec::<String>::new());
+        assert_eq!(visible(4, 5), vec!["y", "f", "x", "scale", "base"]);
+        assert_eq!(visible(7, 1), vec!["later", "scale", "base"]);
+    }
+
+    #[test]
+    fn test_imports() {
+        let analysis = Analysis::new(indoc! {"
+            import \"lib/math.mk\" as math;
+            import { min } from \"lib/math.mk\";
+            let a = math.abs(min(1, 2));
+            export a;
+        "});
+
+        let details: Vec<&str> = analysis
+            .definitions
+            .iter()
+            .map(|definition| definition.detail.as_str())
+            .collect();
+        assert_eq!(
+            details,
+            vec![
+                "import \"lib/math.mk\" as math",
+                "import { min } from \"lib/math.mk\"",
+                "let a",
+            ]
+        );
+
+        assert_eq!(definition_at(&analysis, 3, 10), Some((1, 25)));
+        assert_eq!(definition_at(&analysis, 3, 18), Some((2, 10)));
+        assert_eq!(definition_at(&analysis, 4, 8), Some((3, 5)));
+        // the name after the dot belongs to the module
+        assert_eq!(definition_at(&analysis, 3, 15), None);
+    }
+
+    #[test]
+    fn test_interpolations() {
+        let analysis = Analysis::new(indoc! {"
+            let name = \"Ann\";
+            let greet = fn(x) { \"hi ${name}, ${ \"${x}\" }\" };
+        "});
+
+        assert_eq!(definition_at(&analysis, 2, 27), Some((1, 5)));
+        assert_eq!(definition_at(&analysis, 2, 40), Some((2, 16)));
+        assert_eq!(definition_at(&analysis, 2, 23), None);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let analysis = Analysis::new(indoc! {"
+            let a = 1;
+            let b = a +;
+            a
+        "});
+
+        assert_eq!(definition_at(&analysis, 3, 1), Some((1, 5)));
+        assert_eq!(definition_at(&analysis, 2, 9), Some((1, 5)));
+        assert_eq!(
+            analysis.token_end(&Location::new(2, 11)),
+            Location::new(2, 12)
+        );
+    }
+}
@@ -0,0 +1,563 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+use crate::{lexer::location::Location, linter::linter::Linter};
+
+use super::{
+    analysis::{Analysis, DefinitionKind, TextRange},
+    transport::{read_message, write_message, LspError},
+};
+
+const KEYWORDS: [&str; 8] = ["let", "fn", "if", "else", "return", "true", "false", "null"];
+
+// JSON-RPC and LSP error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// LSP enumerations
+const SEVERITY_ERROR: u64 = 1;
+const SEVERITY_WARNING: u64 = 2;
+const SYMBOL_FUNCTION: u64 = 12;
+const SYMBOL_VARIABLE: u64 = 13;
+const COMPLETION_FUNCTION: u64 = 3;
+const COMPLETION_VARIABLE: u64 = 6;
+const COMPLETION_KEYWORD: u64 = 14;
+const SYNC_FULL: u64 = 1;
+
+/// An open document and what is known about it.
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+impl Document {
+    fn new(text: String) -> Self {
+        let analysis = Analysis::new(&text);
+        Document { text, analysis }
+    }
+
+    /// Converts a location to an LSP position, which counts lines from 0 and
+    /// characters in UTF-16 code units.
+    fn position(&self, location: &Location) -> Value {
+        let line = location.line().saturating_sub(1);
+        let characters = location.column().saturating_sub(1);
+        let text = self.text.lines().nth(line).unwrap_or_default();
+
+        // the end of the input is a character past the end of the line
+        let past_end = characters.saturating_sub(text.chars().count());
+        let character = text
+            .chars()
+            .take(characters)
+            .map(char::len_utf16)
+            .sum::<usize>()
+            + past_end;
+
+        json!({ "line": line, "character": character })
+    }
+
+    fn location(&self, position: &Value) -> Location {
+        let line = position["line"].as_u64().unwrap_or(0) as usize;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+
+        let mut units = 0;
+        let mut column = 1;
+        for char in self.text.lines().nth(line).unwrap_or("").chars() {
+            if units >= character {
+                break;
+            }
+            units += char.len_utf16();
+            column += 1;
+        }
+
+        Location::new(line + 1, column)
+    }
+
+    fn range(&self, range: &TextRange) -> Value {
+        json!({ "start": self.position(&range.start), "end": self.position(&range.end) })
+    }
+}
+
+/// A language server for Monkey, speaking JSON-RPC with `Content-Length`
+/// framed messages as the Language Server Protocol describes.
+///
+/// Documents are synced in full on every change. The server publishes parser
+/// errors, or lints once a document parses, and answers hover, definition,
+/// document symbol and completion requests.
+pub struct LanguageServer<W: Write> {
+    writer: W,
+    linter: Linter,
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+impl<W: Write> LanguageServer<W> {
+    pub fn new(writer: W) -> Self {
+        LanguageServer {
+            writer,
+            linter: Linter::new(),
+            documents: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Serves the messages in `reader` until an `exit` notification or the
+    /// end of the input. Returns whether the client asked to shut down
+    /// first, which is what a clean exit means in LSP.
+    pub fn run(&mut self, reader: &mut impl BufRead) -> Result<bool, LspError> {
+        loop {
+            let message = match read_message(reader) {
+                Ok(Some(message)) => message,
+                Ok(None) => return Ok(self.shutdown),
+                Err(err) => {
+                    self.respond_error(Value::Null, PARSE_ERROR, &err.to_string())?;
+                    continue;
+                }
+            };
+
+            if !self.handle(&message)? {
+                return Ok(self.shutdown);
+            }
+        }
+    }
+
+    /// Handles one message. Returns false once the server should exit.
+    pub fn handle(&mut self, message: &Value) -> Result<bool, LspError> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+
+        let Some(id) = message.get("id").cloned() else {
+            return self.notify(method, params);
+        };
+
+        if self.shutdown {
+            self.respond_error(id, INVALID_REQUEST, "the server is shutting down")?;
+            return Ok(true);
+        }
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": SYNC_FULL,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "documentSymbolProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "interpreter", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/documentSymbol" => self.symbols(params),
+            "textDocument/completion" => self.completion(params),
+            method => {
+                self.respond_error(id, METHOD_NOT_FOUND, &format!("unknown method {}", method))?;
+                return Ok(true);
+            }
+        };
+
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }))?;
+        Ok(true)
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Result<bool, LspError> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        match method {
+            "exit" => return Ok(false),
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.open(uri, text.to_string())?;
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                let text = changes.and_then(|changes| changes.last()?["text"].as_str());
+                if let Some(text) = text {
+                    self.open(uri, text.to_string())?;
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                self.publish(uri, vec![])?;
+            }
+            // `initialized`, `$/` notifications and the like need no answer
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    fn open(&mut self, uri: &str, text: String) -> Result<(), LspError> {
+        let document = Document::new(text);
+        let diagnostics = self.diagnostics(&document);
+        self.documents.insert(uri.to_string(), document);
+        self.publish(uri, diagnostics)
+    }
+
+    fn diagnostics(&self, document: &Document) -> Vec<Value> {
+        let diagnostic = |start: &Location, end: &Location, severity, message: String| {
+            json!({
+                "range": document.range(&TextRange::new(start.clone(), end.clone())),
+                "severity": severity,
+                "source": "monkey",
+                "message": message,
+            })
+        };
+
+        match self.linter.check(&document.text) {
+            Err(errors) => errors
+                .iter()
+                .map(|error| {
+                    let end = document.analysis.token_end(error.location());
+                    diagnostic(
+                        error.location(),
+                        &end,
+                        SEVERITY_ERROR,
+                        error.msg().to_string(),
+                    )
+                })
+                .collect(),
+            Ok(warnings) => warnings
+                .iter()
+                .map(|warning| {
+                    let end = document.analysis.token_end(&warning.span.end);
+                    let mut diagnostic = diagnostic(
+                        &warning.span.start,
+                        &end,
+                        SEVERITY_WARNING,
+                        format!("{} ({})", warning.msg, warning.code.name()),
+                    );
+                    diagnostic["code"] = json!(warning.code.code());
+                    diagnostic
+                })
+                .collect(),
+        }
+    }
+
+    fn publish(&mut self, uri: &str, diagnostics: Vec<Value>) -> Result<(), LspError> {
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }))
+    }
+
+    /// The document a request is about, and the location it points at.
+    fn target(&self, params: &Value) -> Option<(&Document, Location)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let document = self.documents.get(uri)?;
+        let location = document.location(&params["position"]);
+
+        Some((document, location))
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((document, location)) = self.target(params) else {
+            return Value::Null;
+        };
+        let Some(definition) = document.analysis.definition_at(&location) else {
+            return Value::Null;
+        };
+
+        let kind = match definition.kind {
+            DefinitionKind::Variable => "variable",
+            DefinitionKind::Function => "function",
+            DefinitionKind::Parameter => "parameter",
+        };
+
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!(
+                    "```monkey\n{}\n```\n{} defined on line {}",
+                    definition.detail,
+                    kind,
+                    definition.range.start.line()
+                ),
+            },
+            "range": document.range(&definition.range),
+        })
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let uri = &params["textDocument"]["uri"];
+
+        match self.target(params) {
+            Some((document, location)) => match document.analysis.definition_at(&location) {
+                Some(definition) => {
+                    json!({ "uri": uri, "range": document.range(&definition.range) })
+                }
+                None => Value::Null,
+            },
+            None => Value::Null,
+        }
+    }
+
+    fn symbols(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some(document) = self.documents.get(uri) else {
+            return Value::Null;
+        };
+
+        document
+            .analysis
+            .symbols()
+            .map(|definition| {
+                json!({
+                    "name": definition.name,
+                    "detail": definition.detail,
+                    "kind": match definition.kind {
+                        DefinitionKind::Function => SYMBOL_FUNCTION,
+                        _ => SYMBOL_VARIABLE,
+                    },
+                    "range": document.range(&definition.declaration),
+                    "selectionRange": document.range(&definition.range),
+                })
+            })
+            .collect()
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let Some((document, location)) = self.target(params) else {
+            return Value::Null;
+        };
+
+        let names = document
+            .analysis
+            .visible_at(&location)
+            .into_iter()
+            .map(|definition| {
+                json!({
+                    "label": definition.name,
+                    "detail": definition.detail,
+                    "kind": match definition.kind {
+                        DefinitionKind::Function => COMPLETION_FUNCTION,
+                        _ => COMPLETION_VARIABLE,
+                    },
+                })
+            });
+        let keywords = KEYWORDS
+            .iter()
+            .map(|keyword| json!({ "label": keyword, "kind": COMPLETION_KEYWORD }));
+
+        names.chain(keywords).collect()
+    }
+
+    fn respond_error(&mut self, id: Value, code: i64, message: &str) -> Result<(), LspError> {
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }))
+    }
+
+    fn send(&mut self, message: Value) -> Result<(), LspError> {
+        write_message(&mut self.writer, &message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use serde_json::{json, Value};
+
+    use crate::lsp::transport::{read_message, write_message};
+
+    use super::LanguageServer;
+
+    const URI: &str = "file:///main.mk";
+
+    /// Feeds `messages` to a server and returns whether it exited cleanly,
+    /// with everything it sent back.
+    fn serve(messages: Vec<Value>) -> (bool, Vec<Value>) {
+        let mut input = vec![];
+        for message in messages {
+            write_message(&mut input, &message).unwrap();
+        }
+
+        let mut output = vec![];
+        let clean = LanguageServer::new(&mut output)
+            .run(&mut Cursor::new(input))
+            .unwrap();
+
+        let mut output = Cursor::new(output);
+        let responses = std::iter::from_fn(|| read_message(&mut output).unwrap()).collect();
+
+        (clean, responses)
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn notification(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "method": method, "params": params })
+    }
+
+    fn open(text: &str) -> Value {
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": URI, "languageId": "monkey", "version": 1, "text": text } }),
+        )
+    }
+
+    fn at(line: u64, character: u64) -> Value {
+        json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+    }
+
+    fn range(start: (u64, u64), end: (u64, u64)) -> Value {
+        json!({
+            "start": { "line": start.0, "character": start.1 },
+            "end": { "line": end.0, "character": end.1 },
+        })
+    }
+
+    #[test]
+    fn test_session() {
+        let (clean, responses) = serve(vec![
+            request(1, "initialize", json!({ "capabilities": {} })),
+            notification("initialized", json!({})),
+            open("let größe = 2;\nlet double = fn(x) { x * größe };\ndouble(größe)"),
+            request(2, "textDocument/definition", at(2, 8)),
+            request(3, "textDocument/hover", at(1, 26)),
+            request(
+                4,
+                "textDocument/documentSymbol",
+                json!({ "textDocument": { "uri": URI } }),
+            ),
+            request(5, "shutdown", Value::Null),
+            request(6, "textDocument/hover", at(0, 0)),
+            notification("exit", Value::Null),
+        ]);
+
+        assert!(clean);
+        assert_eq!(responses.len(), 7);
+        assert_eq!(
+            responses[0]["result"]["capabilities"]["hoverProvider"],
+            true
+        );
+        assert_eq!(
+            responses[1]["params"],
+            json!({ "uri": URI, "diagnostics": [] })
+        );
+        assert_eq!(
+            responses[2],
+            json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "result": { "uri": URI, "range": range((0, 4), (0, 9)) },
+            })
+        );
+        assert_eq!(
+            responses[3]["result"]["contents"]["value"],
+            "```monkey\nlet größe\n```\nvariable defined on line 1"
+        );
+        assert_eq!(
+            responses[4]["result"],
+            json!([
+                {
+                    "name": "größe",
+                    "detail": "let größe",
+                    "kind": 13,
+                    "range": range((0, 0), (0, 14)),
+                    "selectionRange": range((0, 4), (0, 9)),
+                },
+                {
+                    "name": "double",
+                    "detail": "let double = fn(x)",
+                    "kind": 12,
+                    "range": range((1, 0), (1, 33)),
+                    "selectionRange": range((1, 4), (1, 10)),
+                },
+            ])
+        );
+        assert_eq!(responses[5]["result"], Value::Null);
+        assert_eq!(responses[6]["error"]["code"], -32600);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let (_, responses) = serve(vec![
+            open("let x = 1 +;"),
+            notification(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": URI, "version": 2 },
+                    "contentChanges": [{ "text": "let unused = 1;\n2" }],
+                }),
+            ),
+        ]);
+
+        assert_eq!(
+            responses[0]["params"]["diagnostics"],
+            json!([{
+                "range": range((0, 11), (0, 12)),
+                "severity": 1,
+                "source": "monkey",
+                "message": "Expected prefix expression, got Semicolon",
+            }])
+        );
+        assert_eq!(
+            responses[1]["params"]["diagnostics"],
+            json!([{
+                "range": range((0, 0), (0, 15)),
+                "severity": 2,
+                "source": "monkey",
+                "message": "unused is never used (unused-variable)",
+                "code": "W001",
+            }])
+        );
+    }
+
+    #[test]
+    fn test_completion() {
+        let (_, responses) = serve(vec![
+            open("let one = 1;\nlet f = fn(two) {\n  \n};"),
+            request(1, "textDocument/completion", at(2, 2)),
+        ]);
+
+        let labels: Vec<&str> = responses[1]["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(
+            labels,
+            vec!["two", "f", "one", "let", "fn", "if", "else", "return", "true", "false", "null"]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut input = b"Content-Length: 5\r\n\r\n{oops".to_vec();
+        write_message(&mut input, &request(1, "workspace/symbol", json!({}))).unwrap();
+
+        let mut output = vec![];
+        let clean = LanguageServer::new(&mut output)
+            .run(&mut Cursor::new(input))
+            .unwrap();
+
+        let mut output = Cursor::new(output);
+        let responses: Vec<Value> =
+            std::iter::from_fn(|| read_message(&mut output).unwrap()).collect();
+
+        assert!(!clean);
+        assert_eq!(responses[0]["id"], Value::Null);
+        assert_eq!(responses[0]["error"]["code"], -32700);
+        assert_eq!(
+            responses[1]["error"],
+            json!({ "code": -32601, "message": "unknown method workspace/symbol" })
+        );
+    }
+}
@@ -0,0 +1,4 @@
+pub mod analysis;
+#[allow(clippy::module_inception)]
+pub mod lsp;
+pub mod transport;
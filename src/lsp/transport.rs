@@ -0,0 +1,125 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, Write},
+};
+
+use serde_json::Value;
+
+#[derive(Debug, PartialEq)]
+pub struct LspError {
+    msg: String,
+}
+
+impl LspError {
+    pub fn new(msg: impl Into<String>) -> Self {
+        LspError { msg: msg.into() }
+    }
+}
+
+impl Display for LspError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[LspError] {}", self.msg)
+    }
+}
+
+/// Reads one `Content-Length` framed message. Returns `None` at the end of
+/// the input.
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>, LspError> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        let read = reader
+            .read_line(&mut header)
+            .map_err(|err| LspError::new(format!("failed to read header: {}", err)))?;
+        if read == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(LspError::new("unexpected end of input in headers")),
+            };
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        // other headers, like Content-Type, do not change anything
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>().map_err(|_| {
+                    LspError::new(format!("invalid Content-Length {}", value.trim()))
+                })?);
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| LspError::new("missing Content-Length header"))?;
+    let mut content = vec![0; length];
+    reader
+        .read_exact(&mut content)
+        .map_err(|err| LspError::new(format!("failed to read content: {}", err)))?;
+
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| LspError::new(format!("invalid JSON: {}", err)))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> Result<(), LspError> {
+    let content = message.to_string();
+
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )
+    .and_then(|_| writer.flush())
+    .map_err(|err| LspError::new(format!("failed to write message: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use serde_json::json;
+
+    use super::{read_message, write_message, LspError};
+
+    #[test]
+    fn test_round_trip() {
+        let mut output = vec![];
+        write_message(&mut output, &json!({ "id": 1, "text": "héllo" })).unwrap();
+        write_message(&mut output, &json!({ "method": "exit" })).unwrap();
+
+        let mut input = Cursor::new(output);
+        assert_eq!(
+            read_message(&mut input),
+            Ok(Some(json!({ "id": 1, "text": "héllo" })))
+        );
+        assert_eq!(
+            read_message(&mut input),
+            Ok(Some(json!({ "method": "exit" })))
+        );
+        assert_eq!(read_message(&mut input), Ok(None));
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let tests = vec![
+            ("Content-Type: x\r\n\r\n{}", "missing Content-Length header"),
+            ("Content-Length: x\r\n\r\n", "invalid Content-Length x"),
+            (
+                "Content-Length: 2\r\n",
+                "unexpected end of input in headers",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                read_message(&mut Cursor::new(input)),
+                Err(LspError::new(expected))
+            );
+        }
+    }
+}
@@ -1,13 +1,16 @@
-use repl::Repl;
+use std::process::ExitCode;
 
-mod evaluator;
-mod lexer;
-mod parser;
-mod repl;
+use interpreter::{cli::Command, profiler::allocator::CountingAllocator};
 
-fn main() {
-    println!("Monkey repl! enter empty string to exit");
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
-    let repl = Repl::new(std::io::stdin());
-    repl.start();
+fn main() -> ExitCode {
+    match Command::parse(std::env::args().skip(1)) {
+        Ok(command) => command.execute(),
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,346 @@
+use crate::parser::ast::{
+    expression::Expression,
+    folder::{self, Folder},
+    operator::{InfixOperator, PrefixOperator},
+    program::Program,
+    statement::Statement,
+};
+
+/// Rewrites a `Program` into an equivalent, cheaper one before evaluation.
+///
+/// Every rewrite must keep the observable behavior of the program, including
+/// runtime errors: anything that could fail (`1 / 0`, `5 + true`, unknown
+/// identifiers) is left in place for the evaluator to report.
+#[derive(Default)]
+pub struct Optimizer {}
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Optimizer {}
+    }
+
+    pub fn optimize(&mut self, program: Program) -> Program {
+        self.fold_program(program)
+    }
+
+    fn fold_prefix(&self, operator: &PrefixOperator, rhs: &Expression) -> Option<Expression> {
+        match (operator, rhs) {
+            (PrefixOperator::Not, Expression::Bool(boolean)) => Some(Expression::Bool(!boolean)),
+            (PrefixOperator::Not, Expression::Int(integer)) => {
+                Some(Expression::Bool(*integer == 0))
+            }
+            (PrefixOperator::Negative, Expression::Int(integer)) => {
+                integer.checked_neg().map(Expression::Int)
+            }
+            _ => None,
+        }
+    }
+
+    fn fold_infix(
+        &self,
+        operator: &InfixOperator,
+        lhs: &Expression,
+        rhs: &Expression,
+    ) -> Option<Expression> {
+        match (operator, lhs, rhs) {
+            (InfixOperator::Add, Expression::Int(lhs), Expression::Int(rhs)) => {
+                lhs.checked_add(*rhs).map(Expression::Int)
+            }
+            (InfixOperator::Sub, Expression::Int(lhs), Expression::Int(rhs)) => {
+                lhs.checked_sub(*rhs).map(Expression::Int)
+            }
+            (InfixOperator::Mult, Expression::Int(lhs), Expression::Int(rhs)) => {
+                lhs.checked_mul(*rhs).map(Expression::Int)
+            }
+            // division and modulo by zero must still fail at runtime
+            (InfixOperator::Div, Expression::Int(lhs), Expression::Int(rhs)) => {
+                lhs.checked_div(*rhs).map(Expression::Int)
+            }
+            (InfixOperator::Modulo, Expression::Int(lhs), Expression::Int(rhs)) => {
+                lhs.checked_rem(*rhs).map(Expression::Int)
+            }
+            (InfixOperator::Equal, Expression::Int(lhs), Expression::Int(rhs)) => {
+                Some(Expression::Bool(lhs == rhs))
+            }
+            (InfixOperator::Equal, Expression::Bool(lhs), Expression::Bool(rhs)) => {
+                Some(Expression::Bool(lhs == rhs))
+            }
+            (InfixOperator::NotEqual, Expression::Int(lhs), Expression::Int(rhs)) => {
+                Some(Expression::Bool(lhs != rhs))
+            }
+            (InfixOperator::NotEqual, Expression::Bool(lhs), Expression::Bool(rhs)) => {
+                Some(Expression::Bool(lhs != rhs))
+            }
+            (InfixOperator::GreaterThan, Expression::Int(lhs), Expression::Int(rhs)) => {
+                Some(Expression::Bool(lhs > rhs))
+            }
+            (InfixOperator::LessThan, Expression::Int(lhs), Expression::Int(rhs)) => {
+                Some(Expression::Bool(lhs < rhs))
+            }
+            _ => None,
+        }
+    }
+
+    /// Truthiness of a condition that is known before evaluation, following
+    /// the same rules as the evaluator.
+    fn constant_truthiness(&self, condition: &Expression) -> Option<bool> {
+        match condition {
+            Expression::Bool(boolean) => Some(*boolean),
+            Expression::Int(integer) => Some(*integer != 0),
+            Expression::Null => Some(false),
+            _ => None,
+        }
+    }
+
+    fn is_pure_statement(&self, statement: &Statement) -> bool {
+        match statement {
+            Statement::Expression(expression) => matches!(
+                expression,
+                Expression::Int(_)
+                    | Expression::Bool(_)
+                    | Expression::Null
+                    | Expression::Function { .. }
+            ),
+            Statement::Block(statements) => statements.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+impl Folder for Optimizer {
+    fn fold_statements(&mut self, statements: Vec<Statement>) -> Vec<Statement> {
+        let mut optimized: Vec<Statement> = vec![];
+
+        for statement in statements {
+            let statement = self.fold_statement(statement);
+            let is_return = matches!(statement, Statement::Return { .. });
+
+            optimized.push(statement);
+
+            if is_return {
+                break;
+            }
+        }
+
+        // only the last statement of a list produces its value, so pure
+        // expression statements before it can never be observed
+        let last = optimized.len().saturating_sub(1);
+        optimized
+            .into_iter()
+            .enumerate()
+            .filter(|(i, statement)| *i == last || !self.is_pure_statement(statement))
+            .map(|(_, statement)| statement)
+            .collect()
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        match folder::walk_statement(self, statement) {
+            Statement::Expression(Expression::If {
+                condition,
+                consequence,
+                alternative,
+            }) => match self.constant_truthiness(&condition) {
+                Some(true) => Statement::Block(consequence),
+                Some(false) => Statement::Block(alternative.unwrap_or_default()),
+                None => Statement::Expression(Expression::If {
+                    condition,
+                    consequence,
+                    alternative,
+                }),
+            },
+            statement => statement,
+        }
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        match folder::walk_expression(self, expression) {
+            Expression::Prefix { rhs, operator } => self
+                .fold_prefix(&operator, &rhs)
+                .unwrap_or(Expression::Prefix { rhs, operator }),
+            Expression::Infix { lhs, operator, rhs } => self
+                .fold_infix(&operator, &lhs, &rhs)
+                .unwrap_or(Expression::Infix { lhs, operator, rhs }),
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                // a taken branch can only replace the whole `if` when it is a
+                // single expression; otherwise the enclosing statement turns
+                // it into a block
+                let branch = match self.constant_truthiness(&condition) {
+                    Some(true) => Some(&consequence),
+                    Some(false) => alternative.as_ref(),
+                    None => {
+                        return Expression::If {
+                            condition,
+                            consequence,
+                            alternative,
+                        }
+                    }
+                };
+
+                match branch.map(|branch| branch.as_slice()) {
+                    None | Some([]) => Expression::Null,
+                    Some([Statement::Expression(expression)]) => expression.clone(),
+                    _ => Expression::If {
+                        condition,
+                        consequence,
+                        alternative,
+                    },
+                }
+            }
+            expression => expression,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use indoc::indoc;
+
+    use crate::{
+        evaluator::{environment::Environment, evaluator::Evaluator},
+        lexer::lexer::Lexer,
+        parser::{ast::program::Program, parser::Parser},
+    };
+
+    use super::Optimizer;
+
+    fn parse(input: &str) -> Program {
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+        assert_eq!(parser.errors.len(), 0);
+        program
+    }
+
+    fn optimize(input: &str) -> String {
+        Optimizer::new()
+            .optimize(parse(input))
+            .to_string()
+            .trim()
+            .to_string()
+    }
+
+    fn evaluate(program: &Program) -> String {
+        let environment = Environment::new();
+        match Evaluator::new().eval(program, Rc::clone(&environment)) {
+            Ok(object) => object.to_string(),
+            Err(err) => format!("error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let tests = vec![
+            ("1 + 2 * 3", "7"),
+            ("(5 + 10 * 2 + 15 / 3) * 2 + -10", "50"),
+            ("10 % 3", "1"),
+            ("1 < 2 == true", "true"),
+            ("!(1 == 2)", "true"),
+            ("!0", "true"),
+            ("--5", "5"),
+            ("x + 1 * 2", "(x + 2)"),
+            ("let a = 2 * 21;", "let a = 42"),
+        ];
+
+        for test in tests {
+            assert_eq!(optimize(test.0), test.1, "optimizing {}", test.0);
+        }
+    }
+
+    #[test]
+    fn test_folding_preserves_errors() {
+        let tests = vec![
+            ("1 / 0", "(1 / 0)"),
+            ("1 % 0", "(1 % 0)"),
+            ("5 + true", "(5 + true)"),
+            ("-true", "(-true)"),
+            ("!null", "(!null)"),
+            ("9223372036854775807 + 1", "(9223372036854775807 + 1)"),
+        ];
+
+        for test in tests {
+            assert_eq!(optimize(test.0), test.1, "optimizing {}", test.0);
+        }
+    }
+
+    #[test]
+    fn test_dead_branch_elimination() {
+        let tests = vec![
+            ("if (true) { 1 } else { 2 }", "1"),
+            ("if (false) { 1 } else { 2 }", "2"),
+            ("if (1 > 2) { 1 }", "null"),
+            ("let a = if (0) { 1 } else { x };", "let a = x"),
+            ("if (null) { 1 } else { let a = 2; a }", "let a = 2\na"),
+            ("if (x) { 1 } else { 2 }", "if x { 1 } else { 2 }"),
+        ];
+
+        for test in tests {
+            assert_eq!(optimize(test.0), test.1, "optimizing {}", test.0);
+        }
+    }
+
+    #[test]
+    fn test_drops_pure_expression_statements() {
+        assert_eq!(optimize("1; true; fn(x) { x }; x; 2"), "x\n2");
+        assert_eq!(optimize("let a = 1; 5; null"), "let a = 1\nnull");
+        assert_eq!(optimize("fn() { 1; 2 }"), "fn() { 2 }");
+    }
+
+    #[test]
+    fn test_removes_statements_after_return() {
+        assert_eq!(optimize("return 1; x; 2"), "return 1");
+        assert_eq!(
+            optimize("fn() { let a = 1; return a; a + 1; }"),
+            "fn() { let a = 1 return a }"
+        );
+        assert_eq!(
+            optimize("if (x) { return 1; 2 } else { 3 }"),
+            "if x { return 1 } else { 3 }"
+        );
+    }
+
+    #[test]
+    fn test_optimized_program_evaluates_the_same() {
+        let inputs = vec![
+            indoc! {"
+                let factorial = fn(x) {
+                    if (x == 0) { return 1; 99; }
+                    x * factorial(x - 1)
+                };
+                factorial(2 + 3);
+            "},
+            indoc! {"
+                let f = fn(x) {
+                    if (true) {
+                        let y = x * (2 + 2);
+                        return y;
+                    }
+                    0
+                };
+                f(3) + if (false) { 1 } else { 2 * 5 };
+            "},
+            "if (1 < 2) { return 10; } 20",
+            "1 / 0",
+            "5; 1 / 0; 7",
+            "1; 2; 3",
+            "-true",
+            "unknown; 5",
+            "if (false) { 1 }",
+        ];
+
+        for input in inputs {
+            let program = parse(input);
+            let optimized = Optimizer::new().optimize(parse(input));
+            assert_eq!(
+                evaluate(&optimized),
+                evaluate(&program),
+                "evaluating {}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::fmt::Display;
+
+/// A type written in the source, like `int` or `fn(int, bool) -> int`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum TypeAnnotation {
+    Int,
+    Bool,
+    String,
+    Null,
+    Function {
+        parameters: Vec<TypeAnnotation>,
+        result: Box<TypeAnnotation>,
+    },
+}
+
+impl TypeAnnotation {
+    pub fn function(parameters: Vec<TypeAnnotation>, result: TypeAnnotation) -> Self {
+        TypeAnnotation::Function {
+            parameters,
+            result: Box::new(result),
+        }
+    }
+}
+
+impl Display for TypeAnnotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeAnnotation::Int => write!(f, "int"),
+            TypeAnnotation::Bool => write!(f, "bool"),
+            TypeAnnotation::String => write!(f, "string"),
+            TypeAnnotation::Null => write!(f, "null"),
+            TypeAnnotation::Function { parameters, result } => write!(
+                f,
+                "fn({}) -> {}",
+                parameters
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                result
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TypeAnnotation;
+
+    #[test]
+    fn test_display() {
+        let annotation = TypeAnnotation::function(
+            vec![
+                TypeAnnotation::Int,
+                TypeAnnotation::function(vec![], TypeAnnotation::Null),
+            ],
+            TypeAnnotation::Bool,
+        );
+
+        assert_eq!(annotation.to_string(), "fn(int, fn() -> null) -> bool");
+    }
+}
@@ -1,23 +1,77 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
+
+use crate::parser::literal::{escape_string, quote_string};
 
 use super::{
+    annotation::TypeAnnotation,
     operator::{InfixOperator, PrefixOperator},
     statement::Statement,
 };
 
+/// A function parameter, with the type it was annotated with if any.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub annotation: Option<TypeAnnotation>,
+}
+
+impl Parameter {
+    pub fn new(name: impl Into<String>, annotation: Option<TypeAnnotation>) -> Self {
+        Parameter {
+            name: name.into(),
+            annotation,
+        }
+    }
+}
+
+impl From<&str> for Parameter {
+    fn from(name: &str) -> Self {
+        Parameter::new(name, None)
+    }
+}
+
+impl From<String> for Parameter {
+    fn from(name: String) -> Self {
+        Parameter::new(name, None)
+    }
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.annotation {
+            Some(annotation) => write!(f, "{}: {}", self.name, annotation),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// A piece of an interpolated string.
+#[derive(PartialEq, Debug, Clone)]
+pub enum TemplatePart {
+    Text(String),
+    /// An interpolation `${...}`.
+    Expression(Expression),
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum Expression {
     Int(i64),
     Bool(bool),
+    String(String),
+    /// A string with interpolations, like `"Hello ${name}"`.
+    Template(Vec<TemplatePart>),
     Identifier(String),
     If {
         condition: Box<Expression>,
         consequence: Vec<Statement>,
         alternative: Option<Vec<Statement>>,
     },
+    /// Parameters and body are shared with every closure created from this
+    /// literal, so evaluating it never copies the body.
     Function {
-        parameters: Vec<String>,
-        body: Vec<Statement>,
+        parameters: Arc<[Parameter]>,
+        result: Option<TypeAnnotation>,
+        body: Arc<[Statement]>,
     },
     Call {
         function: Box<Expression>,
@@ -33,6 +87,16 @@ pub enum Expression {
         rhs: Box<Expression>,
     },
     Null,
+    /// An export of a module, `module.name`.
+    Member {
+        object: Box<Expression>,
+        name: String,
+    },
+    Array(Vec<Expression>),
+    Index {
+        left: Box<Expression>,
+        index: Box<Expression>,
+    },
 }
 
 impl Expression {
@@ -40,10 +104,19 @@ impl Expression {
         Expression::Identifier(identifier.into())
     }
 
-    pub fn function(parameters: Vec<impl Into<String>>, body: Vec<Statement>) -> Self {
+    pub fn function(parameters: Vec<impl Into<Parameter>>, body: Vec<Statement>) -> Self {
+        Expression::annotated_function(parameters, None, body)
+    }
+
+    pub fn annotated_function(
+        parameters: Vec<impl Into<Parameter>>,
+        result: Option<TypeAnnotation>,
+        body: Vec<Statement>,
+    ) -> Self {
         Expression::Function {
             parameters: parameters.into_iter().map(|p| p.into()).collect(),
-            body,
+            result,
+            body: body.into(),
         }
     }
 
@@ -80,6 +153,20 @@ impl Expression {
             arguments,
         }
     }
+
+    pub fn member(object: Expression, name: impl Into<String>) -> Self {
+        Expression::Member {
+            object: Box::new(object),
+            name: name.into(),
+        }
+    }
+
+    pub fn index(left: Expression, index: Expression) -> Self {
+        Expression::Index {
+            left: Box::new(left),
+            index: Box::new(index),
+        }
+    }
 }
 
 impl Display for Expression {
@@ -89,6 +176,17 @@ impl Display for Expression {
             Expression::Prefix { operator, rhs } => write!(f, "({}{})", operator, rhs),
             Expression::Bool(b) => write!(f, "{}", b),
             Expression::Int(i) => write!(f, "{}", i),
+            Expression::String(string) => write!(f, "{}", quote_string(string)),
+            Expression::Template(parts) => {
+                write!(f, "\"")?;
+                for part in parts {
+                    match part {
+                        TemplatePart::Text(text) => write!(f, "{}", escape_string(text))?,
+                        TemplatePart::Expression(expression) => write!(f, "${{{}}}", expression)?,
+                    }
+                }
+                write!(f, "\"")
+            }
             Expression::Identifier(identifier) => write!(f, "{}", identifier),
             Expression::If {
                 condition,
@@ -113,14 +211,22 @@ impl Display for Expression {
                     })
                     .unwrap_or_else(|| "".to_string())
             ),
-            Expression::Function { parameters, body } => write!(
+            Expression::Function {
+                parameters,
+                result,
+                body,
+            } => write!(
                 f,
-                "fn({}) {{ {} }}",
+                "fn({}){} {{ {} }}",
                 parameters
                     .iter()
-                    .map(|p| format!("{}", p))
+                    .map(|p| p.to_string())
                     .collect::<Vec<String>>()
                     .join(", "),
+                result
+                    .as_ref()
+                    .map(|result| format!(" -> {}", result))
+                    .unwrap_or_default(),
                 body.iter()
                     .map(|s| format!("{}", s))
                     .collect::<Vec<String>>()
@@ -140,6 +246,17 @@ impl Display for Expression {
                     .join(", ")
             ),
             Expression::Null => write!(f, "null"),
+            Expression::Member { object, name } => write!(f, "{}.{}", object, name),
+            Expression::Array(elements) => write!(
+                f,
+                "[{}]",
+                elements
+                    .iter()
+                    .map(|e| format!("{}", e))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Expression::Index { left, index } => write!(f, "{}[{}]", left, index),
         }
     }
 }
@@ -190,13 +307,24 @@ mod tests {
 
     #[test]
     fn test_function() {
-        let function = Expression::Function {
-            parameters: vec!["foo".to_string()],
-            body: vec![Statement::Expression(Expression::Int(1))],
-        };
+        let function =
+            Expression::function(vec!["foo"], vec![Statement::Expression(Expression::Int(1))]);
         assert_eq!(format!("{}", function), "fn(foo) { 1 }");
     }
 
+    #[test]
+    fn test_annotated_function() {
+        let function = Expression::annotated_function(
+            vec![
+                Parameter::new("a", Some(TypeAnnotation::Int)),
+                Parameter::new("b", None),
+            ],
+            Some(TypeAnnotation::Bool),
+            vec![Statement::Expression(Expression::Bool(true))],
+        );
+        assert_eq!(format!("{}", function), "fn(a: int, b) -> bool { true }");
+    }
+
     #[test]
     fn test_call() {
         let call = Expression::Call {
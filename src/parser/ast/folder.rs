@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use super::{
+    expression::{Expression, TemplatePart},
+    program::Program,
+    statement::Statement,
+};
+
+/// Transforming traversal of the AST, taking nodes by value and returning
+/// their replacements.
+///
+/// Every method defaults to rebuilding the node from its folded children, so
+/// a pass only overrides the nodes it rewrites and calls the matching
+/// `walk_*` function to fold the children first.
+pub trait Folder {
+    fn fold_program(&mut self, program: Program) -> Program {
+        walk_program(self, program)
+    }
+
+    /// A list of statements: the program, a block, or a function body.
+    fn fold_statements(&mut self, statements: Vec<Statement>) -> Vec<Statement> {
+        walk_statements(self, statements)
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        walk_statement(self, statement)
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        walk_expression(self, expression)
+    }
+}
+
+pub fn walk_program<F: Folder + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        statements: folder.fold_statements(program.statements),
+    }
+}
+
+pub fn walk_statements<F: Folder + ?Sized>(
+    folder: &mut F,
+    statements: Vec<Statement>,
+) -> Vec<Statement> {
+    statements
+        .into_iter()
+        .map(|statement| folder.fold_statement(statement))
+        .collect()
+}
+
+pub fn walk_statement<F: Folder + ?Sized>(folder: &mut F, statement: Statement) -> Statement {
+    match statement {
+        Statement::Let {
+            name,
+            annotation,
+            value,
+        } => Statement::Let {
+            name,
+            annotation,
+            value: folder.fold_expression(value),
+        },
+        Statement::Return { value } => Statement::Return {
+            value: folder.fold_expression(value),
+        },
+        Statement::Expression(expression) => {
+            Statement::Expression(folder.fold_expression(expression))
+        }
+        Statement::Block(statements) => Statement::Block(folder.fold_statements(statements)),
+        Statement::Import { .. } | Statement::Export(_) => statement,
+    }
+}
+
+pub fn walk_expression<F: Folder + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    match expression {
+        Expression::Int(_)
+        | Expression::Bool(_)
+        | Expression::String(_)
+        | Expression::Identifier(_)
+        | Expression::Null => expression,
+        Expression::If {
+            condition,
+            consequence,
+            alternative,
+        } => Expression::r#if(
+            folder.fold_expression(*condition),
+            folder.fold_statements(consequence),
+            alternative.map(|alternative| folder.fold_statements(alternative)),
+        ),
+        // the body is shared with closures, so folding it makes a new copy
+        Expression::Function {
+            parameters,
+            result,
+            body,
+        } => Expression::Function {
+            parameters,
+            result,
+            body: Arc::from(folder.fold_statements(body.to_vec())),
+        },
+        Expression::Call {
+            function,
+            arguments,
+        } => Expression::call(
+            folder.fold_expression(*function),
+            arguments
+                .into_iter()
+                .map(|argument| folder.fold_expression(argument))
+                .collect(),
+        ),
+        Expression::Prefix { rhs, operator } => {
+            Expression::prefix(folder.fold_expression(*rhs), operator)
+        }
+        Expression::Infix { lhs, operator, rhs } => Expression::infix(
+            folder.fold_expression(*lhs),
+            folder.fold_expression(*rhs),
+            operator,
+        ),
+        Expression::Member { object, name } => {
+            Expression::member(folder.fold_expression(*object), name)
+        }
+        Expression::Array(elements) => Expression::Array(
+            elements
+                .into_iter()
+                .map(|element| folder.fold_expression(element))
+                .collect(),
+        ),
+        Expression::Template(parts) => Expression::Template(
+            parts
+                .into_iter()
+                .map(|part| match part {
+                    TemplatePart::Expression(expression) => {
+                        TemplatePart::Expression(folder.fold_expression(expression))
+                    }
+                    text => text,
+                })
+                .collect(),
+        ),
+        Expression::Index { left, index } => Expression::index(
+            folder.fold_expression(*left),
+            folder.fold_expression(*index),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::lexer::Lexer,
+        parser::{ast::expression::Expression, parser::Parser},
+    };
+
+    use super::{walk_expression, Folder};
+
+    struct Renamer;
+
+    impl Folder for Renamer {
+        fn fold_expression(&mut self, expression: Expression) -> Expression {
+            match expression {
+                Expression::Identifier(identifier) => {
+                    Expression::identifier(identifier.to_uppercase())
+                }
+                expression => walk_expression(self, expression),
+            }
+        }
+    }
+
+    #[test]
+    fn test_folder_rebuilds_every_node() {
+        let mut parser = Parser::new(Lexer::new(
+            "let f = fn(a) { if (a) { return -b; } else { c(d, e + g) } }; f(h);",
+        ));
+        let program = Renamer.fold_program(parser.parse_program());
+
+        assert_eq!(
+            program.to_string(),
+            "let f = fn(a) { if A { return (-B) } else { C(D, (E + G)) } }\nF(H)\n"
+        );
+    }
+}
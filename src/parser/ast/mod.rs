@@ -1,5 +1,8 @@
+pub mod annotation;
 pub mod expression;
+pub mod folder;
 pub mod node;
 pub mod operator;
 pub mod program;
 pub mod statement;
+pub mod visitor;
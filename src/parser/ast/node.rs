@@ -1,26 +1,30 @@
 use super::{expression::Expression, program::Program, statement::Statement};
 
-#[derive(Debug)]
-pub enum Node {
-    Expression(Expression),
-    Statement(Statement),
-    Program(Program),
+/// A borrowed view of any evaluable piece of the AST.
+///
+/// The evaluator never takes ownership of the tree, so a single parsed
+/// `Program` can be evaluated any number of times.
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Expression(&'a Expression),
+    Statement(&'a Statement),
+    Program(&'a Program),
 }
 
-impl Into<Node> for Program {
-    fn into(self) -> Node {
-        Node::Program(self)
+impl<'a> From<&'a Program> for Node<'a> {
+    fn from(value: &'a Program) -> Self {
+        Node::Program(value)
     }
 }
 
-impl Into<Node> for Expression {
-    fn into(self) -> Node {
-        Node::Expression(self)
+impl<'a> From<&'a Expression> for Node<'a> {
+    fn from(value: &'a Expression) -> Self {
+        Node::Expression(value)
     }
 }
 
-impl Into<Node> for Statement {
-    fn into(self) -> Node {
-        Node::Statement(self)
+impl<'a> From<&'a Statement> for Node<'a> {
+    fn from(value: &'a Statement) -> Self {
+        Node::Statement(value)
     }
 }
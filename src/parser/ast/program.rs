@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use super::statement::Statement;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Program {
     pub statements: Vec<Statement>,
 }
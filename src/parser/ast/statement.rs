@@ -1,19 +1,51 @@
 use std::fmt::Display;
 
-use super::expression::Expression;
+use crate::parser::literal::quote_string;
+
+use super::{annotation::TypeAnnotation, expression::Expression};
+
+/// What an `import` binds in the importing environment.
+#[derive(PartialEq, Debug, Clone)]
+pub enum ImportBinding {
+    /// `import "path" as name;` binds the whole module.
+    Module(String),
+    /// `import { a, b } from "path";` binds the listed exports.
+    Names(Vec<String>),
+}
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Statement {
-    Let { name: String, value: Expression },
-    Return { value: Expression },
+    Let {
+        name: String,
+        annotation: Option<TypeAnnotation>,
+        value: Expression,
+    },
+    Return {
+        value: Expression,
+    },
     Expression(Expression),
     Block(Vec<Statement>),
+    Import {
+        path: String,
+        binding: ImportBinding,
+    },
+    /// Names the top-level bindings other modules can import.
+    Export(Vec<String>),
 }
 
 impl Statement {
     pub fn r#let(name: impl Into<String>, value: Expression) -> Self {
+        Statement::annotated_let(name, None, value)
+    }
+
+    pub fn annotated_let(
+        name: impl Into<String>,
+        annotation: Option<TypeAnnotation>,
+        value: Expression,
+    ) -> Self {
         Statement::Let {
             name: name.into(),
+            annotation,
             value,
         }
     }
@@ -25,12 +57,28 @@ impl Statement {
     pub fn expression(expression: Expression) -> Self {
         Statement::Expression(expression)
     }
+
+    pub fn import(path: impl Into<String>, binding: ImportBinding) -> Self {
+        Statement::Import {
+            path: path.into(),
+            binding,
+        }
+    }
+
+    pub fn export(names: Vec<impl Into<String>>) -> Self {
+        Statement::Export(names.into_iter().map(|name| name.into()).collect())
+    }
 }
 
 impl Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
-            Statement::Let { name, value } => write!(f, "let {} = {}", name, value),
+            Statement::Let {
+                name,
+                annotation: Some(annotation),
+                value,
+            } => write!(f, "let {}: {} = {}", name, annotation, value),
+            Statement::Let { name, value, .. } => write!(f, "let {} = {}", name, value),
             Statement::Return { value } => write!(f, "return {}", value),
             Statement::Expression(expression) => write!(f, "{}", expression),
             Statement::Block(statements) => {
@@ -40,6 +88,20 @@ impl Display for Statement {
                 }
                 write!(f, "{}", result)
             }
+            Statement::Import {
+                path,
+                binding: ImportBinding::Module(name),
+            } => write!(f, "import {} as {}", quote_string(path), name),
+            Statement::Import {
+                path,
+                binding: ImportBinding::Names(names),
+            } => write!(
+                f,
+                "import {{ {} }} from {}",
+                names.join(", "),
+                quote_string(path)
+            ),
+            Statement::Export(names) => write!(f, "export {}", names.join(", ")),
         }
     }
 }
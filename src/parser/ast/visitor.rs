@@ -0,0 +1,162 @@
+use super::{
+    expression::{Expression, TemplatePart},
+    program::Program,
+    statement::Statement,
+};
+
+/// Read-only traversal of the AST.
+///
+/// Every method defaults to walking into the node's children, so an analysis
+/// only overrides the nodes it cares about and calls the matching `walk_*`
+/// function to keep descending.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    /// A list of statements: the program, a block, or a function body.
+    fn visit_statements(&mut self, statements: &[Statement]) {
+        walk_statements(self, statements);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    visitor.visit_statements(&program.statements);
+}
+
+pub fn walk_statements<V: Visitor + ?Sized>(visitor: &mut V, statements: &[Statement]) {
+    for statement in statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Let { value, .. } => visitor.visit_expression(value),
+        Statement::Return { value } => visitor.visit_expression(value),
+        Statement::Expression(expression) => visitor.visit_expression(expression),
+        Statement::Block(statements) => visitor.visit_statements(statements),
+        Statement::Import { .. } | Statement::Export(_) => {}
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Int(_)
+        | Expression::Bool(_)
+        | Expression::String(_)
+        | Expression::Identifier(_)
+        | Expression::Null => {}
+        Expression::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            visitor.visit_expression(condition);
+            visitor.visit_statements(consequence);
+            if let Some(alternative) = alternative {
+                visitor.visit_statements(alternative);
+            }
+        }
+        Expression::Function { body, .. } => visitor.visit_statements(body),
+        Expression::Call {
+            function,
+            arguments,
+        } => {
+            visitor.visit_expression(function);
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        Expression::Prefix { rhs, .. } => visitor.visit_expression(rhs),
+        Expression::Infix { lhs, rhs, .. } => {
+            visitor.visit_expression(lhs);
+            visitor.visit_expression(rhs);
+        }
+        Expression::Member { object, .. } => visitor.visit_expression(object),
+        Expression::Array(elements) => {
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        }
+        Expression::Template(parts) => {
+            for part in parts {
+                if let TemplatePart::Expression(expression) = part {
+                    visitor.visit_expression(expression);
+                }
+            }
+        }
+        Expression::Index { left, index } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        lexer::lexer::Lexer,
+        parser::{
+            ast::{expression::Expression, statement::Statement},
+            parser::Parser,
+        },
+    };
+
+    use super::{walk_expression, walk_statement, Visitor};
+
+    #[derive(Default)]
+    struct Counter {
+        identifiers: Vec<String>,
+        statements: usize,
+        statement_lists: usize,
+    }
+
+    impl Visitor for Counter {
+        fn visit_statements(&mut self, statements: &[Statement]) {
+            self.statement_lists += 1;
+            super::walk_statements(self, statements);
+        }
+
+        fn visit_statement(&mut self, statement: &Statement) {
+            self.statements += 1;
+            walk_statement(self, statement);
+        }
+
+        fn visit_expression(&mut self, expression: &Expression) {
+            if let Expression::Identifier(identifier) = expression {
+                self.identifiers.push(identifier.clone());
+            }
+            walk_expression(self, expression);
+        }
+    }
+
+    #[test]
+    fn test_visitor_reaches_every_node() {
+        let mut parser = Parser::new(Lexer::new(indoc! {"
+            let f = fn(a) { if (a) { return -b; } else { c(d, e + g) } };
+            f(h);
+        "}));
+        let program = parser.parse_program();
+
+        let mut counter = Counter::default();
+        counter.visit_program(&program);
+
+        assert_eq!(
+            counter.identifiers,
+            vec!["a", "b", "c", "d", "e", "g", "f", "h"]
+        );
+        assert_eq!(counter.statements, 5);
+        assert_eq!(counter.statement_lists, 4);
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt::Display;
+
+use crate::lexer::token::TriviaToken;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SyntaxKind {
+    Program,
+    LetStatement,
+    ReturnStatement,
+    ExpressionStatement,
+    ImportStatement,
+    /// The braced names in `import { a, b } from "path"`.
+    ImportList,
+    ExportStatement,
+    Block,
+    Identifier,
+    Integer,
+    String,
+    Boolean,
+    Null,
+    PrefixExpression,
+    InfixExpression,
+    GroupedExpression,
+    IfExpression,
+    ElseClause,
+    FunctionLiteral,
+    ParameterList,
+    /// A type such as `int` or `fn(int) -> bool`, after a `:` or `->`.
+    TypeAnnotation,
+    CallExpression,
+    ArgumentList,
+    MemberExpression,
+    ArrayLiteral,
+    IndexExpression,
+    /// Tokens the parser could not make sense of, or nothing at all where a
+    /// required token or expression was missing.
+    Error,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyntaxElement<'a> {
+    Node(SyntaxNode<'a>),
+    Token(TriviaToken<'a>),
+}
+
+/// A node of the concrete syntax tree.
+///
+/// Unlike the AST, the tree keeps every token of the source, including
+/// punctuation, whitespace and comments, so displaying it reproduces the
+/// input exactly.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxNode<'a> {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement<'a>>,
+}
+
+impl<'a> SyntaxNode<'a> {
+    pub fn new(kind: SyntaxKind, children: Vec<SyntaxElement<'a>>) -> Self {
+        SyntaxNode { kind, children }
+    }
+
+    /// Child nodes, skipping tokens.
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode<'a>> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Direct child tokens, skipping nodes.
+    pub fn tokens(&self) -> impl Iterator<Item = &TriviaToken<'a>> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(_) => None,
+        })
+    }
+
+    /// The first token anywhere under this node.
+    pub fn first_token(&self) -> Option<&TriviaToken<'a>> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(node) => node.first_token(),
+        })
+    }
+
+    /// The last token anywhere under this node.
+    pub fn last_token(&self) -> Option<&TriviaToken<'a>> {
+        self.children.iter().rev().find_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(node) => node.last_token(),
+        })
+    }
+}
+
+impl Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{}", node)?,
+                SyntaxElement::Token(token) => {
+                    for trivia in &token.leading_trivia {
+                        write!(f, "{}", trivia.text)?;
+                    }
+                    write!(f, "{}", token.text)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use crate::{
+    lexer::{location::Location, token::TokenType},
+    parser::{
+        ast::{
+            annotation::TypeAnnotation,
+            expression::{Expression, Parameter},
+            operator::{InfixOperator, PrefixOperator},
+            program::Program,
+            statement::{ImportBinding, Statement},
+        },
+        literal::{parse_integer, parse_string},
+        parser::{Parser, ParserError},
+    },
+};
+
+use super::cst::{SyntaxElement, SyntaxKind, SyntaxNode};
+
+/// Lowers a concrete syntax tree to the AST `Parser` would build from the
+/// same source, dropping trivia and punctuation.
+///
+/// Trees parsed with errors cannot be lowered; the first error node found is
+/// reported instead.
+pub fn lower(program: &SyntaxNode) -> Result<Program, ParserError> {
+    Ok(Program {
+        statements: lower_statements(program)?,
+    })
+}
+
+fn lower_statements(node: &SyntaxNode) -> Result<Vec<Statement>, ParserError> {
+    check(node)?;
+    node.nodes().map(lower_statement).collect()
+}
+
+fn lower_statement(node: &SyntaxNode) -> Result<Statement, ParserError> {
+    check(node)?;
+
+    match node.kind {
+        SyntaxKind::LetStatement => {
+            let name = node
+                .tokens()
+                .find_map(|token| match &token.token.token_type {
+                    TokenType::Identifier(name) => Some(name.to_string()),
+                    _ => None,
+                })
+                .ok_or_else(|| malformed(node))?;
+            let annotation = annotation(node)?;
+            let value = node.nodes().last().ok_or_else(|| malformed(node))?;
+            Ok(Statement::annotated_let(
+                name,
+                annotation,
+                lower_expression(value)?,
+            ))
+        }
+        SyntaxKind::ReturnStatement => Ok(Statement::r#return(lower_expression(child(node, 0)?)?)),
+        SyntaxKind::ExpressionStatement => {
+            Ok(Statement::expression(lower_expression(child(node, 0)?)?))
+        }
+        SyntaxKind::ImportStatement => {
+            let path = node
+                .tokens()
+                .find_map(|token| match &token.token.token_type {
+                    TokenType::String(literal) => Some(
+                        parse_string(literal)
+                            .map_err(|msg| ParserError::new(msg, &token.token.location)),
+                    ),
+                    _ => None,
+                })
+                .ok_or_else(|| malformed(node))??;
+            let binding = match node.nodes().next() {
+                Some(list) => {
+                    check(list)?;
+                    ImportBinding::Names(names(list))
+                }
+                // the alias is the last identifier, after `as`
+                None => ImportBinding::Module(names(node).pop().ok_or_else(|| malformed(node))?),
+            };
+            Ok(Statement::import(path, binding))
+        }
+        SyntaxKind::ExportStatement => Ok(Statement::export(names(node))),
+        _ => Err(malformed(node)),
+    }
+}
+
+/// The identifiers among the tokens of `node`.
+fn names(node: &SyntaxNode) -> Vec<String> {
+    node.tokens()
+        .filter_map(|token| match &token.token.token_type {
+            TokenType::Identifier(name) => Some(name.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn lower_expression(node: &SyntaxNode) -> Result<Expression, ParserError> {
+    check(node)?;
+
+    let token = node.tokens().next().map(|token| &token.token);
+
+    match node.kind {
+        SyntaxKind::Identifier
+        | SyntaxKind::Integer
+        | SyntaxKind::String
+        | SyntaxKind::Boolean
+        | SyntaxKind::Null => match token.map(|token| &token.token_type) {
+            Some(TokenType::Identifier(identifier)) => Ok(Expression::identifier(*identifier)),
+            Some(TokenType::Integer(literal)) => parse_integer(literal)
+                .map(Expression::Int)
+                .map_err(|msg| ParserError::new(msg, &token.unwrap().location)),
+            Some(TokenType::String(literal)) => {
+                Parser::string_literal(literal, &token.unwrap().location, &mut vec![])
+            }
+            Some(TokenType::True) => Ok(Expression::Bool(true)),
+            Some(TokenType::False) => Ok(Expression::Bool(false)),
+            Some(TokenType::Null) => Ok(Expression::Null),
+            _ => Err(malformed(node)),
+        },
+        SyntaxKind::PrefixExpression => {
+            let operator = match token.map(|token| &token.token_type) {
+                Some(TokenType::Bang) => PrefixOperator::Not,
+                Some(TokenType::Minus) => PrefixOperator::Negative,
+                _ => return Err(malformed(node)),
+            };
+            Ok(Expression::prefix(
+                lower_expression(child(node, 0)?)?,
+                operator,
+            ))
+        }
+        SyntaxKind::InfixExpression => {
+            let operator = match token.map(|token| &token.token_type) {
+                Some(TokenType::Eq) => InfixOperator::Equal,
+                Some(TokenType::NotEq) => InfixOperator::NotEqual,
+                Some(TokenType::Plus) => InfixOperator::Add,
+                Some(TokenType::Minus) => InfixOperator::Sub,
+                Some(TokenType::Asterisk) => InfixOperator::Mult,
+                Some(TokenType::Slash) => InfixOperator::Div,
+                Some(TokenType::GT) => InfixOperator::GreaterThan,
+                Some(TokenType::LT) => InfixOperator::LessThan,
+                Some(TokenType::Modulo) => InfixOperator::Modulo,
+                _ => return Err(malformed(node)),
+            };
+            Ok(Expression::infix(
+                lower_expression(child(node, 0)?)?,
+                lower_expression(child(node, 1)?)?,
+                operator,
+            ))
+        }
+        // the parentheses are not a node of the AST
+        SyntaxKind::GroupedExpression => lower_expression(child(node, 0)?),
+        SyntaxKind::IfExpression => {
+            let alternative = match node.nodes().nth(2) {
+                Some(clause) => {
+                    check(clause)?;
+                    Some(lower_statements(child(clause, 0)?)?)
+                }
+                None => None,
+            };
+            Ok(Expression::r#if(
+                lower_expression(child(node, 0)?)?,
+                lower_statements(child(node, 1)?)?,
+                alternative,
+            ))
+        }
+        SyntaxKind::FunctionLiteral => {
+            let parameters = child(node, 0)?;
+            check(parameters)?;
+            // an annotation belongs to the identifier before it
+            let mut lowered: Vec<Parameter> = vec![];
+            for element in &parameters.children {
+                match element {
+                    SyntaxElement::Token(token) => {
+                        if let TokenType::Identifier(name) = &token.token.token_type {
+                            lowered.push(Parameter::from(*name));
+                        }
+                    }
+                    SyntaxElement::Node(annotation) => {
+                        let parameter = lowered.last_mut().ok_or_else(|| malformed(parameters))?;
+                        parameter.annotation = Some(lower_annotation(annotation)?);
+                    }
+                }
+            }
+            let body = node.nodes().last().ok_or_else(|| malformed(node))?;
+            Ok(Expression::annotated_function(
+                lowered,
+                annotation(node)?,
+                lower_statements(body)?,
+            ))
+        }
+        SyntaxKind::CallExpression => {
+            let arguments = child(node, 1)?;
+            check(arguments)?;
+            Ok(Expression::call(
+                lower_expression(child(node, 0)?)?,
+                arguments
+                    .nodes()
+                    .map(lower_expression)
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+        SyntaxKind::ArrayLiteral => Ok(Expression::Array(
+            node.nodes()
+                .map(lower_expression)
+                .collect::<Result<_, _>>()?,
+        )),
+        SyntaxKind::IndexExpression => Ok(Expression::index(
+            lower_expression(child(node, 0)?)?,
+            lower_expression(child(node, 1)?)?,
+        )),
+        SyntaxKind::MemberExpression => {
+            let name = names(node).pop().ok_or_else(|| malformed(node))?;
+            Ok(Expression::member(lower_expression(child(node, 0)?)?, name))
+        }
+        _ => Err(malformed(node)),
+    }
+}
+
+/// The annotation among the children of `node`, if it has one.
+fn annotation(node: &SyntaxNode) -> Result<Option<TypeAnnotation>, ParserError> {
+    node.nodes()
+        .find(|child| child.kind == SyntaxKind::TypeAnnotation)
+        .map(lower_annotation)
+        .transpose()
+}
+
+/// Lowers a `SyntaxKind::TypeAnnotation` node.
+pub fn lower_annotation(node: &SyntaxNode) -> Result<TypeAnnotation, ParserError> {
+    check(node)?;
+
+    match node.tokens().next().map(|token| &token.token.token_type) {
+        Some(TokenType::Identifier("int")) => Ok(TypeAnnotation::Int),
+        Some(TokenType::Identifier("bool")) => Ok(TypeAnnotation::Bool),
+        Some(TokenType::Identifier("string")) => Ok(TypeAnnotation::String),
+        Some(TokenType::Null) => Ok(TypeAnnotation::Null),
+        Some(TokenType::Function) => {
+            let types = node
+                .nodes()
+                .map(lower_annotation)
+                .collect::<Result<Vec<_>, _>>()?;
+            match types.split_last() {
+                Some((result, parameters)) => Ok(TypeAnnotation::function(
+                    parameters.to_vec(),
+                    result.clone(),
+                )),
+                None => Err(malformed(node)),
+            }
+        }
+        _ => Err(malformed(node)),
+    }
+}
+
+fn child<'n, 'a>(
+    node: &'n SyntaxNode<'a>,
+    index: usize,
+) -> Result<&'n SyntaxNode<'a>, ParserError> {
+    node.nodes().nth(index).ok_or_else(|| malformed(node))
+}
+
+/// Fails if `node` is, or directly contains, an error node.
+fn check(node: &SyntaxNode) -> Result<(), ParserError> {
+    if node.kind == SyntaxKind::Error || node.nodes().any(|n| n.kind == SyntaxKind::Error) {
+        return Err(ParserError::new(
+            format!("cannot lower {:?} with syntax errors", node.kind),
+            &location(node),
+        ));
+    }
+
+    Ok(())
+}
+
+fn malformed(node: &SyntaxNode) -> ParserError {
+    ParserError::new(format!("malformed {:?}", node.kind), &location(node))
+}
+
+fn location(node: &SyntaxNode) -> Location {
+    node.first_token()
+        .map(|token| token.token.location.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        lexer::lexer::Lexer,
+        parser::{cst::parser::CstParser, parser::Parser},
+    };
+
+    use super::lower;
+
+    #[test]
+    fn test_lowering_matches_parser() {
+        let inputs = vec![
+            indoc! {"
+                let factorial = fn(x) {
+                    // base case
+                    if (x == 0) { return 1; }
+                    x * factorial(x - 1)
+                };
+                factorial(5);
+            "},
+            "let a = -(1 + 2) * 3 % 4 / b - !c;",
+            "if (a < b) { a } else { b != c == true }",
+            "fn(a, b) { a + b }(1, 2)(3)",
+            "let f = fn() { null }; f()",
+            "import \"a\\\\b.mk\" as b; import { x, y } from \"c\"; export x, y;",
+            "m.f(1).g",
+            "let a = [1, [2, 3], f(x)[0]]; a[1][0] + [][0]",
+            "let s: string = \"a\\n\\\"b\\\"\" + upper(\"größe\")[0];",
+            "\"${a} and ${f(\"${b}\")[0]} \\${c}\"",
+            "let f: fn(int, fn() -> bool) -> null = fn(a: int, b) -> null { null };",
+            "1 2 3",
+            "",
+        ];
+
+        for input in inputs {
+            let mut cst_parser = CstParser::new(Lexer::new(input));
+            let tree = cst_parser.parse_program();
+            assert_eq!(cst_parser.errors.len(), 0, "parsing {:?}", input);
+
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+
+            assert_eq!(
+                lower(&tree).unwrap().statements,
+                program.statements,
+                "lowering {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_lowering_fails_on_errors() {
+        let inputs = vec!["let = 5;", "1 + ;", "fn(a { a }", "if (x) { 1 + }"];
+
+        for input in inputs {
+            let tree = CstParser::new(Lexer::new(input)).parse_program();
+            assert!(lower(&tree).is_err(), "lowering {:?}", input);
+        }
+    }
+}
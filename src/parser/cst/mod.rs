@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod cst;
+pub mod lower;
+pub mod parser;
@@ -0,0 +1,523 @@
+use std::mem::discriminant;
+
+use crate::{
+    lexer::{
+        lexer::Lexer,
+        token::{TokenType, TriviaToken},
+    },
+    parser::{parser::ParserError, precedence::Precedence},
+};
+
+use super::cst::{SyntaxElement, SyntaxKind, SyntaxNode};
+
+/// Parses the same grammar as `Parser`, but into a lossless `SyntaxNode`
+/// tree. Syntax errors are collected in `errors` and kept in the tree as
+/// `SyntaxKind::Error` nodes, so every input produces a tree that displays
+/// back to it.
+pub struct CstParser<'a> {
+    lexer: Lexer<'a>,
+    current: TriviaToken<'a>,
+    /// Number of tokens consumed so far, used to make progress on errors.
+    position: usize,
+    pub errors: Vec<ParserError>,
+}
+
+impl<'a> CstParser<'a> {
+    pub fn new(mut lexer: Lexer<'a>) -> Self {
+        let current = lexer.next_token_with_trivia();
+
+        CstParser {
+            lexer,
+            current,
+            position: 0,
+            errors: vec![],
+        }
+    }
+
+    pub fn parse_program(&mut self) -> SyntaxNode<'a> {
+        let mut children = vec![];
+
+        while !self.at(&TokenType::EOF) {
+            children.push(self.parse_statement_or_error());
+        }
+
+        children.push(self.bump());
+
+        SyntaxNode::new(SyntaxKind::Program, children)
+    }
+
+    /// Parses a statement, or turns the current token into an error node when
+    /// no statement can start with it.
+    fn parse_statement_or_error(&mut self) -> SyntaxElement<'a> {
+        let position = self.position;
+        let statement = self.parse_statement();
+
+        if self.position == position {
+            let token = self.bump();
+            return SyntaxElement::Node(SyntaxNode::new(SyntaxKind::Error, vec![token]));
+        }
+
+        SyntaxElement::Node(statement)
+    }
+
+    fn parse_statement(&mut self) -> SyntaxNode<'a> {
+        let mut children = vec![];
+
+        let kind = match self.current.token.token_type {
+            TokenType::Let => {
+                children.push(self.bump());
+                self.expect_identifier(&mut children);
+                if self.at(&TokenType::Colon) {
+                    children.push(self.bump());
+                    children.push(self.parse_type_annotation());
+                }
+                self.expect(&mut children, TokenType::Assign);
+                children.push(self.parse_expression(Precedence::LOWEST));
+                SyntaxKind::LetStatement
+            }
+            TokenType::Return => {
+                children.push(self.bump());
+                children.push(self.parse_expression(Precedence::LOWEST));
+                SyntaxKind::ReturnStatement
+            }
+            TokenType::Import => {
+                children.push(self.bump());
+                if self.at(&TokenType::LBrace) {
+                    let mut names = vec![self.bump()];
+                    self.parse_names(&mut names);
+                    self.expect(&mut names, TokenType::RBrace);
+                    children.push(SyntaxElement::Node(SyntaxNode::new(
+                        SyntaxKind::ImportList,
+                        names,
+                    )));
+                    self.expect_word(&mut children, "from");
+                    self.expect(&mut children, TokenType::String(""));
+                } else {
+                    self.expect(&mut children, TokenType::String(""));
+                    self.expect_word(&mut children, "as");
+                    self.expect_identifier(&mut children);
+                }
+                SyntaxKind::ImportStatement
+            }
+            TokenType::Export => {
+                children.push(self.bump());
+                self.parse_names(&mut children);
+                SyntaxKind::ExportStatement
+            }
+            _ => {
+                children.push(self.parse_expression(Precedence::LOWEST));
+                SyntaxKind::ExpressionStatement
+            }
+        };
+
+        if self.at(&TokenType::Semicolon) {
+            children.push(self.bump());
+        }
+
+        SyntaxNode::new(kind, children)
+    }
+
+    fn parse_expression(&mut self, precedence: Precedence) -> SyntaxElement<'a> {
+        let mut lhs = self.parse_prefix();
+
+        while !self.at(&TokenType::Semicolon) && precedence < Precedence::from(&self.current.token)
+        {
+            lhs = self.parse_infix(lhs);
+        }
+
+        lhs
+    }
+
+    fn parse_prefix(&mut self) -> SyntaxElement<'a> {
+        let kind = match self.current.token.token_type {
+            TokenType::Identifier(_) => SyntaxKind::Identifier,
+            TokenType::Integer(_) => SyntaxKind::Integer,
+            TokenType::String(_) => SyntaxKind::String,
+            TokenType::True | TokenType::False => SyntaxKind::Boolean,
+            TokenType::Null => SyntaxKind::Null,
+            TokenType::LParen => return self.parse_grouped_expression(),
+            TokenType::Bang | TokenType::Minus => return self.parse_prefix_expression(),
+            TokenType::If => return self.parse_if_expression(),
+            TokenType::Function => return self.parse_function_literal(),
+            TokenType::LBracket => {
+                let elements = self.parse_expression_list(TokenType::RBracket);
+                return SyntaxElement::Node(SyntaxNode::new(SyntaxKind::ArrayLiteral, elements));
+            }
+            _ => return self.parse_prefix_error(),
+        };
+
+        let token = self.bump();
+        SyntaxElement::Node(SyntaxNode::new(kind, vec![token]))
+    }
+
+    fn parse_prefix_error(&mut self) -> SyntaxElement<'a> {
+        self.error(format!(
+            "Expected prefix expression, got {:?}",
+            self.current.token.token_type
+        ));
+
+        // closing tokens are left for the enclosing construct to consume
+        let children = match self.current.token.token_type {
+            TokenType::RParen | TokenType::RBrace | TokenType::Semicolon | TokenType::EOF => {
+                vec![]
+            }
+            _ => vec![self.bump()],
+        };
+
+        SyntaxElement::Node(SyntaxNode::new(SyntaxKind::Error, children))
+    }
+
+    fn parse_grouped_expression(&mut self) -> SyntaxElement<'a> {
+        let mut children = vec![self.bump()];
+        children.push(self.parse_expression(Precedence::LOWEST));
+        self.expect(&mut children, TokenType::RParen);
+
+        SyntaxElement::Node(SyntaxNode::new(SyntaxKind::GroupedExpression, children))
+    }
+
+    fn parse_prefix_expression(&mut self) -> SyntaxElement<'a> {
+        let mut children = vec![self.bump()];
+        children.push(self.parse_expression(Precedence::PREFIX));
+
+        SyntaxElement::Node(SyntaxNode::new(SyntaxKind::PrefixExpression, children))
+    }
+
+    fn parse_if_expression(&mut self) -> SyntaxElement<'a> {
+        let mut children = vec![self.bump()];
+
+        self.expect(&mut children, TokenType::LParen);
+        children.push(self.parse_expression(Precedence::LOWEST));
+        self.expect(&mut children, TokenType::RParen);
+        children.push(self.parse_block());
+
+        if self.at(&TokenType::Else) {
+            let mut clause = vec![self.bump()];
+            clause.push(self.parse_block());
+            children.push(SyntaxElement::Node(SyntaxNode::new(
+                SyntaxKind::ElseClause,
+                clause,
+            )));
+        }
+
+        SyntaxElement::Node(SyntaxNode::new(SyntaxKind::IfExpression, children))
+    }
+
+    fn parse_function_literal(&mut self) -> SyntaxElement<'a> {
+        let mut children = vec![self.bump()];
+
+        let mut parameters = vec![];
+        if self.expect(&mut parameters, TokenType::LParen) {
+            while let TokenType::Identifier(_) = self.current.token.token_type {
+                parameters.push(self.bump());
+                if self.at(&TokenType::Colon) {
+                    parameters.push(self.bump());
+                    parameters.push(self.parse_type_annotation());
+                }
+                if self.at(&TokenType::Comma) {
+                    parameters.push(self.bump());
+                }
+            }
+            self.expect(&mut parameters, TokenType::RParen);
+        }
+        children.push(SyntaxElement::Node(SyntaxNode::new(
+            SyntaxKind::ParameterList,
+            parameters,
+        )));
+
+        if self.at(&TokenType::Arrow) {
+            children.push(self.bump());
+            children.push(self.parse_type_annotation());
+        }
+
+        children.push(self.parse_block());
+
+        SyntaxElement::Node(SyntaxNode::new(SyntaxKind::FunctionLiteral, children))
+    }
+
+    fn parse_type_annotation(&mut self) -> SyntaxElement<'a> {
+        let mut children = vec![];
+
+        match self.current.token.token_type {
+            TokenType::Identifier("int" | "bool" | "string") | TokenType::Null => {
+                children.push(self.bump())
+            }
+            TokenType::Function => {
+                children.push(self.bump());
+                self.expect(&mut children, TokenType::LParen);
+                if !self.at(&TokenType::RParen) {
+                    children.push(self.parse_type_annotation());
+                    while self.at(&TokenType::Comma) {
+                        children.push(self.bump());
+                        children.push(self.parse_type_annotation());
+                    }
+                }
+                self.expect(&mut children, TokenType::RParen);
+                self.expect(&mut children, TokenType::Arrow);
+                children.push(self.parse_type_annotation());
+            }
+            _ => {
+                self.error(format!(
+                    "expected a type, got {}",
+                    self.current.token.token_type
+                ));
+                // like a missing expression, a misspelled name is kept
+                let error = match self.current.token.token_type {
+                    TokenType::Identifier(_) => vec![self.bump()],
+                    _ => vec![],
+                };
+                children.push(SyntaxElement::Node(SyntaxNode::new(
+                    SyntaxKind::Error,
+                    error,
+                )));
+            }
+        }
+
+        SyntaxElement::Node(SyntaxNode::new(SyntaxKind::TypeAnnotation, children))
+    }
+
+    fn parse_block(&mut self) -> SyntaxElement<'a> {
+        let mut children = vec![];
+
+        if self.expect(&mut children, TokenType::LBrace) {
+            while !self.at(&TokenType::RBrace) && !self.at(&TokenType::EOF) {
+                children.push(self.parse_statement_or_error());
+            }
+            self.expect(&mut children, TokenType::RBrace);
+        }
+
+        SyntaxElement::Node(SyntaxNode::new(SyntaxKind::Block, children))
+    }
+
+    fn parse_infix(&mut self, lhs: SyntaxElement<'a>) -> SyntaxElement<'a> {
+        let precedence = Precedence::from(&self.current.token);
+
+        if self.at(&TokenType::LParen) {
+            let arguments = self.parse_expression_list(TokenType::RParen);
+            let arguments =
+                SyntaxElement::Node(SyntaxNode::new(SyntaxKind::ArgumentList, arguments));
+            return SyntaxElement::Node(SyntaxNode::new(
+                SyntaxKind::CallExpression,
+                vec![lhs, arguments],
+            ));
+        }
+
+        if self.at(&TokenType::LBracket) {
+            let mut children = vec![lhs, self.bump()];
+            children.push(self.parse_expression(Precedence::LOWEST));
+            self.expect(&mut children, TokenType::RBracket);
+            return SyntaxElement::Node(SyntaxNode::new(SyntaxKind::IndexExpression, children));
+        }
+
+        if self.at(&TokenType::Dot) {
+            let mut children = vec![lhs, self.bump()];
+            self.expect_identifier(&mut children);
+            return SyntaxElement::Node(SyntaxNode::new(SyntaxKind::MemberExpression, children));
+        }
+
+        let operator = self.bump();
+        let rhs = self.parse_expression(precedence);
+
+        SyntaxElement::Node(SyntaxNode::new(
+            SyntaxKind::InfixExpression,
+            vec![lhs, operator, rhs],
+        ))
+    }
+
+    /// Consumes the current token if it has the `expected` type, otherwise
+    /// records an error and leaves an empty error node in its place.
+    fn expect(&mut self, children: &mut Vec<SyntaxElement<'a>>, expected: TokenType<'a>) -> bool {
+        if self.at(&expected) {
+            children.push(self.bump());
+            return true;
+        }
+
+        self.missing(
+            children,
+            format!("unexpected token {}", self.current.token.token_type),
+        );
+        false
+    }
+
+    fn expect_identifier(&mut self, children: &mut Vec<SyntaxElement<'a>>) {
+        if let TokenType::Identifier(_) = self.current.token.token_type {
+            children.push(self.bump());
+            return;
+        }
+
+        self.missing(
+            children,
+            format!("expected identifier, got {}", self.current.token.token_type),
+        );
+    }
+
+    /// The opening token, then expressions separated by commas up to the
+    /// `end` token.
+    fn parse_expression_list(&mut self, end: TokenType<'a>) -> Vec<SyntaxElement<'a>> {
+        let mut children = vec![self.bump()];
+
+        if !self.at(&end) {
+            children.push(self.parse_expression(Precedence::LOWEST));
+            while self.at(&TokenType::Comma) {
+                children.push(self.bump());
+                children.push(self.parse_expression(Precedence::LOWEST));
+            }
+        }
+        self.expect(&mut children, end);
+
+        children
+    }
+
+    /// Identifiers separated by commas, as after `export`.
+    fn parse_names(&mut self, children: &mut Vec<SyntaxElement<'a>>) {
+        self.expect_identifier(children);
+        while self.at(&TokenType::Comma) {
+            children.push(self.bump());
+            self.expect_identifier(children);
+        }
+    }
+
+    /// Expects an identifier used as a keyword in one place only, like the
+    /// `as` of an import.
+    fn expect_word(&mut self, children: &mut Vec<SyntaxElement<'a>>, word: &str) {
+        if self.current.token.token_type == TokenType::Identifier(word) {
+            children.push(self.bump());
+            return;
+        }
+
+        self.missing(
+            children,
+            format!("expected {}, got {}", word, self.current.token.token_type),
+        );
+    }
+
+    fn missing(&mut self, children: &mut Vec<SyntaxElement<'a>>, msg: String) {
+        self.error(msg);
+        children.push(SyntaxElement::Node(SyntaxNode::new(
+            SyntaxKind::Error,
+            vec![],
+        )));
+    }
+
+    fn error(&mut self, msg: String) {
+        self.errors
+            .push(ParserError::new(msg, &self.current.token.location));
+    }
+
+    /// Whether the current token has the same type as `token_type`, ignoring
+    /// any payload.
+    fn at(&self, token_type: &TokenType<'_>) -> bool {
+        discriminant(&self.current.token.token_type) == discriminant(token_type)
+    }
+
+    fn bump(&mut self) -> SyntaxElement<'a> {
+        let next = self.lexer.next_token_with_trivia();
+        self.position += 1;
+        SyntaxElement::Token(std::mem::replace(&mut self.current, next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        lexer::lexer::Lexer,
+        parser::cst::cst::{SyntaxElement, SyntaxKind, SyntaxNode},
+    };
+
+    use super::CstParser;
+
+    fn parse(input: &str) -> (SyntaxNode<'_>, usize) {
+        let mut parser = CstParser::new(Lexer::new(input));
+        let tree = parser.parse_program();
+        (tree, parser.errors.len())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = vec![
+            indoc! {"
+                // factorial
+                let factorial = fn(x) {
+                    if (x == 0) { return 1; } // base case
+                    x * factorial(x - 1)
+                };
+
+                factorial( 5 ) ;
+            "},
+            "let  a=1;\r\n\tlet b = -(a + 2) % 3 // trailing",
+            "if (a) {} else { b }  ",
+            "fn(a, b,) { a }(1, 2)",
+            "let f : fn( int,bool )->null = fn(a: int, b) -> int { a };",
+            "import \"lib/math.mk\"  as math;\nimport {a,b} from \"x\"\nexport a , b;",
+            "math . add(1, 2).x",
+            "let a = [1, [ 2,3 ], f(x)[0]] ; a [ 1 ][0]",
+            "[]",
+            "let s :string = \"größe \\\"q\\\"\" + s[0]",
+            "\"a ${ f(\"}\") }\\${b}\"",
+            "",
+            "   \n  // only a comment",
+        ];
+
+        for input in inputs {
+            let (tree, errors) = parse(input);
+            assert_eq!(errors, 0, "parsing {:?}", input);
+            assert_eq!(tree.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_with_errors() {
+        let inputs = vec![
+            "let = 5;",
+            "let x 5",
+            "1 + ;",
+            ") } let y = 2;",
+            "if (x { 1 }",
+            "fn(a { a }",
+            "add(1, 2",
+            "let x = @;",
+            "{ 1 }",
+            "let x: = 1;",
+            "let x: float = 1;",
+            "fn(a: fn(int) { a }",
+            "import x as y;",
+            "import { a, } from \"x\";",
+            "import \"x\" from y;",
+            "export;",
+            "a.;",
+            "[1, 2",
+            "a[1;",
+            "a[]",
+        ];
+
+        for input in inputs {
+            let (tree, errors) = parse(input);
+            assert!(errors > 0, "parsing {:?}", input);
+            assert_eq!(tree.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_tree_structure() {
+        let (tree, _) = parse("let x = 1 + f(2);");
+
+        let statement = tree.nodes().next().unwrap();
+        assert_eq!(statement.kind, SyntaxKind::LetStatement);
+
+        let kinds: Vec<SyntaxKind> = statement.nodes().map(|node| node.kind).collect();
+        assert_eq!(kinds, vec![SyntaxKind::InfixExpression]);
+
+        let infix = statement.nodes().next().unwrap();
+        let kinds: Vec<SyntaxKind> = infix.nodes().map(|node| node.kind).collect();
+        assert_eq!(kinds, vec![SyntaxKind::Integer, SyntaxKind::CallExpression]);
+
+        let texts: Vec<&str> = statement.tokens().map(|token| token.text).collect();
+        assert_eq!(texts, vec!["let", "x", "=", ";"]);
+
+        assert!(matches!(
+            tree.children.last(),
+            Some(SyntaxElement::Token(token)) if token.text.is_empty()
+        ));
+    }
+}
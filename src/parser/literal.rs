@@ -0,0 +1,157 @@
+/// Parses the text of an integer literal: decimal, or hexadecimal `0x`,
+/// binary `0b` and octal `0o`, with single `_` separators between digits.
+pub fn parse_integer(literal: &str) -> Result<i64, String> {
+    let (radix, digits) = match literal.get(..2) {
+        Some("0x" | "0X") => (16, &literal[2..]),
+        Some("0b" | "0B") => (2, &literal[2..]),
+        Some("0o" | "0O") => (8, &literal[2..]),
+        _ => (10, literal),
+    };
+
+    let malformed = || format!("malformed integer literal {}", literal);
+
+    if digits.is_empty()
+        || digits.starts_with('_')
+        || digits.ends_with('_')
+        || digits.contains("__")
+        || !digits.chars().all(|ch| ch == '_' || ch.is_digit(radix))
+    {
+        return Err(malformed());
+    }
+
+    let digits = digits.replace('_', "");
+    i64::from_str_radix(&digits, radix)
+        .map_err(|_| format!("integer literal {} is out of range", literal))
+}
+
+/// Parses the text between the quotes of a string literal, replacing the
+/// escapes `\"`, `\\`, `\$`, `\n`, `\t` and `\r`.
+pub fn parse_string(literal: &str) -> Result<String, String> {
+    let mut string = String::with_capacity(literal.len());
+    let mut chars = literal.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            string.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('"') => string.push('"'),
+            Some('\\') => string.push('\\'),
+            Some('$') => string.push('$'),
+            Some('n') => string.push('\n'),
+            Some('t') => string.push('\t'),
+            Some('r') => string.push('\r'),
+            Some(other) => return Err(format!("unknown escape \\{} in string", other)),
+            None => return Err("unfinished escape at the end of a string".to_string()),
+        }
+    }
+
+    Ok(string)
+}
+
+/// Writes `string` as a literal that `parse_string` reads back.
+pub fn quote_string(string: &str) -> String {
+    format!("\"{}\"", escape_string(string))
+}
+
+/// Escapes `string` to be written between the quotes of a literal, so that
+/// none of it is taken for an interpolation either.
+pub fn escape_string(string: &str) -> String {
+    let mut literal = String::with_capacity(string.len());
+    let mut chars = string.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '$' if chars.peek() == Some(&'{') => literal.push_str("\\$"),
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '\r' => literal.push_str("\\r"),
+            ch => literal.push(ch),
+        }
+    }
+
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_integer, parse_string, quote_string};
+
+    #[test]
+    fn test_parse_string() {
+        let tests = vec![
+            ("", Ok("")),
+            ("utils.mk", Ok("utils.mk")),
+            (r#"a \"b\" \\ c\n\t"#, Ok("a \"b\" \\ c\n\t")),
+            ("größe", Ok("größe")),
+            (r"\${a} $ {b} $", Ok("${a} $ {b} $")),
+            (r"\q", Err("unknown escape \\q in string")),
+        ];
+
+        for (literal, expected) in tests {
+            if let Ok(string) = expected {
+                assert_eq!(quote_string(string), format!("\"{}\"", literal));
+            }
+            assert_eq!(
+                parse_string(literal),
+                expected.map(str::to_string).map_err(str::to_string),
+                "parsing {}",
+                literal
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_integer() {
+        let tests = vec![
+            ("0", 0),
+            ("1234", 1234),
+            ("1_000_000", 1_000_000),
+            ("0xFF", 255),
+            ("0Xff_ff", 65535),
+            ("0b1010", 10),
+            ("0o17", 15),
+            ("007", 7),
+            ("9223372036854775807", i64::MAX),
+            ("0x7fff_ffff_ffff_ffff", i64::MAX),
+        ];
+
+        for (literal, expected) in tests {
+            assert_eq!(parse_integer(literal), Ok(expected), "parsing {}", literal);
+        }
+    }
+
+    #[test]
+    fn test_parse_malformed_integer() {
+        let tests = vec![
+            ("0x", "malformed integer literal 0x"),
+            ("1__0", "malformed integer literal 1__0"),
+            ("1_", "malformed integer literal 1_"),
+            ("0b_1", "malformed integer literal 0b_1"),
+            ("0b2", "malformed integer literal 0b2"),
+            ("0o8", "malformed integer literal 0o8"),
+            ("0xfg", "malformed integer literal 0xfg"),
+            (
+                "9223372036854775808",
+                "integer literal 9223372036854775808 is out of range",
+            ),
+            (
+                "0x1_0000_0000_0000_0000",
+                "integer literal 0x1_0000_0000_0000_0000 is out of range",
+            ),
+        ];
+
+        for (literal, expected) in tests {
+            assert_eq!(
+                parse_integer(literal),
+                Err(expected.to_string()),
+                "parsing {}",
+                literal
+            );
+        }
+    }
+}
@@ -1,4 +1,9 @@
 pub mod ast;
+pub mod cst;
+pub(crate) mod literal;
 mod macros;
+#[allow(clippy::module_inception)]
 pub mod parser;
-mod precedence;
+pub(crate) mod precedence;
+pub mod source_map;
+pub mod stream;
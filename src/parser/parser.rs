@@ -3,20 +3,23 @@ use std::fmt::Display;
 use crate::{
     expect_peek,
     lexer::{
-        lexer::Lexer,
-        location::Location,
-        token::{Token, TokenType},
+        lexer::{split_string, Lexer},
+        location::{Location, Span},
+        token::{Comment, StringPart, Token, TokenType},
     },
 };
 
 use super::{
     ast::{
-        expression::Expression,
+        annotation::TypeAnnotation,
+        expression::{Expression, Parameter, TemplatePart},
         operator::{InfixOperator, PrefixOperator},
         program::Program,
-        statement::Statement,
+        statement::{ImportBinding, Statement},
     },
+    literal,
     precedence::Precedence,
+    source_map::{RecordedSpan, SourceMap},
 };
 
 #[derive(Debug)]
@@ -32,19 +35,28 @@ impl Display for ParserError {
 }
 
 impl ParserError {
-    fn new(msg: impl Into<String>, location: &Location) -> ParserError {
+    pub(crate) fn new(msg: impl Into<String>, location: &Location) -> ParserError {
         ParserError {
             msg: msg.into(),
             location: location.clone(),
         }
     }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
 }
 
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
-    current_token: Token,
-    peeking_token: Token,
+    current_token: Token<'a>,
+    peeking_token: Token<'a>,
     pub errors: Vec<ParserError>,
+    spans: Vec<RecordedSpan>,
 }
 
 impl<'a> Parser<'a> {
@@ -52,39 +64,65 @@ impl<'a> Parser<'a> {
         let current_token = lexer.next_token();
         let peeking_token = lexer.next_token();
 
-        let parser = Parser {
+        Parser {
             lexer,
             current_token,
             peeking_token,
             errors: vec![],
-        };
-
-        parser
+            spans: vec![],
+        }
     }
 
     pub fn parse_program(&mut self) -> Program {
         let mut program = Program::new();
 
         while self.current_token.token_type != TokenType::EOF {
+            let recorded = self.spans.len();
             let stmt = self.parse_statement();
 
             match stmt {
-                Ok(stmt) => program.statements.push(stmt),
-                Err(err) => self.errors.push(err),
+                Ok(stmt) => {
+                    program.statements.push(stmt);
+                    self.next_token();
+                }
+                Err(err) => {
+                    self.errors.push(err);
+                    self.spans.truncate(recorded);
+                    self.advance_tokens();
+                }
             }
-
-            self.advance_tokens();
         }
 
         program
     }
 
+    /// Source locations of the nodes in `program`, which must be the program
+    /// returned by this parser's `parse_program`.
+    pub fn source_map<'p>(&self, program: &'p Program) -> SourceMap<'p> {
+        SourceMap::new(program, &self.spans)
+    }
+
+    /// Comments skipped while parsing, in source order, if the lexer keeps
+    /// them.
+    pub fn comments(&self) -> &[Comment<'a>] {
+        self.lexer.comments()
+    }
+
     fn parse_statement(&mut self) -> Result<Statement, ParserError> {
-        match self.current_token.token_type {
+        let start = self.current_token.location.clone();
+
+        let statement = match self.current_token.token_type {
             TokenType::Let => self.parse_let_statement(),
             TokenType::Return => self.parse_return_statement(),
+            TokenType::Import => self.parse_import_statement(),
+            TokenType::Export => self.parse_export_statement(),
             _ => self.parse_expression_statement(),
-        }
+        }?;
+
+        self.spans
+            .push(RecordedSpan::Statement(self.span_from(start)));
+
+        Ok(statement)
     }
 
     fn parse_expression_statement(&mut self) -> Result<Statement, ParserError> {
@@ -98,6 +136,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, ParserError> {
+        let start = self.current_token.location.clone();
         let mut lhs = self.parse_prefix()?;
 
         while self.peeking_token.token_type != TokenType::Semicolon
@@ -106,11 +145,18 @@ impl<'a> Parser<'a> {
             self.next_token();
 
             lhs = self.parse_infix(lhs)?;
+            self.spans
+                .push(RecordedSpan::Expression(self.span_from(start.clone())));
         }
 
         Ok(lhs)
     }
 
+    /// Span from `start` to the token the parser is currently on.
+    fn span_from(&self, start: Location) -> Span {
+        Span::new(start, self.current_token.end(self.lexer.input()))
+    }
+
     fn advance_tokens(&mut self) {
         while self.current_token.token_type != TokenType::Semicolon
             && self.current_token.token_type != TokenType::EOF
@@ -124,48 +170,144 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_prefix(&mut self) -> Result<Expression, ParserError> {
-        match &self.current_token.token_type {
-            TokenType::Identifier(identifier) => Ok(Expression::identifier(identifier)),
+        let start = self.current_token.location.clone();
+
+        let expression = match &self.current_token.token_type {
+            TokenType::Identifier(identifier) => Ok(Expression::identifier(*identifier)),
             TokenType::Integer(integer_literal) => self.parse_integer(integer_literal),
-            TokenType::LParen => self.parse_grouped_expression(),
+            TokenType::String(literal) => {
+                Parser::string_literal(literal, &self.current_token.location, &mut self.spans)
+            }
+            // the parentheses are not a node of their own
+            TokenType::LParen => return self.parse_grouped_expression(),
             TokenType::True | TokenType::False => self.parse_boolean(),
             TokenType::Bang | TokenType::Minus => self.parse_prefix_expression(),
             TokenType::If => self.parse_if_expression(),
             TokenType::Function => self.parse_function_literal(),
             TokenType::Null => Ok(Expression::Null),
+            TokenType::LBracket => self.parse_array_literal(),
             token_type => Err(ParserError::new(
                 format!("Expected prefix expression, got {:?}", token_type),
                 &self.current_token.location,
             )),
+        }?;
+
+        self.spans
+            .push(RecordedSpan::Expression(self.span_from(start)));
+
+        Ok(expression)
+    }
+
+    /// Parses the text of a string literal starting at `location`. Each
+    /// interpolation is parsed on its own, with locations carrying on from
+    /// where it is in the source, and its spans are added to `spans`.
+    pub(crate) fn string_literal(
+        literal: &str,
+        location: &Location,
+        spans: &mut Vec<RecordedSpan>,
+    ) -> Result<Expression, ParserError> {
+        let parts = split_string(literal);
+        if let [StringPart::Text(text)] = parts.as_slice() {
+            return literal::parse_string(text)
+                .map(Expression::String)
+                .map_err(|msg| ParserError::new(msg, location));
+        }
+
+        let mut template = vec![];
+        for part in parts {
+            match part {
+                StringPart::Text(text) => template.push(TemplatePart::Text(
+                    literal::parse_string(text).map_err(|msg| ParserError::new(msg, location))?,
+                )),
+                StringPart::Interpolation(code, offset) => {
+                    // the lexer starts after the `{` before the code
+                    let start = location.after(&literal[..offset]);
+                    let mut parser =
+                        Parser::new(Lexer::continuing_from(code, start.clone()).keeping_comments());
+
+                    if parser.current_token.token_type == TokenType::EOF {
+                        return Err(ParserError::new("empty interpolation", &start));
+                    }
+                    let expression = parser.parse_expression(Precedence::LOWEST)?;
+                    // a comment would have to be dropped when the string is
+                    // formatted on one line
+                    if let Some(comment) = parser.comments().first() {
+                        return Err(ParserError::new(
+                            "comments are not allowed in interpolations",
+                            &comment.location,
+                        ));
+                    }
+                    if let Some(error) = parser.errors.into_iter().next() {
+                        return Err(error);
+                    }
+                    if parser.peeking_token.token_type != TokenType::EOF {
+                        return Err(ParserError::new(
+                            format!(
+                                "unexpected token {} in interpolation",
+                                parser.peeking_token.token_type
+                            ),
+                            &parser.peeking_token.location,
+                        ));
+                    }
+
+                    spans.append(&mut parser.spans);
+                    template.push(TemplatePart::Expression(expression));
+                }
+            }
         }
+
+        Ok(Expression::Template(template))
     }
 
     fn parse_call_expression(&mut self, function: Expression) -> Result<Expression, ParserError> {
-        let arguments = self.parse_call_arguments()?;
+        let arguments = self.parse_expression_list(TokenType::RParen)?;
         Ok(Expression::call(function, arguments))
     }
 
-    fn parse_call_arguments(&mut self) -> Result<Vec<Expression>, ParserError> {
-        let mut arguments = vec![];
+    fn parse_array_literal(&mut self) -> Result<Expression, ParserError> {
+        let elements = self.parse_expression_list(TokenType::RBracket)?;
+        Ok(Expression::Array(elements))
+    }
 
-        if self.peeking_token.token_type == TokenType::RParen {
+    fn parse_index_expression(&mut self, left: Expression) -> Result<Expression, ParserError> {
+        self.next_token();
+
+        let index = self.parse_expression(Precedence::LOWEST)?;
+
+        expect_peek!(self, RBracket)?;
+
+        Ok(Expression::index(left, index))
+    }
+
+    /// Parses expressions separated by commas up to the `end` token, leaving
+    /// the parser on it.
+    fn parse_expression_list(&mut self, end: TokenType) -> Result<Vec<Expression>, ParserError> {
+        let mut expressions = vec![];
+
+        if self.peeking_token.token_type == end {
             self.next_token();
-            return Ok(arguments);
+            return Ok(expressions);
         }
 
         self.next_token();
 
-        arguments.push(self.parse_expression(Precedence::LOWEST)?);
+        expressions.push(self.parse_expression(Precedence::LOWEST)?);
 
         while self.peeking_token.token_type == TokenType::Comma {
             self.next_token();
             self.next_token();
-            arguments.push(self.parse_expression(Precedence::LOWEST)?);
+            expressions.push(self.parse_expression(Precedence::LOWEST)?);
         }
 
-        expect_peek!(self, RParen)?;
+        if self.peeking_token.token_type != end {
+            return Err(ParserError::new(
+                format!("unexpected token {}", self.peeking_token.token_type),
+                &self.peeking_token.location,
+            ));
+        }
+        self.next_token();
 
-        Ok(arguments)
+        Ok(expressions)
     }
 
     fn parse_function_literal(&mut self) -> Result<Expression, ParserError> {
@@ -173,14 +315,21 @@ impl<'a> Parser<'a> {
 
         let parameters = self.parse_function_params()?;
 
+        let result = if self.peeking_token.token_type == TokenType::Arrow {
+            self.next_token();
+            Some(self.parse_type_annotation()?)
+        } else {
+            None
+        };
+
         expect_peek!(self, LBrace)?;
 
         let body = self.parse_block_statement()?;
 
-        Ok(Expression::function(parameters, body))
+        Ok(Expression::annotated_function(parameters, result, body))
     }
 
-    fn parse_function_params(&mut self) -> Result<Vec<String>, ParserError> {
+    fn parse_function_params(&mut self) -> Result<Vec<Parameter>, ParserError> {
         let mut params = vec![];
 
         if self.peeking_token.token_type == TokenType::RParen {
@@ -191,7 +340,17 @@ impl<'a> Parser<'a> {
         self.next_token();
 
         while let TokenType::Identifier(identifier) = &self.current_token.token_type {
-            params.push(identifier.clone());
+            let start = self.current_token.location.clone();
+            let name = identifier.to_string();
+            let annotation = if self.peeking_token.token_type == TokenType::Colon {
+                self.next_token();
+                Some(self.parse_type_annotation()?)
+            } else {
+                None
+            };
+            params.push(Parameter::new(name, annotation));
+            self.spans
+                .push(RecordedSpan::Parameter(self.span_from(start)));
 
             self.next_token();
             if let TokenType::Comma = self.current_token.token_type {
@@ -229,6 +388,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_block_statement(&mut self) -> Result<Vec<Statement>, ParserError> {
+        let start = self.current_token.location.clone();
         self.next_token();
 
         let mut statements = vec![];
@@ -241,6 +401,8 @@ impl<'a> Parser<'a> {
             self.next_token();
         }
 
+        self.spans.push(RecordedSpan::Block(self.span_from(start)));
+
         Ok(statements)
     }
 
@@ -286,6 +448,11 @@ impl<'a> Parser<'a> {
             TokenType::LT => InfixOperator::LessThan,
             TokenType::Modulo => InfixOperator::Modulo,
             TokenType::LParen => return self.parse_call_expression(lhs),
+            TokenType::LBracket => return self.parse_index_expression(lhs),
+            TokenType::Dot => {
+                let name = self.expect_identifier()?;
+                return Ok(Expression::member(lhs, name));
+            }
             token_type => {
                 return Err(ParserError::new(
                     format!("unexpected token {}", token_type),
@@ -311,20 +478,17 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_integer(&self, literal: &String) -> Result<Expression, ParserError> {
-        literal.parse().map(Expression::Int).map_err(|_| {
-            ParserError::new(
-                format!("failed to parse integer {}", literal),
-                &self.current_token.location,
-            )
-        })
+    fn parse_integer(&self, literal: &str) -> Result<Expression, ParserError> {
+        literal::parse_integer(literal)
+            .map(Expression::Int)
+            .map_err(|msg| ParserError::new(msg, &self.current_token.location))
     }
 
     fn parse_let_statement(&mut self) -> Result<Statement, ParserError> {
         self.next_token();
 
         let identifier = match &self.current_token.token_type {
-            TokenType::Identifier(identifier) => identifier.clone(),
+            TokenType::Identifier(identifier) => identifier.to_string(),
             _ => {
                 return Err(ParserError::new(
                     format!("expected identifier, got {}", self.current_token.token_type),
@@ -333,6 +497,13 @@ impl<'a> Parser<'a> {
             }
         };
 
+        let annotation = if self.peeking_token.token_type == TokenType::Colon {
+            self.next_token();
+            Some(self.parse_type_annotation()?)
+        } else {
+            None
+        };
+
         expect_peek!(self, Assign)?;
 
         self.next_token();
@@ -343,7 +514,141 @@ impl<'a> Parser<'a> {
             self.next_token();
         }
 
-        Ok(Statement::r#let(identifier, expression))
+        Ok(Statement::annotated_let(identifier, annotation, expression))
+    }
+
+    /// Parses the type after the next token, leaving the parser on its last
+    /// token.
+    fn parse_type_annotation(&mut self) -> Result<TypeAnnotation, ParserError> {
+        self.next_token();
+
+        match &self.current_token.token_type {
+            TokenType::Identifier("int") => Ok(TypeAnnotation::Int),
+            TokenType::Identifier("bool") => Ok(TypeAnnotation::Bool),
+            TokenType::Identifier("string") => Ok(TypeAnnotation::String),
+            TokenType::Null => Ok(TypeAnnotation::Null),
+            TokenType::Function => {
+                expect_peek!(self, LParen)?;
+
+                let mut parameters = vec![];
+                if self.peeking_token.token_type != TokenType::RParen {
+                    parameters.push(self.parse_type_annotation()?);
+                    while self.peeking_token.token_type == TokenType::Comma {
+                        self.next_token();
+                        parameters.push(self.parse_type_annotation()?);
+                    }
+                }
+
+                expect_peek!(self, RParen)?;
+                expect_peek!(self, Arrow)?;
+
+                let result = self.parse_type_annotation()?;
+                Ok(TypeAnnotation::function(parameters, result))
+            }
+            token_type => Err(ParserError::new(
+                format!("expected a type, got {}", token_type),
+                &self.current_token.location,
+            )),
+        }
+    }
+
+    /// Parses `import "path" as name;` or `import { a, b } from "path";`.
+    fn parse_import_statement(&mut self) -> Result<Statement, ParserError> {
+        let statement = match self.peeking_token.token_type {
+            TokenType::String(_) => {
+                let path = self.expect_string()?;
+                self.expect_word("as")?;
+                let name = self.expect_identifier()?;
+                Statement::import(path, ImportBinding::Module(name))
+            }
+            TokenType::LBrace => {
+                self.next_token();
+                let names = self.parse_names()?;
+                expect_peek!(self, RBrace)?;
+                self.expect_word("from")?;
+                let path = self.expect_string()?;
+                Statement::import(path, ImportBinding::Names(names))
+            }
+            _ => {
+                return Err(ParserError::new(
+                    format!(
+                        "expected a string or {{ after import, got {}",
+                        self.peeking_token.token_type
+                    ),
+                    &self.peeking_token.location,
+                ))
+            }
+        };
+
+        if self.peeking_token.token_type == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        Ok(statement)
+    }
+
+    fn parse_export_statement(&mut self) -> Result<Statement, ParserError> {
+        let names = self.parse_names()?;
+
+        if self.peeking_token.token_type == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Statement::export(names))
+    }
+
+    /// Parses the identifiers after the current token, separated by commas.
+    fn parse_names(&mut self) -> Result<Vec<String>, ParserError> {
+        let mut names = vec![self.expect_identifier()?];
+
+        while self.peeking_token.token_type == TokenType::Comma {
+            self.next_token();
+            names.push(self.expect_identifier()?);
+        }
+
+        Ok(names)
+    }
+
+    fn expect_identifier(&mut self) -> Result<String, ParserError> {
+        match self.peeking_token.token_type {
+            TokenType::Identifier(identifier) => {
+                self.next_token();
+                Ok(identifier.to_string())
+            }
+            _ => Err(ParserError::new(
+                format!("expected identifier, got {}", self.peeking_token.token_type),
+                &self.peeking_token.location,
+            )),
+        }
+    }
+
+    fn expect_string(&mut self) -> Result<String, ParserError> {
+        match self.peeking_token.token_type {
+            TokenType::String(string) => {
+                self.next_token();
+                literal::parse_string(string)
+                    .map_err(|msg| ParserError::new(msg, &self.current_token.location))
+            }
+            _ => Err(ParserError::new(
+                format!("expected a string, got {}", self.peeking_token.token_type),
+                &self.peeking_token.location,
+            )),
+        }
+    }
+
+    /// Expects a word like `as`, which is only a keyword where it is
+    /// expected and an identifier everywhere else.
+    fn expect_word(&mut self, word: &str) -> Result<(), ParserError> {
+        match self.peeking_token.token_type {
+            TokenType::Identifier(identifier) if identifier == word => {
+                self.next_token();
+                Ok(())
+            }
+            _ => Err(ParserError::new(
+                format!("expected {}, got {}", word, self.peeking_token.token_type),
+                &self.peeking_token.location,
+            )),
+        }
     }
 
     fn parse_return_statement(&mut self) -> Result<Statement, ParserError> {
@@ -371,9 +676,10 @@ mod tests {
     use crate::{
         lexer::{lexer::Lexer, token::TokenType},
         parser::ast::{
-            expression::Expression,
+            annotation::TypeAnnotation,
+            expression::{Expression, Parameter, TemplatePart},
             operator::{InfixOperator, PrefixOperator},
-            statement::Statement,
+            statement::{ImportBinding, Statement},
         },
     };
 
@@ -418,6 +724,29 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_statements_without_semicolons() {
+        let mut parser = make_parser(indoc! {"
+            let x = 5
+            if (x) { x } else { 1 }
+            x
+        "});
+        let program = parser.parse_program();
+        assert_eq!(parser.errors.len(), 0);
+        assert_eq!(
+            program.statements,
+            vec![
+                Statement::r#let("x", Expression::Int(5)),
+                Statement::Expression(Expression::r#if(
+                    Expression::identifier("x"),
+                    vec![Statement::Expression(Expression::identifier("x"))],
+                    Some(vec![Statement::Expression(Expression::Int(1))])
+                )),
+                Statement::Expression(Expression::identifier("x")),
+            ]
+        );
+    }
+
     #[test]
     fn test_multiple_statements() {
         let mut parser = make_parser(indoc! {"
@@ -745,7 +1074,7 @@ mod tests {
         assert_eq!(parser.current_token.token_type, TokenType::Let);
         assert_eq!(
             parser.peeking_token.token_type,
-            TokenType::Identifier(String::from("five"))
+            TokenType::Identifier("five")
         );
     }
 
@@ -775,6 +1104,259 @@ mod tests {
         assert_eq!(parser.peeking_token.token_type, TokenType::Semicolon);
     }
 
+    #[test]
+    fn test_type_annotations() {
+        let mut parser = make_parser(indoc! {"
+            let x: int = 1;
+            let f: fn(int, fn() -> null) -> bool = fn(a: int, b) -> bool { true };
+        "});
+        let program = parser.parse_program();
+
+        assert_eq!(parser.errors.len(), 0);
+        assert_eq!(
+            program.statements,
+            vec![
+                Statement::annotated_let("x", Some(TypeAnnotation::Int), Expression::Int(1)),
+                Statement::annotated_let(
+                    "f",
+                    Some(TypeAnnotation::function(
+                        vec![
+                            TypeAnnotation::Int,
+                            TypeAnnotation::function(vec![], TypeAnnotation::Null),
+                        ],
+                        TypeAnnotation::Bool,
+                    )),
+                    Expression::annotated_function(
+                        vec![
+                            Parameter::new("a", Some(TypeAnnotation::Int)),
+                            Parameter::from("b"),
+                        ],
+                        Some(TypeAnnotation::Bool),
+                        vec![Statement::Expression(Expression::Bool(true))],
+                    ),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imports_and_exports() {
+        let mut parser = make_parser(indoc! {r#"
+            import "lib/math.mk" as math;
+            import { min, max } from "lib/\"q\".mk"
+            export clamp, min;
+            math.abs(-1).sign
+        "#});
+        let program = parser.parse_program();
+
+        assert_eq!(parser.errors.len(), 0);
+        assert_eq!(
+            program.statements,
+            vec![
+                Statement::import("lib/math.mk", ImportBinding::Module("math".to_string())),
+                Statement::import(
+                    "lib/\"q\".mk",
+                    ImportBinding::Names(vec!["min".to_string(), "max".to_string()])
+                ),
+                Statement::export(vec!["clamp".to_string(), "min".to_string()]),
+                Statement::Expression(Expression::member(
+                    Expression::call(
+                        Expression::member(Expression::identifier("math"), "abs"),
+                        vec![Expression::prefix(
+                            Expression::Int(1),
+                            PrefixOperator::Negative
+                        )],
+                    ),
+                    "sign"
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let mut parser = make_parser(r#"let s: string = "a \"b\"\n"; s + "größe""#);
+        let program = parser.parse_program();
+
+        assert_eq!(parser.errors.len(), 0);
+        assert_eq!(
+            program.statements,
+            vec![
+                Statement::annotated_let(
+                    "s",
+                    Some(TypeAnnotation::String),
+                    Expression::String("a \"b\"\n".to_string())
+                ),
+                Statement::Expression(Expression::infix(
+                    Expression::identifier("s"),
+                    Expression::String("größe".to_string()),
+                    InfixOperator::Add
+                )),
+            ]
+        );
+        assert_eq!(
+            program.statements[0].to_string(),
+            r#"let s: string = "a \"b\"\n""#
+        );
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let mut parser = make_parser(r#"let s = "a ${b + 1}\n${f("}")}\${c}";"#);
+        let program = parser.parse_program();
+
+        assert_eq!(parser.errors.len(), 0);
+        assert_eq!(
+            program.statements,
+            vec![Statement::r#let(
+                "s",
+                Expression::Template(vec![
+                    TemplatePart::Text("a ".to_string()),
+                    TemplatePart::Expression(Expression::infix(
+                        Expression::identifier("b"),
+                        Expression::Int(1),
+                        InfixOperator::Add
+                    )),
+                    TemplatePart::Text("\n".to_string()),
+                    TemplatePart::Expression(Expression::call(
+                        Expression::identifier("f"),
+                        vec![Expression::String("}".to_string())]
+                    )),
+                    TemplatePart::Text("${c}".to_string()),
+                ])
+            )]
+        );
+        assert_eq!(
+            program.statements[0].to_string(),
+            r#"let s = "a ${(b + 1)}\n${f("}")}\${c}""#
+        );
+
+        let source_map = parser.source_map(&program);
+        let Statement::Let { value, .. } = &program.statements[0] else {
+            unreachable!()
+        };
+        let Expression::Template(parts) = value else {
+            unreachable!()
+        };
+        let TemplatePart::Expression(sum) = &parts[1] else {
+            unreachable!()
+        };
+        assert_eq!(
+            source_map.expression(sum).unwrap().to_string(),
+            "@1:14-1:18"
+        );
+    }
+
+    #[test]
+    fn test_string_interpolation_errors() {
+        let tests = vec![
+            (r#""a ${}""#, "@1:5] empty interpolation"),
+            (r#""a ${1 +}""#, "@1:9]"),
+            (
+                r#""a ${1 2}""#,
+                "@1:8] unexpected token integer 2 in interpolation",
+            ),
+            ("\"\n ${x\n +}\"", "@3:3]"),
+            (
+                "let s = \"${ 1 // c7\n}\";",
+                "@1:15] comments are not allowed in interpolations",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = make_parser(input);
+            parser.parse_program();
+
+            assert!(
+                parser.errors[0].to_string().contains(expected),
+                "parsing {}: {}",
+                input,
+                parser.errors[0]
+            );
+        }
+    }
+
+    #[test]
+    fn test_arrays_and_index() {
+        let mut parser = make_parser("[1, 2 * 3, []][0]; [f]");
+        let program = parser.parse_program();
+
+        assert_eq!(parser.errors.len(), 0);
+        assert_eq!(
+            program.statements,
+            vec![
+                Statement::Expression(Expression::index(
+                    Expression::Array(vec![
+                        Expression::Int(1),
+                        Expression::infix(
+                            Expression::Int(2),
+                            Expression::Int(3),
+                            InfixOperator::Mult
+                        ),
+                        Expression::Array(vec![]),
+                    ]),
+                    Expression::Int(0)
+                )),
+                Statement::Expression(Expression::Array(vec![Expression::identifier("f")])),
+            ]
+        );
+
+        for input in ["[1, 2", "a[1", "a[]", "[1,]"] {
+            let mut parser = make_parser(input);
+            parser.parse_program();
+            assert!(!parser.errors.is_empty(), "parsing {}", input);
+        }
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let tests = vec![
+            (
+                "import math;",
+                "expected a string or { after import, got identifier math",
+            ),
+            ("import \"m\" from x;", "expected as, got identifier from"),
+            ("import { } from \"m\";", "expected identifier, got }"),
+            ("import { a } \"m\";", "expected from, got string \"m\""),
+            ("import \"\\q\" as m;", "unknown escape \\q in string"),
+            ("export 1;", "expected identifier, got integer 1"),
+            ("a.1", "expected identifier, got integer 1"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = make_parser(input);
+            parser.parse_program();
+
+            assert!(
+                parser.errors[0].to_string().contains(expected),
+                "parsing {}: {}",
+                input,
+                parser.errors[0]
+            );
+        }
+    }
+
+    #[test]
+    fn test_type_annotation_errors() {
+        let tests = vec![
+            ("let x: float = 1;", "expected a type, got identifier float"),
+            ("let x: = 1;", "expected a type, got assign"),
+            ("fn(f: fn(int)) { f }", "unexpected token )"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = make_parser(input);
+            parser.parse_program();
+
+            assert!(
+                parser.errors[0].to_string().contains(expected),
+                "parsing {}: {}",
+                input,
+                parser.errors[0]
+            );
+        }
+    }
+
     #[test]
     fn test_if_expression() {
         let mut parser = make_parser(indoc! {"
@@ -916,6 +1498,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_prefixed_integer_literal_expression() {
+        let mut parser = make_parser(indoc! {"
+            0xff + 0b1_01;
+            let x = 1__0;
+        "});
+        let program = parser.parse_program();
+
+        assert_eq!(
+            program.statements[0],
+            Statement::expression(Expression::infix(
+                Expression::Int(255),
+                Expression::Int(5),
+                InfixOperator::Add
+            ))
+        );
+        assert_eq!(parser.errors.len(), 1);
+        assert_eq!(
+            parser.errors[0].to_string(),
+            "[ParserError:@2:9] malformed integer literal 1__0 "
+        );
+    }
+
     #[test]
     fn test_parse_null() {
         let mut parser = make_parser(indoc! {"
@@ -995,6 +1600,10 @@ mod tests {
             ("2 / (5 + 5)", "(2 / (5 + 5))"),
             ("-(5 + 5)", "(-(5 + 5))"),
             ("!(true == true)", "(!(true == true))"),
+            ("a * [1, 2][b * c] * d", "((a * [1, 2][(b * c)]) * d)"),
+            ("-a[0]", "(-a[0])"),
+            ("f(x)[1][2]", "f(x)[1][2]"),
+            ("add(a * b[2], b[1])", "add((a * b[2]), b[1])"),
         ];
 
         for test in tests {
@@ -1007,7 +1616,6 @@ mod tests {
 
     fn make_parser<'a>(input: &'a str) -> Parser<'a> {
         let lexer = Lexer::new(input);
-        let parser = Parser::new(lexer);
-        return parser;
+        Parser::new(lexer)
     }
 }
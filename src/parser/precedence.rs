@@ -1,6 +1,9 @@
 use crate::lexer::token::{Token, TokenType};
 
-#[derive(Debug, PartialEq, PartialOrd)]
+use super::ast::operator::InfixOperator;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
 pub enum Precedence {
     LOWEST = 1,
     EQUALS = 2,
@@ -9,9 +12,10 @@ pub enum Precedence {
     PRODUCT = 5,
     PREFIX = 6,
     CALL = 7,
+    INDEX = 8,
 }
 
-impl From<&Token> for Precedence {
+impl From<&Token<'_>> for Precedence {
     fn from(token: &Token) -> Self {
         match token.token_type {
             TokenType::Eq => Precedence::EQUALS,
@@ -23,15 +27,28 @@ impl From<&Token> for Precedence {
             TokenType::GT => Precedence::LESSGREATER,
             TokenType::LT => Precedence::LESSGREATER,
             TokenType::LParen => Precedence::CALL,
+            TokenType::Dot => Precedence::CALL,
+            TokenType::LBracket => Precedence::INDEX,
             TokenType::Modulo => Precedence::PRODUCT,
             _ => Precedence::LOWEST,
         }
     }
 }
 
+impl From<&InfixOperator> for Precedence {
+    fn from(operator: &InfixOperator) -> Self {
+        match operator {
+            InfixOperator::Equal | InfixOperator::NotEqual => Precedence::EQUALS,
+            InfixOperator::GreaterThan | InfixOperator::LessThan => Precedence::LESSGREATER,
+            InfixOperator::Add | InfixOperator::Sub => Precedence::SUM,
+            InfixOperator::Mult | InfixOperator::Div | InfixOperator::Modulo => Precedence::PRODUCT,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::parser::precedence::Precedence;
+    use crate::parser::{ast::operator::InfixOperator, precedence::Precedence};
 
     #[test]
     fn test_precedence() {
@@ -42,4 +59,18 @@ mod tests {
         assert!(Precedence::PRODUCT < Precedence::PREFIX);
         assert!(Precedence::PREFIX < Precedence::CALL);
     }
+
+    #[test]
+    fn test_infix_operator_precedence() {
+        assert_eq!(Precedence::from(&InfixOperator::Equal), Precedence::EQUALS);
+        assert_eq!(
+            Precedence::from(&InfixOperator::LessThan),
+            Precedence::LESSGREATER
+        );
+        assert_eq!(Precedence::from(&InfixOperator::Sub), Precedence::SUM);
+        assert_eq!(
+            Precedence::from(&InfixOperator::Modulo),
+            Precedence::PRODUCT
+        );
+    }
 }
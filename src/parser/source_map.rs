@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use crate::lexer::location::Span;
+
+use super::ast::{
+    expression::{Expression, Parameter, TemplatePart},
+    program::Program,
+    statement::Statement,
+};
+
+/// Span recorded by the parser as soon as a node is complete.
+#[derive(Debug, Clone)]
+pub(crate) enum RecordedSpan {
+    Parameter(Span),
+    Expression(Span),
+    Statement(Span),
+    Block(Span),
+}
+
+/// Source locations for the nodes of a parsed `Program`.
+///
+/// The AST itself carries no positions, so spans are looked up by node
+/// identity. The map borrows the `Program` it was built from, which keeps
+/// that program from being changed or dropped while the map is in use;
+/// nodes of any other program simply have no span.
+#[derive(Debug)]
+pub struct SourceMap<'a> {
+    program: &'a Program,
+    statements: HashMap<usize, Span>,
+    expressions: HashMap<usize, Span>,
+    parameters: HashMap<usize, Span>,
+    blocks: HashMap<usize, Vec<Span>>,
+}
+
+impl<'a> SourceMap<'a> {
+    /// Pairs the spans recorded while parsing with the nodes of `program`.
+    ///
+    /// The parser records a node when it finishes parsing it, so the spans
+    /// arrive in the same post-order this walk visits the tree in.
+    pub(crate) fn new(program: &'a Program, recorded: &[RecordedSpan]) -> Self {
+        let mut builder = Builder {
+            recorded: recorded.iter(),
+            source_map: SourceMap {
+                program,
+                statements: HashMap::new(),
+                expressions: HashMap::new(),
+                parameters: HashMap::new(),
+                blocks: HashMap::new(),
+            },
+        };
+
+        for statement in &program.statements {
+            builder.statement(statement);
+        }
+
+        builder.source_map
+    }
+
+    /// The program the spans belong to.
+    pub fn program(&self) -> &'a Program {
+        self.program
+    }
+
+    pub fn statement(&self, statement: &Statement) -> Option<&Span> {
+        self.statements
+            .get(&(statement as *const Statement as usize))
+    }
+
+    pub fn expression(&self, expression: &Expression) -> Option<&Span> {
+        self.expressions
+            .get(&(expression as *const Expression as usize))
+    }
+
+    /// Span of a function parameter, its annotation included.
+    pub fn parameter(&self, parameter: &Parameter) -> Option<&Span> {
+        self.parameters
+            .get(&(parameter as *const Parameter as usize))
+    }
+
+    /// Spans of the `{ ... }` blocks owned by an `if` (consequence, then
+    /// alternative) or a function literal (body), braces included.
+    pub fn blocks(&self, expression: &Expression) -> &[Span] {
+        self.blocks
+            .get(&(expression as *const Expression as usize))
+            .map(|blocks| blocks.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+struct Builder<'a, 'r> {
+    recorded: std::slice::Iter<'r, RecordedSpan>,
+    source_map: SourceMap<'a>,
+}
+
+impl Builder<'_, '_> {
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let { value, .. } => self.expression(value),
+            Statement::Return { value } => self.expression(value),
+            Statement::Expression(expression) => self.expression(expression),
+            Statement::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement);
+                }
+            }
+            Statement::Import { .. } | Statement::Export(_) => {}
+        }
+
+        if let Some(RecordedSpan::Statement(span)) = self.recorded.next() {
+            self.source_map
+                .statements
+                .insert(statement as *const Statement as usize, span.clone());
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        let mut blocks = vec![];
+
+        match expression {
+            Expression::Int(_)
+            | Expression::Bool(_)
+            | Expression::String(_)
+            | Expression::Identifier(_)
+            | Expression::Null => {}
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.expression(condition);
+                blocks.extend(self.block(consequence));
+                if let Some(alternative) = alternative {
+                    blocks.extend(self.block(alternative));
+                }
+            }
+            Expression::Function {
+                parameters, body, ..
+            } => {
+                for parameter in parameters.iter() {
+                    self.parameter(parameter);
+                }
+                blocks.extend(self.block(body));
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                self.expression(function);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expression::Prefix { rhs, .. } => self.expression(rhs),
+            Expression::Infix { lhs, rhs, .. } => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            Expression::Member { object, .. } => self.expression(object),
+            Expression::Array(elements) => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+            Expression::Index { left, index } => {
+                self.expression(left);
+                self.expression(index);
+            }
+            Expression::Template(parts) => {
+                for part in parts {
+                    if let TemplatePart::Expression(expression) = part {
+                        self.expression(expression);
+                    }
+                }
+            }
+        }
+
+        let key = expression as *const Expression as usize;
+
+        if !blocks.is_empty() {
+            self.source_map.blocks.insert(key, blocks);
+        }
+
+        if let Some(RecordedSpan::Expression(span)) = self.recorded.next() {
+            self.source_map.expressions.insert(key, span.clone());
+        }
+    }
+
+    fn parameter(&mut self, parameter: &Parameter) {
+        if let Some(RecordedSpan::Parameter(span)) = self.recorded.next() {
+            self.source_map
+                .parameters
+                .insert(parameter as *const Parameter as usize, span.clone());
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) -> Option<Span> {
+        for statement in statements {
+            self.statement(statement);
+        }
+
+        match self.recorded.next() {
+            Some(RecordedSpan::Block(span)) => Some(span.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        lexer::{
+            lexer::Lexer,
+            location::{Location, Span},
+        },
+        parser::{
+            ast::{expression::Expression, statement::Statement},
+            parser::Parser,
+        },
+    };
+
+    fn span(start: (usize, usize), end: (usize, usize)) -> Span {
+        Span::new(Location::new(start.0, start.1), Location::new(end.0, end.1))
+    }
+
+    #[test]
+    fn test_statement_and_expression_spans() {
+        let mut parser = Parser::new(Lexer::new(indoc! {"
+            let x = (1 + 2) * y;
+            add(x,
+                -3)
+        "}));
+        let program = parser.parse_program();
+        let source_map = parser.source_map(&program);
+
+        assert_eq!(
+            source_map.statement(&program.statements[0]),
+            Some(&span((1, 1), (1, 20)))
+        );
+        assert_eq!(
+            source_map.statement(&program.statements[1]),
+            Some(&span((2, 1), (3, 7)))
+        );
+
+        let Statement::Let { value, .. } = &program.statements[0] else {
+            panic!("expected a let statement");
+        };
+        assert_eq!(source_map.expression(value), Some(&span((1, 9), (1, 19))));
+        let Expression::Infix { lhs, rhs, .. } = value else {
+            panic!("expected an infix expression");
+        };
+        assert_eq!(source_map.expression(lhs), Some(&span((1, 10), (1, 14))));
+        assert_eq!(source_map.expression(rhs), Some(&span((1, 19), (1, 19))));
+
+        let Statement::Expression(Expression::Call { arguments, .. }) = &program.statements[1]
+        else {
+            panic!("expected a call");
+        };
+        assert_eq!(
+            source_map.expression(&arguments[1]),
+            Some(&span((3, 5), (3, 6)))
+        );
+    }
+
+    #[test]
+    fn test_spans_end_with_their_last_token() {
+        let mut parser = Parser::new(Lexer::new(indoc! {r#"
+            let name = "monkey";
+            limit != 100
+        "#}));
+        let program = parser.parse_program();
+        let source_map = parser.source_map(&program);
+
+        let Statement::Let { value, .. } = &program.statements[0] else {
+            panic!("expected a let statement");
+        };
+        assert_eq!(source_map.expression(value), Some(&span((1, 12), (1, 19))));
+
+        let Statement::Expression(comparison) = &program.statements[1] else {
+            panic!("expected an expression statement");
+        };
+        assert_eq!(
+            source_map.statement(&program.statements[1]),
+            Some(&span((2, 1), (2, 12)))
+        );
+        let Expression::Infix { rhs, .. } = comparison else {
+            panic!("expected an infix expression");
+        };
+        assert_eq!(source_map.expression(rhs), Some(&span((2, 10), (2, 12))));
+    }
+
+    #[test]
+    fn test_block_spans() {
+        let mut parser = Parser::new(Lexer::new(indoc! {"
+            if (x) {
+                let f = fn(a) { a };
+                f(1);
+            } else {}
+        "}));
+        let program = parser.parse_program();
+        let source_map = parser.source_map(&program);
+
+        let Statement::Expression(if_expression) = &program.statements[0] else {
+            panic!("expected an expression statement");
+        };
+        assert_eq!(
+            source_map.blocks(if_expression),
+            &[span((1, 8), (4, 1)), span((4, 8), (4, 9))]
+        );
+        assert_eq!(
+            source_map.expression(if_expression),
+            Some(&span((1, 1), (4, 9)))
+        );
+
+        let Expression::If { consequence, .. } = if_expression else {
+            panic!("expected an if expression");
+        };
+        assert_eq!(
+            source_map.statement(&consequence[1]),
+            Some(&span((3, 5), (3, 9)))
+        );
+        let Statement::Let {
+            value: function, ..
+        } = &consequence[0]
+        else {
+            panic!("expected a let statement");
+        };
+        assert_eq!(source_map.blocks(function), &[span((2, 19), (2, 23))]);
+        let Expression::Function { parameters, .. } = function else {
+            panic!("expected a function");
+        };
+        assert_eq!(
+            source_map.parameter(&parameters[0]),
+            Some(&span((2, 16), (2, 16)))
+        );
+    }
+
+    #[test]
+    fn test_spans_skip_statements_with_errors() {
+        let mut parser = Parser::new(Lexer::new(indoc! {"
+            let x = fn(a) { let b = a; b + };
+            let y = 2;
+        "}));
+        let program = parser.parse_program();
+        let source_map = parser.source_map(&program);
+
+        assert_eq!(parser.errors.len(), 1);
+        assert_eq!(
+            source_map.statement(&program.statements[0]),
+            Some(&span((2, 1), (2, 10)))
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    io::{ErrorKind, Read},
+};
+
+use crate::lexer::{lexer::Lexer, location::Location, token::TokenType};
+
+use super::{ast::statement::Statement, parser::Parser};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub struct StreamError {
+    msg: String,
+}
+
+impl StreamError {
+    fn new(msg: impl Into<String>) -> Self {
+        StreamError { msg: msg.into() }
+    }
+}
+
+impl Display for StreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+/// Parses top-level statements from a reader as soon as they are complete,
+/// so a consumer can start on the first statements before the rest of the
+/// input has arrived.
+///
+/// Input is read in fixed-size chunks and only the text of the statements
+/// not yet parsed is kept. A statement is complete once the `;` ending it
+/// outside any parentheses or braces has been read, once a `let`, `return`,
+/// `import` or `export` outside them starts the next one, or at the end of
+/// input. Each chunk is scanned once: the tokens already scanned and the
+/// nesting depth are kept between reads. Parser errors are yielded as they
+/// are found and parsing goes on.
+pub struct StatementStream<R: Read> {
+    reader: R,
+    chunk_size: usize,
+    /// Bytes read but not decoded yet, the start of a split UTF-8 char.
+    bytes: Vec<u8>,
+    /// Decoded source that has not been parsed yet.
+    buffer: String,
+    /// Location of the last char before `buffer`.
+    start: Location,
+    /// Length of the start of `buffer` already scanned for the end of the
+    /// statement, which always ends after a complete token.
+    scanned: usize,
+    /// Location of the last char before the unscanned rest of `buffer`.
+    scan_location: Location,
+    /// Parentheses and braces open at `scanned`.
+    depth: usize,
+    parsed: VecDeque<Result<Statement, StreamError>>,
+    eof: bool,
+    /// Read error reported once the input before it has been parsed.
+    error: Option<StreamError>,
+}
+
+impl<R: Read> StatementStream<R> {
+    pub fn new(reader: R) -> Self {
+        StatementStream::with_chunk_size(reader, CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> Self {
+        StatementStream {
+            reader,
+            chunk_size: chunk_size.max(1),
+            bytes: vec![],
+            buffer: String::new(),
+            start: Location::new(1, 0),
+            scanned: 0,
+            scan_location: Location::new(1, 0),
+            depth: 0,
+            parsed: VecDeque::new(),
+            eof: false,
+            error: None,
+        }
+    }
+
+    /// Byte length and location of the end of the first complete statement
+    /// in the buffer, if any. Only the text after `scanned` is lexed.
+    fn statement_end(&mut self) -> Option<(usize, Location)> {
+        let base = self.scanned;
+        let mut tokens =
+            Lexer::continuing_from(&self.buffer[base..], self.scan_location.clone()).peekable();
+        let mut scanned = base;
+        let mut depth = self.depth;
+
+        while let Some(token) = tokens.next() {
+            match token.token_type {
+                TokenType::EOF => break,
+                TokenType::Semicolon if depth == 0 => {
+                    return Some((base + token.offsets.end, token.location));
+                }
+                _ => {}
+            }
+
+            // the last token may go on in the next chunk, as `let` does in
+            // `letter`
+            let complete = tokens
+                .peek()
+                .is_some_and(|next| next.token_type != TokenType::EOF);
+            if !complete && !self.eof {
+                break;
+            }
+
+            match token.token_type {
+                TokenType::Let | TokenType::Return | TokenType::Import | TokenType::Export
+                    if depth == 0 && scanned > 0 =>
+                {
+                    let start = base + token.offsets.start;
+                    let location = self.scan_location.after(&self.buffer[base..start]);
+                    return Some((start, location));
+                }
+                TokenType::LParen | TokenType::LBrace => depth += 1,
+                TokenType::RParen | TokenType::RBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            scanned = base + token.offsets.end;
+        }
+
+        self.scan_location = self.scan_location.after(&self.buffer[base..scanned]);
+        self.scanned = scanned;
+        self.depth = depth;
+
+        None
+    }
+
+    /// Parses the first `end` bytes of the buffer and queues the results.
+    fn parse(&mut self, end: usize, end_location: Location) {
+        let mut parser = Parser::new(Lexer::continuing_from(
+            &self.buffer[..end],
+            self.start.clone(),
+        ));
+        let program = parser.parse_program();
+
+        self.parsed.extend(program.statements.into_iter().map(Ok));
+        self.parsed.extend(
+            parser
+                .errors
+                .into_iter()
+                .map(|error| Err(StreamError::new(error.to_string()))),
+        );
+
+        self.buffer.drain(..end);
+        self.start = end_location.clone();
+        self.scanned = 0;
+        self.scan_location = end_location;
+        self.depth = 0;
+    }
+
+    /// Reads the next chunk into the buffer, setting `eof` at the end of the
+    /// input or on the first error.
+    fn fill(&mut self) {
+        let mut chunk = vec![0; self.chunk_size];
+
+        let read = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.fail(format!("failed to read input: {}", err));
+                    return;
+                }
+            }
+        };
+
+        if read == 0 {
+            self.eof = true;
+            if !self.bytes.is_empty() {
+                self.fail("input ends in the middle of a UTF-8 char");
+            }
+            return;
+        }
+
+        self.bytes.extend_from_slice(&chunk[..read]);
+
+        let valid = match std::str::from_utf8(&self.bytes) {
+            Ok(text) => text.len(),
+            // the rest of a split char arrives with the next chunk
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => {
+                self.fail("input is not valid UTF-8");
+                err.valid_up_to()
+            }
+        };
+
+        let text = std::str::from_utf8(&self.bytes[..valid]).unwrap_or_default();
+        self.buffer.push_str(text);
+        self.bytes.drain(..valid);
+    }
+
+    fn fail(&mut self, msg: impl Into<String>) {
+        self.eof = true;
+        self.error = Some(StreamError::new(msg));
+    }
+}
+
+impl<R: Read> Iterator for StatementStream<R> {
+    type Item = Result<Statement, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(parsed) = self.parsed.pop_front() {
+                return Some(parsed);
+            }
+
+            if let Some((end, end_location)) = self.statement_end() {
+                self.parse(end, end_location);
+                continue;
+            }
+
+            if self.eof {
+                if self.buffer.is_empty() {
+                    return self.error.take().map(Err);
+                }
+                let end_location = self.start.clone();
+                self.parse(self.buffer.len(), end_location);
+                continue;
+            }
+
+            self.fill();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use indoc::indoc;
+
+    use crate::{
+        lexer::lexer::Lexer,
+        parser::{
+            ast::{expression::Expression, statement::Statement},
+            parser::Parser,
+        },
+    };
+
+    use super::{StatementStream, StreamError};
+
+    const SOURCE: &str = indoc! {"
+        // a comment; with a semicolon
+        let add = fn(a, b) {
+            let sum = a + b;
+            sum
+        };
+        if (add(1, 2) > 2) { 10 } else { 20 }
+        let c = 3; // café; naïve
+        add(1,
+            2) * 3
+    "};
+
+    fn collect(reader: impl Read, chunk_size: usize) -> Vec<Result<Statement, StreamError>> {
+        StatementStream::with_chunk_size(reader, chunk_size).collect()
+    }
+
+    #[test]
+    fn test_stream_matches_parser() {
+        let mut parser = Parser::new(Lexer::new(SOURCE));
+        let expected: Vec<Result<Statement, StreamError>> = parser
+            .parse_program()
+            .statements
+            .into_iter()
+            .map(Ok)
+            .collect();
+
+        for chunk_size in [1, 2, 3, 7, 64, 8192] {
+            assert_eq!(
+                collect(SOURCE.as_bytes(), chunk_size),
+                expected,
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_stream_without_semicolons_matches_parser() {
+        let source = indoc! {"
+            let a = 1
+            let letter = fn(x) {
+                let y = x
+                y
+            }
+            return letter(a)
+            let b = ; let c = [1,
+                2]
+        "};
+        let mut parser = Parser::new(Lexer::new(source));
+        let program = parser.parse_program();
+        let mut expected: Vec<Result<Statement, StreamError>> =
+            program.statements.into_iter().map(Ok).collect();
+        expected.insert(3, Err(StreamError::new(parser.errors[0].to_string())));
+
+        for chunk_size in [1, 2, 3, 7, 8192] {
+            assert_eq!(
+                collect(source.as_bytes(), chunk_size),
+                expected,
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_stream_error_locations() {
+        let input = "let a = 1;\nlet b = ;\nlet c = 3;\n  let = 4;";
+        let results = collect(input.as_bytes(), 4);
+
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1],
+            Err(StreamError::new(
+                "[ParserError:@2:9] Expected prefix expression, got Semicolon "
+            ))
+        );
+        assert!(results[2].is_ok());
+        assert_eq!(
+            results[3],
+            Err(StreamError::new(
+                "[ParserError:@4:7] expected identifier, got assign "
+            ))
+        );
+    }
+
+    #[test]
+    fn test_stream_rejects_invalid_utf8() {
+        let results = collect(&b"let a = 1; \xff"[..], 4);
+
+        assert_eq!(
+            results,
+            vec![
+                Ok(Statement::r#let("a", Expression::Int(1))),
+                Err(StreamError::new("input is not valid UTF-8")),
+            ]
+        );
+    }
+
+    /// Hands out one chunk per read and counts how many were read.
+    struct Chunks<'a> {
+        chunks: &'a [&'a str],
+        read: &'a std::cell::Cell<usize>,
+    }
+
+    impl Read for Chunks<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some(chunk) = self.chunks.get(self.read.get()) else {
+                return Ok(0);
+            };
+            self.read.set(self.read.get() + 1);
+            buf[..chunk.len()].copy_from_slice(chunk.as_bytes());
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_stream_yields_before_input_ends() {
+        let read = std::cell::Cell::new(0);
+        let chunks = ["let a = ", "1; let b", " = 2;", " a + b"];
+        let mut stream = StatementStream::new(Chunks {
+            chunks: &chunks,
+            read: &read,
+        });
+
+        assert!(stream.next().unwrap().is_ok());
+        assert_eq!(read.get(), 2);
+        assert!(stream.next().unwrap().is_ok());
+        assert_eq!(read.get(), 3);
+        assert!(stream.next().unwrap().is_ok());
+        assert!(stream.next().is_none());
+        assert_eq!(read.get(), 4);
+    }
+
+    #[test]
+    fn test_next_let_ends_a_statement() {
+        let read = std::cell::Cell::new(0);
+        let chunks = ["let a = 1\nlet", " b = a\n", "b * 2"];
+        let mut stream = StatementStream::new(Chunks {
+            chunks: &chunks,
+            read: &read,
+        });
+
+        assert_eq!(
+            stream.next(),
+            Some(Ok(Statement::r#let("a", Expression::Int(1))))
+        );
+        assert_eq!(read.get(), 2);
+        assert_eq!(stream.by_ref().count(), 2);
+    }
+}
@@ -0,0 +1,34 @@
+// The prelude, evaluated before every script and in the repl unless they
+// run with --no-prelude. A `let` of the same name replaces any of these
+// for the code after it, but not for the other definitions here.
+// check: allow-file(unused-variable)
+
+let identity = fn(x) { x };
+let constant = fn(x) { fn(_) { x } };
+let compose = fn(f, g) { fn(x) { f(g(x)) } };
+let flip = fn(f) { fn(a, b) { f(b, a) } };
+
+let min = fn(a: int, b: int) -> int { if (a < b) { a } else { b } };
+let max = fn(a: int, b: int) -> int { if (a > b) { a } else { b } };
+let abs = fn(x: int) -> int { if (x < 0) { -x } else { x } };
+
+// Arrays come with native builtins like len, map, filter and reduce, which
+// all take the array first. These build on them.
+let is_empty = fn(array) { len(array) == 0 };
+let each = fn(array, f) { reduce(array, null, fn(_, x) { f(x); null }) };
+let sum = fn(array) { reduce(array, 0, fn(a, b) { a + b }) };
+let take = fn(array, n: int) {
+    reduce(array, [], fn(taken, x) { if (len(taken) < n) { push(taken, x) } else { taken } })
+};
+
+// The integers from `from` up to, but not including, `to`. Halving keeps
+// the recursion shallow for long ranges.
+let range = fn(from: int, to: int) {
+    if (to - from > 1) {
+        let middle = from + (to - from) / 2;
+        flatten([range(from, middle), range(middle, to)])
+    } else {
+        if (to - from == 1) { [from] } else { [] }
+    }
+};
+let times = fn(n: int, f) { map(range(0, n), f) };
@@ -0,0 +1,148 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    evaluator::{environment::Environment, evaluator::Evaluator},
+    lexer::lexer::Lexer,
+    parser::{ast::program::Program, parser::Parser},
+    typechecker::typechecker::TypeChecker,
+};
+
+/// Utilities written in Monkey, embedded in the binary.
+pub const PRELUDE: &str = include_str!("prelude.mk");
+
+fn program() -> Program {
+    let mut parser = Parser::new(Lexer::new(PRELUDE));
+    let program = parser.parse_program();
+    assert!(parser.errors.is_empty(), "the prelude does not parse");
+    program
+}
+
+/// Binds the definitions of the prelude in `environment`. They are evaluated
+/// in an environment of their own, so rebinding one of their names does not
+/// change what the others refer to.
+pub fn load(environment: &Rc<RefCell<Environment>>) {
+    let prelude = Environment::new();
+    Evaluator::new()
+        .eval(&program(), Rc::clone(&prelude))
+        .expect("the prelude evaluates");
+
+    let mut environment = environment.borrow_mut();
+    for (name, value) in prelude.borrow().bindings() {
+        environment.set(name, value.clone());
+    }
+}
+
+/// Adds the types of the prelude to `checker`.
+pub fn check(checker: &mut TypeChecker) {
+    let _ = checker.check(&program(), None);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use indoc::indoc;
+
+    use crate::{
+        evaluator::{environment::Environment, evaluator::Evaluator, object::Object},
+        lexer::lexer::Lexer,
+        linter::linter::Linter,
+        parser::parser::Parser,
+        typechecker::{typechecker::TypeChecker, types::Type},
+    };
+
+    use super::{check, load, program, PRELUDE};
+
+    fn evaluate(input: &str) -> Result<Object, String> {
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        let environment = Environment::new();
+        load(&environment);
+
+        Evaluator::new()
+            .eval(&program, Rc::clone(&environment))
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn test_functions() {
+        let tests = vec![
+            ("identity(5)", 5),
+            ("constant(3)(4)", 3),
+            ("compose(fn(x) { x + 1 }, fn(x) { x * 2 })(5)", 11),
+            ("flip(fn(a, b) { a - b })(1, 10)", 9),
+            ("min(3, -2) + max(3, -2) + abs(-7)", 8),
+            (
+                "len(range(0, 0)) + len(range(3, 0)) + len(range(0, 1000))",
+                1000,
+            ),
+            ("sum(range(1, 5)) + sum([])", 10),
+            ("sum(map(range(1, 4), fn(x) { x * x }))", 14),
+            ("sum(take(range(0, 100), 3)) + len(times(2, identity))", 5),
+            ("len(take([1], 5))", 1),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                evaluate(input),
+                Ok(Object::Integer(expected)),
+                "evaluating {}",
+                input
+            );
+        }
+
+        assert_eq!(evaluate("is_empty(range(3, 3))"), Ok(Object::Boolean(true)));
+        assert_eq!(evaluate("each([1, 2], identity)"), Ok(Object::Null));
+    }
+
+    #[test]
+    fn test_definitions_can_be_replaced() {
+        let input = indoc! {"
+            let reduce = fn(array, initial, f) { 0 };
+            let abs = fn(x) { 42 };
+            sum(range(0, 4)) + reduce(1, 2, 3) + abs(-1)
+        "};
+
+        // `sum` still uses the builtin `reduce`
+        assert_eq!(evaluate(input), Ok(Object::Integer(48)));
+    }
+
+    #[test]
+    fn test_errors_in_callbacks() {
+        assert_eq!(
+            evaluate("times(2, fn(x) { x + true })"),
+            Err("invalid operation: 0 + true".to_string())
+        );
+    }
+
+    #[test]
+    fn test_prelude_passes_the_linter() {
+        assert_eq!(Linter::new().check(PRELUDE).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_types() {
+        assert!(TypeChecker::new().check(&program(), None).is_ok());
+
+        let mut checker = TypeChecker::new();
+        check(&mut checker);
+
+        let mut type_of = |input: &str| {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+            checker
+                .check(&program, None)
+                .map_err(|errors| errors[0].to_string())
+        };
+
+        assert_eq!(type_of("abs(identity(-1))"), Ok(Type::Int));
+        assert_eq!(
+            type_of("compose(abs, fn(b) { if (b) { 1 } else { 2 } })(true)"),
+            Ok(Type::Int)
+        );
+        assert!(type_of("min(1, true)").is_err());
+        assert_eq!(
+            type_of("take(times(3, fn(x) { x > 1 }), 2)"),
+            Ok(Type::array(Type::Bool))
+        );
+        assert!(type_of("sum([true])").is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod allocator;
+#[allow(clippy::module_inception)]
+pub mod profiler;
@@ -0,0 +1,284 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    evaluator::{evaluator::EvaluationError, object::Object, observer::EvalObserver},
+    parser::ast::{expression::Expression, statement::Statement},
+};
+
+use super::allocator;
+
+/// What the profiler recorded about every call to one function.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FunctionProfile {
+    pub calls: usize,
+    /// Time from the start to the end of the calls. Recursive calls are
+    /// only counted once, in the outermost one.
+    pub inclusive: Duration,
+    /// Time spent in the function itself, not in the functions it called.
+    pub exclusive: Duration,
+    /// Allocations made by the function itself, not by the functions it
+    /// called.
+    pub allocations: usize,
+}
+
+/// A call in progress.
+struct Frame {
+    name: String,
+    start: Instant,
+    /// Inclusive time of the calls made from this one so far.
+    children: Duration,
+    allocations: usize,
+    child_allocations: usize,
+}
+
+/// Records time and allocations per function, and the stacks they were
+/// spent in. Functions are named after the first `let` binding them, or the
+/// expression they were called through.
+#[derive(Default)]
+pub struct Profiler {
+    functions: HashMap<String, FunctionProfile>,
+    /// Exclusive time per call stack, with the frames joined by `;`.
+    stacks: HashMap<String, Duration>,
+    names: HashMap<*const Statement, String>,
+    frames: Vec<Frame>,
+    /// Allocations made by the profiler itself, left out of the counts.
+    overhead: usize,
+}
+
+impl Profiler {
+    /// Also starts the allocation counting the profile relies on.
+    pub fn new() -> Self {
+        allocator::start_counting();
+        Profiler::default()
+    }
+
+    pub fn functions(&self) -> &HashMap<String, FunctionProfile> {
+        &self.functions
+    }
+
+    /// The functions with the most exclusive time first, one per line.
+    pub fn table(&self) -> String {
+        let mut functions: Vec<(&String, &FunctionProfile)> = self.functions.iter().collect();
+        functions.sort_by(|(a_name, a), (b_name, b)| {
+            b.exclusive.cmp(&a.exclusive).then(a_name.cmp(b_name))
+        });
+
+        let width = functions
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .chain(["function".len()])
+            .max()
+            .unwrap_or_default();
+
+        let mut table = format!(
+            "{:<width$} {:>8} {:>12} {:>12} {:>12}\n",
+            "function", "calls", "inclusive", "exclusive", "allocations"
+        );
+        for (name, profile) in functions {
+            let _ = writeln!(
+                table,
+                "{:<width$} {:>8} {:>12} {:>12} {:>12}",
+                name,
+                profile.calls,
+                format!("{:.3?}", profile.inclusive),
+                format!("{:.3?}", profile.exclusive),
+                profile.allocations
+            );
+        }
+
+        table
+    }
+
+    /// The exclusive time of every call stack in nanoseconds, in the folded
+    /// format read by flamegraph tools: `outer;inner 1234`.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<(&String, &Duration)> = self.stacks.iter().collect();
+        stacks.sort();
+
+        stacks
+            .into_iter()
+            .map(|(stack, time)| format!("{} {}\n", stack, time.as_nanos()))
+            .collect()
+    }
+
+    fn allocations(&self) -> usize {
+        allocator::allocations() - self.overhead
+    }
+
+    fn name(&self, callee: &Expression, function: &Object) -> String {
+        let named = match function {
+            Object::Function { body, .. } => self.names.get(&body.as_ptr()),
+            _ => None,
+        };
+
+        match (named, callee) {
+            (Some(name), _) => name.clone(),
+            (None, Expression::Identifier(name)) => name.clone(),
+            (None, _) => format!("<{}>", function.summary()),
+        }
+    }
+}
+
+impl EvalObserver for Profiler {
+    fn on_call(&mut self, callee: &Expression, function: &Object, _arguments: &[Object]) {
+        let before = allocator::allocations();
+
+        let name = self.name(callee, function);
+        self.frames.push(Frame {
+            name,
+            start: Instant::now(),
+            children: Duration::ZERO,
+            allocations: 0,
+            child_allocations: 0,
+        });
+
+        self.overhead += allocator::allocations() - before;
+        let allocations = self.allocations();
+        if let Some(frame) = self.frames.last_mut() {
+            frame.allocations = allocations;
+        }
+    }
+
+    fn on_return(
+        &mut self,
+        _callee: &Expression,
+        _function: &Object,
+        _result: Result<&Object, &EvaluationError>,
+    ) {
+        let allocations = self.allocations();
+        let before = allocator::allocations();
+
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        let inclusive = frame.start.elapsed();
+        let exclusive = inclusive.saturating_sub(frame.children);
+        let allocations = allocations - frame.allocations;
+
+        if let Some(parent) = self.frames.last_mut() {
+            parent.children += inclusive;
+            parent.child_allocations += allocations;
+        }
+
+        let recursive = self.frames.iter().any(|outer| outer.name == frame.name);
+        let profile = self.functions.entry(frame.name.clone()).or_default();
+        profile.calls += 1;
+        profile.exclusive += exclusive;
+        profile.allocations += allocations - frame.child_allocations;
+        if !recursive {
+            profile.inclusive += inclusive;
+        }
+
+        let mut stack: Vec<&str> = self.frames.iter().map(|f| f.name.as_str()).collect();
+        stack.push(&frame.name);
+        *self.stacks.entry(stack.join(";")).or_default() += exclusive;
+
+        self.overhead += allocator::allocations() - before;
+    }
+
+    fn on_bind(&mut self, name: &str, value: &Object) {
+        let before = allocator::allocations();
+
+        if let Object::Function { body, .. } = value {
+            self.names
+                .entry(body.as_ptr())
+                .or_insert_with(|| name.to_string());
+        }
+
+        self.overhead += allocator::allocations() - before;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use indoc::indoc;
+
+    use crate::{
+        evaluator::{environment::Environment, evaluator::Evaluator},
+        lexer::lexer::Lexer,
+        parser::parser::Parser,
+    };
+
+    use super::Profiler;
+
+    fn profile(input: &str) -> Profiler {
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        let profiler = Rc::new(RefCell::new(Profiler::new()));
+
+        let mut evaluator = Evaluator::new();
+        evaluator.attach(Box::new(Rc::clone(&profiler)));
+        evaluator.eval(&program, Environment::new()).unwrap();
+        drop(evaluator);
+
+        Rc::into_inner(profiler).unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_calls_and_stacks() {
+        let profiler = profile(indoc! {"
+            let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+            let twice = fn(f, x) { f(f(x)) };
+            let square = fn(x) { x * x };
+            let alias = square;
+            twice(alias, fib(3)) + twice(fn(x) { x + 1 }, 0) + fn() { 1 }()
+        "});
+
+        let mut calls: Vec<(&str, usize)> = profiler
+            .functions()
+            .iter()
+            .map(|(name, profile)| (name.as_str(), profile.calls))
+            .collect();
+        calls.sort();
+        assert_eq!(
+            calls,
+            vec![
+                ("<fn()>", 1),
+                ("f", 2),
+                ("fib", 5),
+                ("square", 2),
+                ("twice", 2)
+            ]
+        );
+
+        let folded = profiler.folded();
+        let stacks: Vec<&str> = folded
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            stacks,
+            vec![
+                "<fn()>",
+                "fib",
+                "fib;fib",
+                "fib;fib;fib",
+                "twice",
+                "twice;f",
+                "twice;square"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recursion_is_counted_once() {
+        let profiler = profile("let f = fn(n) { if (n > 0) { f(n - 1) } else { 0 } }; f(20)");
+
+        let f = &profiler.functions()["f"];
+        assert_eq!(f.calls, 21);
+        assert_eq!(f.inclusive, f.exclusive);
+
+        let table = profiler.table();
+        assert!(table.starts_with("function    calls    inclusive    exclusive  allocations\n"));
+        assert!(table
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("f              21 "));
+    }
+}
@@ -1,50 +1,81 @@
 use std::{
+    cell::RefCell,
     io::{self, Stdin, Write},
+    path::PathBuf,
     rc::Rc,
 };
 
 use crate::{
-    evaluator::{
-        environment::Environment,
-        evaluator::{EvaluationError, Evaluator},
-        object::Object,
-    },
-    lexer::lexer::Lexer,
+    debugger::debugger::Debugger,
+    evaluator::{environment::Environment, evaluator::Evaluator, module::FsLoader, tracer::Tracer},
+    lexer::{lexer::Lexer, location::Location},
     parser::{
-        ast::{node::Node, program::Program},
-        parser::Parser,
+        ast::program::Program,
+        parser::{Parser, ParserError},
     },
+    prelude::prelude,
+    typechecker::typechecker::TypeChecker,
 };
 
 pub struct Repl {
     stdin: Stdin,
+    /// Whether to start with the definitions of the prelude.
+    prelude: bool,
 }
 
-const PROMPT: &'static str = ">> ";
+const PROMPT: &str = ">> ";
+const TYPE_COMMAND: &str = ":type";
+const DEBUG_COMMAND: &str = ":debug";
+const TRACE_COMMAND: &str = ":trace";
 
 impl Repl {
-    pub fn new(stdin: Stdin) -> Self {
-        Repl { stdin }
+    pub fn new(stdin: Stdin, prelude: bool) -> Self {
+        Repl { stdin, prelude }
     }
 
+    /// Reads and evaluates lines until an empty one. Bindings carry over
+    /// from line to line, and `:type <expr>` prints the inferred type of an
+    /// expression instead of evaluating it. `:debug <code>` evaluates the
+    /// code in the step debugger, and `:trace on` prints every call, return
+    /// and binding until `:trace off`. Imports are found in the working
+    /// directory.
     pub fn start(&self) {
+        let environment = Environment::new();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_loader(Box::new(FsLoader::new(vec![PathBuf::new()])));
+        let mut checker = TypeChecker::new();
+        if self.prelude {
+            prelude::load(&environment);
+            prelude::check(&mut checker);
+        }
+        evaluator.set_globals(&environment.borrow());
+        let mut tracing = false;
+
         let mut line = String::new();
         self.read_input(&mut line, &self.stdin);
 
         while !line.trim().is_empty() {
-            let lexer = Lexer::new(&line);
-            let mut parser = Parser::new(lexer);
-            let program = parser.parse_program();
-
-            if parser.errors.len() == 0 {
-                match self.evaluate_program(program) {
-                    Ok(object) => println!("{}", object),
-                    Err(err) => println!("{}", err),
-                }
+            if let Some((source, start)) = command_input(&line, TYPE_COMMAND) {
+                self.print_type(source, start, &checker);
+            } else if let Some((source, start)) = command_input(&line, DEBUG_COMMAND) {
+                self.debug(&line, source, start, &mut evaluator, &environment);
+            } else if let Some(argument) = line.trim().strip_prefix(TRACE_COMMAND) {
+                self.trace(argument.trim(), &mut evaluator, &mut tracing);
             } else {
-                println!("Woops! parser got {} errors!", parser.errors.len());
-                for error in parser.errors {
-                    println!("{}", error);
+                let lexer = Lexer::new(&line);
+                let mut parser = Parser::new(lexer);
+                let program = parser.parse_program();
+
+                if parser.errors.is_empty() {
+                    // only keeps the types of new bindings for `:type`
+                    let _ = checker.check(&program, None);
+
+                    match evaluator.eval(&program, Rc::clone(&environment)) {
+                        Ok(object) => println!("{}", object),
+                        Err(err) => println!("{}", err),
+                    }
+                } else {
+                    self.print_parser_errors(parser.errors);
                 }
             }
 
@@ -52,11 +83,79 @@ impl Repl {
         }
     }
 
-    fn evaluate_program(&self, program: Program) -> Result<Object, EvaluationError> {
-        let environment = Environment::new();
-        let mut evaluator = Evaluator::new();
+    fn print_type(&self, source: &str, start: Location, checker: &TypeChecker) {
+        let mut parser = Parser::new(Lexer::continuing_from(source, start));
+        let program = parser.parse_program();
+
+        if !parser.errors.is_empty() {
+            self.print_parser_errors(parser.errors);
+            return;
+        }
+
+        // checking must not bind anything, as the input is not evaluated
+        let source_map = parser.source_map(&program);
+        match checker.clone().check(&program, Some(&source_map)) {
+            Ok(ty) => println!("{}", ty),
+            Err(errors) => {
+                for error in errors {
+                    println!("{}", error);
+                }
+            }
+        }
+    }
+
+    fn debug(
+        &self,
+        line: &str,
+        source: &str,
+        start: Location,
+        evaluator: &mut Evaluator,
+        environment: &Rc<RefCell<Environment>>,
+    ) {
+        let mut parser = Parser::new(Lexer::continuing_from(source, start));
+        let program = parser.parse_program();
+
+        if !parser.errors.is_empty() {
+            self.print_parser_errors(parser.errors);
+            return;
+        }
+
+        // the whole line is shown, so that columns line up with the spans
+        let source_map = parser.source_map(&program);
+        let debugger = Debugger::new(self.stdin.lock(), io::stdout(), line, &source_map, []);
 
-        evaluator.eval(Node::Program(program), Rc::clone(&environment))
+        evaluator.attach(Box::new(debugger));
+        match evaluator.eval(&program, Rc::clone(environment)) {
+            Ok(object) => println!("{}", object),
+            Err(err) => println!("{}", err),
+        }
+        evaluator.detach();
+    }
+
+    fn trace(&self, argument: &str, evaluator: &mut Evaluator, tracing: &mut bool) {
+        match (argument, *tracing) {
+            ("on", false) => evaluator.attach(Box::new(Tracer::new(io::stdout()))),
+            ("off", true) => {
+                evaluator.detach();
+            }
+            ("on", true) | ("off", false) => {
+                println!("tracing is already {}", argument);
+                return;
+            }
+            _ => {
+                println!("usage: {} on|off", TRACE_COMMAND);
+                return;
+            }
+        }
+
+        *tracing = argument == "on";
+    }
+
+    fn print_parser_errors(&self, errors: Vec<ParserError>) {
+        println!("Woops! parser got {} errors!", errors.len());
+        for error in errors {
+            println!("{}", error);
+        }
     }
 
     #[allow(dead_code)]
@@ -73,3 +172,36 @@ impl Repl {
         stdin.read_line(input).expect("failed to read line");
     }
 }
+
+/// The code after `command` on `line`, if the line starts with it, with the
+/// location of the char before the code. Locations then count from the start
+/// of the line, leading whitespace included.
+fn command_input<'a>(line: &'a str, command: &str) -> Option<(&'a str, Location)> {
+    let source = line.trim().strip_prefix(command)?;
+    let indent = line.len() - line.trim_start().len();
+
+    Some((
+        source,
+        Location::new(1, line[..indent].chars().count() + command.len()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::location::Location;
+
+    use super::{command_input, DEBUG_COMMAND, TYPE_COMMAND};
+
+    #[test]
+    fn test_command_input() {
+        assert_eq!(
+            command_input(":type 1 + 2\n", TYPE_COMMAND),
+            Some((" 1 + 2", Location::new(1, 5)))
+        );
+        assert_eq!(
+            command_input("  \t:debug f(1)\n", DEBUG_COMMAND),
+            Some((" f(1)", Location::new(1, 9)))
+        );
+        assert_eq!(command_input("let x = 1;\n", TYPE_COMMAND), None);
+    }
+}
@@ -0,0 +1,890 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    lexer::location::Span,
+    parser::{
+        ast::{
+            annotation::TypeAnnotation,
+            expression::{Expression, TemplatePart},
+            operator::{InfixOperator, PrefixOperator},
+            program::Program,
+            statement::{ImportBinding, Statement},
+        },
+        source_map::SourceMap,
+    },
+};
+
+use super::types::Type;
+
+#[derive(Debug, PartialEq)]
+pub struct TypeError {
+    msg: String,
+    span: Option<Span>,
+}
+
+impl TypeError {
+    fn new(msg: impl Into<String>, span: Option<Span>) -> Self {
+        TypeError {
+            msg: msg.into(),
+            span,
+        }
+    }
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "[TypeError:{}] {}", span, self.msg),
+            None => write!(f, "[TypeError] {}", self.msg),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Var {
+    /// Not known yet; the level is the `let` nesting depth the variable
+    /// belongs to, which decides whether it may be generalized.
+    Unbound(usize),
+    Bound(Type),
+}
+
+/// A type with the variables that each use of a binding instantiates anew.
+#[derive(Debug, Clone)]
+struct Scheme {
+    vars: Vec<usize>,
+    ty: Type,
+}
+
+impl Scheme {
+    fn monomorphic(ty: Type) -> Self {
+        Scheme { vars: vec![], ty }
+    }
+}
+
+/// Hindley–Milner type inference over the AST.
+///
+/// Bindings are let-polymorphic: `let id = fn(x) { x };` can be applied to
+/// an `int` and a `bool` in the same program. Typing is stricter than the
+/// evaluator: conditions and `!` take a `bool`, both branches of an `if`
+/// must agree, and an `if` without `else` is `null`.
+///
+/// Top-level bindings stay in the checker, so a program can be checked a
+/// piece at a time, like the REPL does.
+#[derive(Debug, Clone)]
+pub struct TypeChecker {
+    vars: Vec<Var>,
+    /// Only function bodies open a scope, like in the evaluator.
+    scopes: Vec<HashMap<String, Scheme>>,
+    /// Result types of the functions being checked, innermost last.
+    returns: Vec<Type>,
+    level: usize,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        TypeChecker::new()
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        TypeChecker {
+            vars: vec![],
+            scopes: vec![HashMap::new()],
+            returns: vec![],
+            level: 0,
+        }
+    }
+
+    /// Checks every top-level statement, returning the type of the last one
+    /// like the evaluator returns its value. Statements with errors are
+    /// skipped, and their bindings can then hold anything.
+    pub fn check(
+        &mut self,
+        program: &Program,
+        source_map: Option<&SourceMap>,
+    ) -> Result<Type, Vec<TypeError>> {
+        let mut inference = Inference {
+            checker: self,
+            source_map,
+        };
+        let mut errors = vec![];
+        let mut result = Type::Null;
+
+        for statement in &program.statements {
+            match inference.statement(statement) {
+                Ok(ty) => result = ty,
+                Err(err) => {
+                    errors.push(err);
+                    if let Statement::Let { name, .. } = statement {
+                        let any = inference.checker.fresh_var();
+                        inference.checker.bind(
+                            name,
+                            Scheme {
+                                vars: vec![any],
+                                ty: Type::Var(any),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(self.resolve_deep(&result))
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn fresh_var(&mut self) -> usize {
+        self.vars.push(Var::Unbound(self.level));
+        self.vars.len() - 1
+    }
+
+    fn fresh(&mut self) -> Type {
+        Type::Var(self.fresh_var())
+    }
+
+    fn bind(&mut self, name: &str, scheme: Scheme) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), scheme);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Scheme> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+    }
+
+    /// Follows bound variables until a type that is not one.
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(var) => match &self.vars[*var] {
+                Var::Bound(bound) => self.resolve(bound),
+                Var::Unbound(_) => ty.clone(),
+            },
+            _ => ty.clone(),
+        }
+    }
+
+    /// Resolves every variable inside `ty` as well.
+    fn resolve_deep(&self, ty: &Type) -> Type {
+        match self.resolve(ty) {
+            Type::Function(parameters, result) => Type::function(
+                parameters
+                    .iter()
+                    .map(|parameter| self.resolve_deep(parameter))
+                    .collect(),
+                self.resolve_deep(&result),
+            ),
+            Type::Array(element) => Type::array(self.resolve_deep(&element)),
+            ty => ty,
+        }
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> Result<(), ()> {
+        match (self.resolve(a), self.resolve(b)) {
+            (Type::Var(a), Type::Var(b)) if a == b => Ok(()),
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => {
+                let Var::Unbound(level) = self.vars[var] else {
+                    return Err(());
+                };
+                self.occurs(var, level, &ty)?;
+                self.vars[var] = Var::Bound(ty);
+                Ok(())
+            }
+            (Type::Int, Type::Int)
+            | (Type::Bool, Type::Bool)
+            | (Type::String, Type::String)
+            | (Type::Null, Type::Null) => Ok(()),
+            (Type::Function(a_parameters, a_result), Type::Function(b_parameters, b_result))
+                if a_parameters.len() == b_parameters.len() =>
+            {
+                for (a, b) in a_parameters.iter().zip(&b_parameters) {
+                    self.unify(a, b)?;
+                }
+                self.unify(&a_result, &b_result)
+            }
+            (Type::Array(a), Type::Array(b)) => self.unify(&a, &b),
+            _ => Err(()),
+        }
+    }
+
+    /// Fails if `var` appears in `ty`, which would make an infinite type,
+    /// and lowers the level of the variables in `ty` to `level` so they are
+    /// generalized no earlier than `var`.
+    fn occurs(&mut self, var: usize, level: usize, ty: &Type) -> Result<(), ()> {
+        match self.resolve(ty) {
+            Type::Var(other) if other == var => Err(()),
+            Type::Var(other) => {
+                if let Var::Unbound(other_level) = self.vars[other] {
+                    self.vars[other] = Var::Unbound(other_level.min(level));
+                }
+                Ok(())
+            }
+            Type::Function(parameters, result) => {
+                for parameter in &parameters {
+                    self.occurs(var, level, parameter)?;
+                }
+                self.occurs(var, level, &result)
+            }
+            Type::Array(element) => self.occurs(var, level, &element),
+            _ => Ok(()),
+        }
+    }
+
+    /// Quantifies the variables of `ty` created inside the current `let`.
+    fn generalize(&self, ty: &Type) -> Scheme {
+        let ty = self.resolve_deep(ty);
+        let mut vars = vec![];
+        self.collect_vars(&ty, &mut vars);
+        vars.retain(|var| matches!(self.vars[*var], Var::Unbound(level) if level > self.level));
+
+        Scheme { vars, ty }
+    }
+
+    fn collect_vars(&self, ty: &Type, vars: &mut Vec<usize>) {
+        match ty {
+            Type::Var(var) if !vars.contains(var) => vars.push(*var),
+            Type::Function(parameters, result) => {
+                for parameter in parameters {
+                    self.collect_vars(parameter, vars);
+                }
+                self.collect_vars(result, vars);
+            }
+            Type::Array(element) => self.collect_vars(element, vars),
+            _ => {}
+        }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let fresh: HashMap<usize, Type> =
+            scheme.vars.iter().map(|var| (*var, self.fresh())).collect();
+
+        substitute(&scheme.ty, &fresh)
+    }
+}
+
+fn substitute(ty: &Type, fresh: &HashMap<usize, Type>) -> Type {
+    match ty {
+        Type::Var(var) => fresh.get(var).cloned().unwrap_or(Type::Var(*var)),
+        Type::Function(parameters, result) => Type::function(
+            parameters
+                .iter()
+                .map(|parameter| substitute(parameter, fresh))
+                .collect(),
+            substitute(result, fresh),
+        ),
+        Type::Array(element) => Type::array(substitute(element, fresh)),
+        ty => ty.clone(),
+    }
+}
+
+/// One run of the checker over a program, with the spans to report.
+struct Inference<'a> {
+    checker: &'a mut TypeChecker,
+    source_map: Option<&'a SourceMap<'a>>,
+}
+
+impl Inference<'_> {
+    fn span(&self, expression: &Expression) -> Option<Span> {
+        self.source_map?.expression(expression).cloned()
+    }
+
+    /// Unifies the type of `expression` with the type it must have.
+    fn expect(
+        &mut self,
+        expression: &Expression,
+        actual: &Type,
+        expected: &Type,
+    ) -> Result<(), TypeError> {
+        if self.checker.unify(expected, actual).is_ok() {
+            return Ok(());
+        }
+
+        let mut names = vec![];
+        let expected = self.checker.resolve_deep(expected).display_with(&mut names);
+        let actual = self.checker.resolve_deep(actual).display_with(&mut names);
+
+        Err(TypeError::new(
+            format!("expected {}, got {}", expected, actual),
+            self.span(expression),
+        ))
+    }
+
+    /// Type of a list of statements: the type of the last one, `null` when
+    /// there are none, or anything at all after a `return`.
+    fn statements(&mut self, statements: &[Statement]) -> Result<Type, TypeError> {
+        let mut ty = Type::Null;
+
+        for statement in statements {
+            ty = self.statement(statement)?;
+        }
+
+        Ok(ty)
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<Type, TypeError> {
+        match statement {
+            Statement::Let {
+                name,
+                annotation,
+                value,
+            } => self.r#let(name, annotation.as_ref(), value),
+            Statement::Return { value } => {
+                let ty = self.expression(value)?;
+                if let Some(result) = self.checker.returns.last().cloned() {
+                    self.expect(value, &ty, &result)?;
+                }
+                Ok(self.checker.fresh())
+            }
+            Statement::Expression(expression) => self.expression(expression),
+            Statement::Block(statements) => self.statements(statements),
+            // modules are checked on their own, so whatever they export
+            // can be used at any type
+            Statement::Import {
+                binding: ImportBinding::Module(name),
+                ..
+            } => {
+                let scheme = self.unknown();
+                self.checker.bind(name, scheme);
+                Ok(Type::Null)
+            }
+            Statement::Import {
+                binding: ImportBinding::Names(names),
+                ..
+            } => {
+                for name in names {
+                    let scheme = self.unknown();
+                    self.checker.bind(name, scheme);
+                }
+                Ok(Type::Null)
+            }
+            Statement::Export(_) => Ok(Type::Null),
+        }
+    }
+
+    /// The type of a builtin function, fresh for every use. The pairs made
+    /// by `zip` and `group_by` mix types, so their elements can be anything,
+    /// and `len` takes arrays and strings alike.
+    fn builtin(&mut self, name: &str) -> Option<Type> {
+        let a = self.checker.fresh();
+        let b = self.checker.fresh();
+        let array = |ty: &Type| Type::array(ty.clone());
+        let predicate = Type::function(vec![a.clone()], Type::Bool);
+        let string = Type::String;
+
+        let (parameters, result) = match name {
+            "len" => (vec![a.clone()], Type::Int),
+            "first" | "last" => (vec![array(&a)], a.clone()),
+            "rest" | "uniq" => (vec![array(&a)], array(&a)),
+            "push" => (vec![array(&a), a.clone()], array(&a)),
+            "map" => (
+                vec![array(&a), Type::function(vec![a.clone()], b.clone())],
+                array(&b),
+            ),
+            "filter" => (vec![array(&a), predicate], array(&a)),
+            "any" | "all" => (vec![array(&a), predicate], Type::Bool),
+            "reduce" => (
+                vec![
+                    array(&a),
+                    b.clone(),
+                    Type::function(vec![b.clone(), a.clone()], b.clone()),
+                ],
+                b.clone(),
+            ),
+            "sort_by" => (
+                vec![array(&a), Type::function(vec![a.clone()], b.clone())],
+                array(&a),
+            ),
+            "group_by" => (
+                vec![array(&a), Type::function(vec![a.clone()], b.clone())],
+                array(&self.checker.fresh()),
+            ),
+            "zip" => (vec![array(&a), array(&b)], array(&self.checker.fresh())),
+            "flatten" => (vec![array(&array(&a))], array(&a)),
+            "split" => (vec![string.clone(), string.clone()], array(&string)),
+            "join" => (vec![array(&string), string.clone()], string.clone()),
+            "trim" | "upper" | "lower" => (vec![string.clone()], string.clone()),
+            "contains" | "starts_with" => (vec![string.clone(), string.clone()], Type::Bool),
+            "replace" => (vec![string.clone(); 3], string.clone()),
+            "substring" => (vec![string.clone(), Type::Int, Type::Int], string.clone()),
+            "chars" => (vec![string.clone()], array(&string)),
+            "repeat" => (vec![string.clone(), Type::Int], string.clone()),
+            "pad_left" => (
+                vec![string.clone(), Type::Int, string.clone()],
+                string.clone(),
+            ),
+            "to_string" => (vec![a.clone()], string.clone()),
+            "to_int" => (vec![string.clone()], Type::Int),
+            _ => return None,
+        };
+
+        Some(Type::function(parameters, result))
+    }
+
+    /// A scheme every use of which gets a type of its own.
+    fn unknown(&mut self) -> Scheme {
+        self.checker.level += 1;
+        let ty = self.checker.fresh();
+        self.checker.level -= 1;
+
+        self.checker.generalize(&ty)
+    }
+
+    fn r#let(
+        &mut self,
+        name: &str,
+        annotation: Option<&TypeAnnotation>,
+        value: &Expression,
+    ) -> Result<Type, TypeError> {
+        self.checker.level += 1;
+
+        // recursive calls see the function at a fresh monomorphic type, which
+        // inferring the body unifies with its real type; only the result is
+        // generalized, so a function cannot call itself at another type
+        let ty = if let Expression::Function { .. } = value {
+            let recursive = self.checker.fresh();
+            self.checker
+                .bind(name, Scheme::monomorphic(recursive.clone()));
+            let ty = self.expression(value);
+            ty.and_then(|ty| self.expect(value, &ty, &recursive).map(|_| ty))
+        } else {
+            self.expression(value)
+        };
+        let ty = match annotation {
+            Some(annotation) => ty.and_then(|ty| {
+                let annotated = Type::from(annotation);
+                self.expect(value, &ty, &annotated).map(|_| annotated)
+            }),
+            None => ty,
+        };
+
+        self.checker.level -= 1;
+        let ty = ty?;
+
+        let scheme = self.checker.generalize(&ty);
+        self.checker.bind(name, scheme);
+
+        Ok(ty)
+    }
+
+    /// The annotated type, or a fresh variable when there is none.
+    fn annotated(&mut self, annotation: Option<&TypeAnnotation>) -> Type {
+        match annotation {
+            Some(annotation) => Type::from(annotation),
+            None => self.checker.fresh(),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<Type, TypeError> {
+        match expression {
+            Expression::Int(_) => Ok(Type::Int),
+            Expression::Bool(_) => Ok(Type::Bool),
+            Expression::String(_) => Ok(Type::String),
+            Expression::Null => Ok(Type::Null),
+            Expression::Identifier(name) => match self.checker.lookup(name) {
+                Some(scheme) => Ok(self.checker.instantiate(&scheme)),
+                None => self.builtin(name).ok_or_else(|| {
+                    TypeError::new(
+                        format!("identifier not found: {}", name),
+                        self.span(expression),
+                    )
+                }),
+            },
+            Expression::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let ty = self.expression(condition)?;
+                self.expect(condition, &ty, &Type::Bool)?;
+
+                let consequence_ty = self.statements(consequence)?;
+                let Some(alternative) = alternative else {
+                    return Ok(Type::Null);
+                };
+                let alternative_ty = self.statements(alternative)?;
+
+                self.expect(expression, &alternative_ty, &consequence_ty)?;
+                Ok(consequence_ty)
+            }
+            Expression::Function {
+                parameters,
+                result,
+                body,
+            } => {
+                let parameter_tys: Vec<Type> = parameters
+                    .iter()
+                    .map(|parameter| self.annotated(parameter.annotation.as_ref()))
+                    .collect();
+                let locals = parameters
+                    .iter()
+                    .zip(&parameter_tys)
+                    .map(|(parameter, ty)| {
+                        (parameter.name.clone(), Scheme::monomorphic(ty.clone()))
+                    })
+                    .collect();
+                let result = self.annotated(result.as_ref());
+
+                self.checker.scopes.push(locals);
+                self.checker.returns.push(result.clone());
+                let body_ty = self.statements(body);
+                self.checker.returns.pop();
+                self.checker.scopes.pop();
+
+                let last = match body.last() {
+                    Some(Statement::Expression(last)) => last,
+                    Some(Statement::Let { value, .. }) => value,
+                    _ => expression,
+                };
+                self.expect(last, &body_ty?, &result)?;
+
+                Ok(Type::function(parameter_tys, result))
+            }
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                let function_ty = self.expression(function)?;
+                let argument_tys = arguments
+                    .iter()
+                    .map(|argument| self.expression(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                match self.checker.resolve(&function_ty) {
+                    Type::Function(parameters, result) => {
+                        if parameters.len() != arguments.len() {
+                            return Err(TypeError::new(
+                                format!(
+                                    "wrong number of arguments: got {}, but function wants {}",
+                                    arguments.len(),
+                                    parameters.len()
+                                ),
+                                self.span(expression),
+                            ));
+                        }
+                        for ((argument, ty), parameter) in
+                            arguments.iter().zip(&argument_tys).zip(&parameters)
+                        {
+                            self.expect(argument, ty, parameter)?;
+                        }
+                        Ok(*result)
+                    }
+                    _ => {
+                        let result = self.checker.fresh();
+                        self.expect(
+                            function,
+                            &function_ty,
+                            &Type::function(argument_tys, result.clone()),
+                        )?;
+                        Ok(result)
+                    }
+                }
+            }
+            Expression::Member { object, .. } => {
+                self.expression(object)?;
+                Ok(self.checker.fresh())
+            }
+            Expression::Array(elements) => {
+                let element = self.checker.fresh();
+                for expression in elements {
+                    let ty = self.expression(expression)?;
+                    self.expect(expression, &ty, &element)?;
+                }
+                Ok(Type::array(element))
+            }
+            Expression::Template(parts) => {
+                for part in parts {
+                    if let TemplatePart::Expression(expression) = part {
+                        self.expression(expression)?;
+                    }
+                }
+                Ok(Type::String)
+            }
+            Expression::Index { left, index } => {
+                let left_ty = self.expression(left)?;
+                let element = match self.checker.resolve(&left_ty) {
+                    Type::String => Type::String,
+                    _ => {
+                        let element = self.checker.fresh();
+                        self.expect(left, &left_ty, &Type::array(element.clone()))?;
+                        element
+                    }
+                };
+                let index_ty = self.expression(index)?;
+                self.expect(index, &index_ty, &Type::Int)?;
+                Ok(element)
+            }
+            Expression::Prefix { rhs, operator } => {
+                let ty = self.expression(rhs)?;
+                let operand = match operator {
+                    PrefixOperator::Not => Type::Bool,
+                    PrefixOperator::Negative => Type::Int,
+                };
+                self.expect(rhs, &ty, &operand)?;
+                Ok(operand)
+            }
+            Expression::Infix { lhs, operator, rhs } => {
+                let lhs_ty = self.expression(lhs)?;
+                let rhs_ty = self.expression(rhs)?;
+
+                match operator {
+                    // `+` joins strings when either side is known to be one
+                    InfixOperator::Add
+                        if self.checker.resolve(&lhs_ty) == Type::String
+                            || self.checker.resolve(&rhs_ty) == Type::String =>
+                    {
+                        self.expect(lhs, &lhs_ty, &Type::String)?;
+                        self.expect(rhs, &rhs_ty, &Type::String)?;
+                        Ok(Type::String)
+                    }
+                    InfixOperator::Add
+                    | InfixOperator::Sub
+                    | InfixOperator::Mult
+                    | InfixOperator::Div
+                    | InfixOperator::Modulo => {
+                        self.expect(lhs, &lhs_ty, &Type::Int)?;
+                        self.expect(rhs, &rhs_ty, &Type::Int)?;
+                        Ok(Type::Int)
+                    }
+                    InfixOperator::GreaterThan | InfixOperator::LessThan => {
+                        self.expect(lhs, &lhs_ty, &Type::Int)?;
+                        self.expect(rhs, &rhs_ty, &Type::Int)?;
+                        Ok(Type::Bool)
+                    }
+                    InfixOperator::Equal | InfixOperator::NotEqual => {
+                        self.expect(rhs, &rhs_ty, &lhs_ty)?;
+                        Ok(Type::Bool)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{lexer::lexer::Lexer, parser::parser::Parser};
+
+    use super::TypeChecker;
+
+    fn infer(input: &str) -> Result<String, Vec<String>> {
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+        assert_eq!(parser.errors.len(), 0);
+
+        TypeChecker::new()
+            .check(&program, Some(&parser.source_map(&program)))
+            .map(|ty| ty.to_string())
+            .map_err(|errors| errors.iter().map(|error| error.to_string()).collect())
+    }
+
+    #[test]
+    fn test_infer_types() {
+        let tests = vec![
+            ("1 + 2 * 3", "int"),
+            ("1 < 2", "bool"),
+            ("!true", "bool"),
+            ("null", "null"),
+            ("", "null"),
+            ("fn(x) { x }", "fn(a) -> a"),
+            ("fn(x, y) { x + y }", "fn(int, int) -> int"),
+            ("fn(f, x) { f(f(x)) }", "fn(fn(a) -> a, a) -> a"),
+            ("fn(x) { fn(y) { x == y } }", "fn(a) -> fn(a) -> bool"),
+            ("fn() { return 1; }", "fn() -> int"),
+            ("fn(x) { if (x) { 1 } }", "fn(bool) -> null"),
+            (
+                "let add = fn(a) { fn(b) { a + b } }; add(1)",
+                "fn(int) -> int",
+            ),
+            ("let x = 5; let y = x > 2; y", "bool"),
+            ("[1, 2][0]", "int"),
+            ("[[true]]", "[[bool]]"),
+            ("[]", "[a]"),
+            ("fn(a) { a[0] + 1 }", "fn([int]) -> int"),
+            ("map([1, 2], fn(x) { x > 1 })", "[bool]"),
+            ("reduce([1, 2], true, fn(acc, x) { acc })", "bool"),
+            ("flatten([[1], []])", "[int]"),
+            ("first", "fn([a]) -> a"),
+            ("let len = 1; len", "int"),
+            (r#""a" + "b""#, "string"),
+            (r#""abc"[0]"#, "string"),
+            (r#"fn(s) { s + "!" }"#, "fn(string) -> string"),
+            (r#"len("abc") + len([1])"#, "int"),
+            (r#"fn(x) { "x is ${x}" }"#, "fn(a) -> string"),
+            (r#"split(upper("a b"), " ")"#, "[string]"),
+            (
+                "fn(n) { pad_left(to_string(n), 3, \"0\") }",
+                "fn(a) -> string",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                infer(input),
+                Ok(expected.to_string()),
+                "inferring {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_let_polymorphism() {
+        let input = indoc! {"
+            let id = fn(x) { x };
+            let compose = fn(f, g) { fn(x) { f(g(x)) } };
+            let twice = fn(f) { compose(f, f) };
+            if (id(true)) { twice(fn(n) { n * 2 })(id(3)) } else { 0 }
+        "};
+
+        assert_eq!(infer(input), Ok("int".to_string()));
+    }
+
+    #[test]
+    fn test_recursion_and_returns() {
+        let input = indoc! {"
+            let factorial = fn(n) {
+                if (n == 0) {
+                    return 1;
+                }
+                n * factorial(n - 1)
+            };
+            factorial
+        "};
+
+        assert_eq!(infer(input), Ok("fn(int) -> int".to_string()));
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let tests = vec![
+            ("1 + true", "[TypeError:@1:5-1:8] expected int, got bool"),
+            (
+                "if (1 < 2) { 1 } else { false }",
+                "[TypeError:@1:1-1:31] expected int, got bool",
+            ),
+            (
+                "if (1) { 2 }",
+                "[TypeError:@1:5-1:5] expected bool, got int",
+            ),
+            (
+                "let f = fn(a, b) { a }; f(1)",
+                "[TypeError:@1:25-1:28] wrong number of arguments: got 1, but function wants 2",
+            ),
+            (
+                "5(1)",
+                "[TypeError:@1:1-1:1] expected fn(int) -> a, got int",
+            ),
+            (
+                "fn(x) { x(x) }",
+                "[TypeError:@1:9-1:9] expected fn(a) -> b, got a",
+            ),
+            (
+                "fn(x) { if (x) { return 1; } true }",
+                "[TypeError:@1:30-1:33] expected int, got bool",
+            ),
+            ("y + 1", "[TypeError:@1:1-1:1] identifier not found: y"),
+            ("[1, true]", "[TypeError:@1:5-1:8] expected int, got bool"),
+            (
+                r#""a" + 1"#,
+                "[TypeError:@1:7-1:7] expected string, got int",
+            ),
+            (
+                r#"substring("abc", 0, "1")"#,
+                "[TypeError:@1:21-1:23] expected int, got string",
+            ),
+            ("[1][true]", "[TypeError:@1:5-1:8] expected int, got bool"),
+            (
+                r#""sum: ${1 + true}""#,
+                "[TypeError:@1:13-1:16] expected int, got bool",
+            ),
+            ("1[0]", "[TypeError:@1:1-1:1] expected [a], got int"),
+            (
+                "filter([1], fn(x) { x })",
+                "[TypeError:@1:13-1:23] expected fn(int) -> bool, got fn(int) -> int",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                infer(input),
+                Err(vec![expected.to_string()]),
+                "inferring {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_annotations() {
+        let tests = vec![
+            ("fn(x: int) { x }", Ok("fn(int) -> int")),
+            ("fn(f) -> bool { f(1) }", Ok("fn(fn(int) -> bool) -> bool")),
+            (
+                "let id: fn(int) -> int = fn(x) { x }; id",
+                Ok("fn(int) -> int"),
+            ),
+            (
+                "let x: bool = 1;",
+                Err("[TypeError:@1:15-1:15] expected bool, got int"),
+            ),
+            (
+                "fn(x: bool) -> int { x }",
+                Err("[TypeError:@1:22-1:22] expected int, got bool"),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                infer(input),
+                expected
+                    .map(|ty| ty.to_string())
+                    .map_err(|error| vec![error.to_string()]),
+                "inferring {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_errors_do_not_cascade() {
+        let input = indoc! {"
+            let x = 1 + true;
+            let y = x(1) + 2;
+            let z = -false;
+        "};
+
+        assert_eq!(
+            infer(input),
+            Err(vec![
+                "[TypeError:@1:13-1:16] expected int, got bool".to_string(),
+                "[TypeError:@3:10-3:14] expected int, got bool".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_bindings_persist_between_checks() {
+        let mut checker = TypeChecker::new();
+
+        for (input, expected) in [
+            ("let pair = fn(a, b) { a };", "fn(a, b) -> a"),
+            ("pair(1, true)", "int"),
+            ("pair", "fn(a, b) -> a"),
+        ] {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program();
+            let ty = checker.check(&program, None).unwrap();
+
+            assert_eq!(ty.to_string(), expected);
+        }
+    }
+}
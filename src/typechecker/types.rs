@@ -0,0 +1,118 @@
+use std::fmt::Display;
+
+use crate::parser::ast::annotation::TypeAnnotation;
+
+/// A type inferred by the type checker.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Type {
+    Int,
+    Bool,
+    String,
+    Null,
+    /// A type variable, standing for any type.
+    Var(usize),
+    /// A function taking the parameter types and returning the last type.
+    Function(Vec<Type>, Box<Type>),
+    /// An array of elements of the same type.
+    Array(Box<Type>),
+}
+
+impl Type {
+    pub fn function(parameters: Vec<Type>, result: Type) -> Self {
+        Type::Function(parameters, Box::new(result))
+    }
+
+    pub fn array(element: Type) -> Self {
+        Type::Array(Box::new(element))
+    }
+
+    /// Renders the type, naming its variables `a`, `b`, ... in order of
+    /// appearance. Sharing `names` between calls keeps the names consistent
+    /// across several types.
+    pub fn display_with(&self, names: &mut Vec<usize>) -> String {
+        match self {
+            Type::Int => "int".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::String => "string".to_string(),
+            Type::Null => "null".to_string(),
+            Type::Var(var) => {
+                let index = match names.iter().position(|name| name == var) {
+                    Some(index) => index,
+                    None => {
+                        names.push(*var);
+                        names.len() - 1
+                    }
+                };
+                var_name(index)
+            }
+            Type::Function(parameters, result) => {
+                let parameters: Vec<String> = parameters
+                    .iter()
+                    .map(|parameter| parameter.display_with(names))
+                    .collect();
+                format!(
+                    "fn({}) -> {}",
+                    parameters.join(", "),
+                    result.display_with(names)
+                )
+            }
+            Type::Array(element) => format!("[{}]", element.display_with(names)),
+        }
+    }
+}
+
+impl From<&TypeAnnotation> for Type {
+    fn from(annotation: &TypeAnnotation) -> Self {
+        match annotation {
+            TypeAnnotation::Int => Type::Int,
+            TypeAnnotation::Bool => Type::Bool,
+            TypeAnnotation::String => Type::String,
+            TypeAnnotation::Null => Type::Null,
+            TypeAnnotation::Function { parameters, result } => Type::function(
+                parameters.iter().map(Type::from).collect(),
+                Type::from(result.as_ref()),
+            ),
+        }
+    }
+}
+
+fn var_name(index: usize) -> String {
+    match u8::try_from(index) {
+        Ok(index) if index < 26 => char::from(b'a' + index).to_string(),
+        _ => format!("t{}", index),
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display_with(&mut vec![]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Type;
+
+    #[test]
+    fn test_display() {
+        let tests = vec![
+            (Type::Int, "int"),
+            (Type::function(vec![], Type::Null), "fn() -> null"),
+            (
+                Type::function(vec![Type::Var(7), Type::Bool], Type::Var(7)),
+                "fn(a, bool) -> a",
+            ),
+            (
+                Type::function(
+                    vec![Type::function(vec![Type::Var(3)], Type::Var(1))],
+                    Type::function(vec![Type::Var(3)], Type::Var(1)),
+                ),
+                "fn(fn(a) -> b) -> fn(a) -> b",
+            ),
+        ];
+
+        for (ty, expected) in tests {
+            assert_eq!(ty.to_string(), expected);
+        }
+    }
+}